@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use sudoku_variants::{
+    solver::{BacktrackingSolver, Solution, Solver},
+    Sudoku,
+};
+
+use crate::{
+    annotation::{HighlightKind, Highlights},
+    board::{Cell, CellPosition, ValueEntered},
+    events::{NewGame, Reset},
+    game::AssistUsage,
+    history::{BoardSnapshot, History, Move, Undo},
+    ui::{FontAssets, Theme, ThickLine},
+    variant::VariantRules,
+    SudokuSolution,
+};
+
+pub struct AnalysisPlugin;
+
+impl Plugin for AnalysisPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FindWrongMove>()
+            .init_resource::<WrongMove>()
+            .add_system(find_wrong_move_events)
+            .add_system(wrong_move_button_system)
+            .add_system(clear_wrong_move);
+    }
+}
+
+/// Event to look back through the history for the move that made the board unsolvable
+pub struct FindWrongMove;
+
+/// Resource for the last analysis, the cell it points at stays highlighted until the next move
+#[derive(Resource, Default)]
+pub struct WrongMove(pub Option<WrongMoveReport>);
+
+#[derive(Debug, Copy, Clone)]
+pub struct WrongMoveReport {
+    /// Index of the move in the history, oldest first
+    pub index: usize,
+    pub moves: usize,
+    pub pos: CellPosition,
+}
+
+/// Whether the values in a snapshot can still be completed
+pub fn is_solvable(
+    snapshot: &BoardSnapshot,
+    solution: &SudokuSolution,
+    rules: &VariantRules,
+) -> bool {
+    // a generated puzzle has a single solution, so comparing against it is enough
+    if solution.0.is_some() {
+        return (0..9).all(|y| {
+            (0..9).all(|x| match snapshot.0[y][x].value {
+                Some(value) => solution.get(x, y) == Some(value),
+                None => true,
+            })
+        });
+    }
+
+    let mut sudoku = Sudoku::new_empty(3, 3, rules.constraint()).unwrap();
+    let grid = sudoku.grid_mut();
+    for (y, row) in snapshot.0.iter().enumerate() {
+        for (x, state) in row.iter().enumerate() {
+            if let Some(value) = state.value {
+                grid.set_cell(x, y, value.into()).unwrap();
+            }
+        }
+    }
+    if !sudoku.is_valid() {
+        return false;
+    }
+    !matches!(BacktrackingSolver.solve(&sudoku), Solution::Impossible)
+}
+
+/// Index of the move that started the current dead end, None if the board is solvable or was never solvable
+pub fn first_wrong_move(
+    moves: &[Move],
+    current: &BoardSnapshot,
+    solution: &SudokuSolution,
+    rules: &VariantRules,
+) -> Option<usize> {
+    if is_solvable(current, solution, rules) {
+        return None;
+    }
+    let mut state = *current;
+    for (i, step) in moves.iter().enumerate().rev() {
+        for change in step.changes.iter() {
+            state.0[change.pos.y][change.pos.x] = change.before;
+        }
+        if is_solvable(&state, solution, rules) {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// The cell a move went wrong in, preferring a value that disagrees with the solution
+fn offending_cell(step: &Move, solution: &SudokuSolution) -> Option<CellPosition> {
+    let placed = step
+        .changes
+        .iter()
+        .filter(|change| change.after.value.is_some() && change.after.value != change.before.value);
+    let wrong = placed.clone().find(|change| match solution.get(change.pos.x, change.pos.y) {
+        Some(expected) => change.after.value != Some(expected),
+        None => false,
+    });
+    wrong
+        .or_else(|| placed.clone().next())
+        .or_else(|| step.changes.first())
+        .map(|change| change.pos)
+}
+
+#[derive(Component)]
+struct WrongMovePanel;
+
+#[derive(Component, Debug, Copy, Clone)]
+enum WrongMoveButton {
+    Rewind,
+    Close,
+}
+
+fn find_wrong_move_events(
+    mut commands: Commands,
+    mut find_events: EventReader<FindWrongMove>,
+    history: Res<History>,
+    solution: Res<SudokuSolution>,
+    rules: Res<VariantRules>,
+    font_assets: Res<FontAssets>,
+    theme: Res<Theme>,
+    mut wrong_move: ResMut<WrongMove>,
+    mut highlights: ResMut<Highlights>,
+    mut assists: ResMut<AssistUsage>,
+    panel_query: Query<Entity, With<WrongMovePanel>>,
+    query: Query<(&Cell, &CellPosition)>,
+) {
+    for _ in find_events.iter() {
+        assists.wrong_moves += 1;
+        for panel in panel_query.iter() {
+            commands.entity(panel).despawn_recursive();
+        }
+
+        let current = BoardSnapshot::new(query.iter());
+        let moves = history.moves();
+        let report = first_wrong_move(moves, &current, &solution, &rules).and_then(|index| {
+            offending_cell(&moves[index], &solution).map(|pos| WrongMoveReport {
+                index,
+                moves: moves.len(),
+                pos,
+            })
+        });
+
+        let message = match report {
+            Some(report) => format!(
+                "Move {} of {} led to a dead end at row {}, column {}",
+                report.index + 1,
+                report.moves,
+                report.pos.y + 1,
+                report.pos.x + 1
+            ),
+            None if is_solvable(&current, &solution, &rules) => "The board can still be solved".to_string(),
+            None => "No move to rewind, the puzzle can't be solved".to_string(),
+        };
+        wrong_move.0 = report;
+        highlights.set(HighlightKind::WrongMove, report.map(|report| report.pos));
+
+        spawn_wrong_move_panel(&mut commands, &theme, &font_assets, message, report.is_some());
+    }
+}
+
+fn spawn_wrong_move_panel(
+    commands: &mut Commands,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    message: String,
+    can_rewind: bool,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(WrongMovePanel)
+        .insert(Name::new("Wrong Move"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: theme.line_thick.into(),
+                    ..default()
+                })
+                .insert(ThickLine)
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(10.0)),
+                                margin: UiRect::all(Val::Px(2.0)),
+                                ..default()
+                            },
+                            background_color: theme.btn_normal.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle {
+                                text: font_assets.status(message, theme),
+                                ..default()
+                            });
+                        });
+
+                    let buttons: &[(WrongMoveButton, &str)] = if can_rewind {
+                        &[
+                            (WrongMoveButton::Rewind, "Rewind"),
+                            (WrongMoveButton::Close, "Close"),
+                        ]
+                    } else {
+                        &[(WrongMoveButton::Close, "Close")]
+                    };
+                    for (button, label) in buttons {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                                        margin: UiRect::all(Val::Px(2.0)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: theme.btn_normal.into(),
+                                    ..default()
+                                },
+                                Name::new(label.to_string()),
+                                *button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle {
+                                    text: font_assets.status(*label, theme),
+                                    ..default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+/// Rewinding undoes every move from the wrong one on, so it can be redone
+fn wrong_move_button_system(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &WrongMoveButton), (Changed<Interaction>, With<Button>)>,
+    panel_query: Query<Entity, With<WrongMovePanel>>,
+    wrong_move: Res<WrongMove>,
+    mut undo_event: EventWriter<Undo>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        if let (WrongMoveButton::Rewind, Some(report)) = (button, wrong_move.0) {
+            for _ in report.index..report.moves {
+                undo_event.send(Undo);
+            }
+        }
+        for panel in panel_query.iter() {
+            commands.entity(panel).despawn_recursive();
+        }
+    }
+}
+
+fn clear_wrong_move(
+    mut value_entered: EventReader<ValueEntered>,
+    mut new_game: EventReader<NewGame>,
+    mut reset_event: EventReader<Reset>,
+    mut wrong_move: ResMut<WrongMove>,
+    mut highlights: ResMut<Highlights>,
+) {
+    let cleared = value_entered.iter().count() > 0
+        || new_game.iter().count() > 0
+        || reset_event.iter().count() > 0;
+    if cleared && wrong_move.0.is_some() {
+        wrong_move.0 = None;
+        highlights.clear(HighlightKind::WrongMove);
+    }
+}
@@ -0,0 +1,386 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{cell_center, BoardSelect, Cell, CellPosition, EntryMode, EntryModeButton},
+    events::{NewGame, Reset},
+    game::GameStatus,
+    history::History,
+    ui::Theme,
+};
+
+pub struct AnnotationPlugin;
+
+impl Plugin for AnnotationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Annotations>()
+            .init_resource::<Highlights>()
+            .init_resource::<DrawTool>()
+            .init_resource::<DrawAnchor>()
+            .add_system(draw_select_events)
+            .add_system(clear_draw_anchor)
+            .add_system(clear_annotations)
+            .add_system(update_draw_button_label)
+            .add_system(render_annotations);
+    }
+}
+
+/// A mark drawn over the board, anchored to cell centers
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Annotation {
+    Line { from: CellPosition, to: CellPosition },
+    Arrow { from: CellPosition, to: CellPosition },
+    Circle(CellPosition),
+}
+
+/// Resource for the annotations drawn this game
+#[derive(Resource, Default, Debug, Clone)]
+pub struct Annotations(pub Vec<Annotation>);
+
+impl Annotations {
+    /// Add the annotation, or remove it if it is already drawn, returns true if added
+    pub fn toggle(&mut self, annotation: Annotation) -> bool {
+        match self.0.iter().position(|a| *a == annotation) {
+            Some(index) => {
+                self.0.remove(index);
+                false
+            }
+            None => {
+                self.0.push(annotation);
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, annotation: Annotation) {
+        self.0.retain(|a| *a != annotation);
+    }
+}
+
+/// Why a cell is outlined on the overlay
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HighlightKind {
+    /// Differs from the checkpoint being compared against
+    Compare,
+    /// Where the move that led to a dead end was made
+    WrongMove,
+    /// Empty cell with exactly two candidates
+    BiValue,
+    /// Only place for a value in one of its units
+    HiddenSingle,
+    /// Cells forming the logic step being previewed
+    LogicPattern,
+    /// Cells the previewed logic step removes candidates from
+    LogicElimination,
+    /// Cells the current tutorial step points at
+    Lesson,
+}
+
+impl HighlightKind {
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            HighlightKind::Compare => theme.compare,
+            HighlightKind::WrongMove => theme.text_error,
+            HighlightKind::BiValue => theme.bi_value,
+            HighlightKind::HiddenSingle => theme.hidden_single,
+            HighlightKind::LogicPattern => theme.logic_pattern,
+            HighlightKind::LogicElimination => theme.logic_elimination,
+            HighlightKind::Lesson => theme.logic_pattern,
+        }
+    }
+}
+
+/// Resource for cells outlined by other features, each owning its kind
+#[derive(Resource, Default)]
+pub struct Highlights(Vec<(HighlightKind, CellPosition)>);
+
+impl Highlights {
+    /// Replace the highlights of one kind
+    pub fn set(&mut self, kind: HighlightKind, positions: impl IntoIterator<Item = CellPosition>) {
+        self.0.retain(|(k, _)| *k != kind);
+        self.0.extend(positions.into_iter().map(|pos| (kind, pos)));
+    }
+
+    pub fn clear(&mut self, kind: HighlightKind) {
+        self.set(kind, []);
+    }
+}
+
+/// Resource for what [`EntryMode::Draw`] draws
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawTool {
+    Line,
+    Arrow,
+    Circle,
+}
+
+impl Default for DrawTool {
+    fn default() -> Self {
+        Self::Line
+    }
+}
+
+impl DrawTool {
+    /// Tool to cycle to, None after the last one
+    pub fn next(self) -> Option<DrawTool> {
+        match self {
+            DrawTool::Line => Some(DrawTool::Arrow),
+            DrawTool::Arrow => Some(DrawTool::Circle),
+            DrawTool::Circle => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            DrawTool::Line => "Line",
+            DrawTool::Arrow => "Arrow",
+            DrawTool::Circle => "Circle",
+        }
+    }
+}
+
+/// Resource for the first cell of a line or arrow waiting on its second
+#[derive(Resource, Default)]
+pub struct DrawAnchor(pub Option<CellPosition>);
+
+/// Overlay covering the board that annotations are drawn into
+#[derive(Component)]
+pub struct AnnotationLayer;
+
+/// Spawned last so it renders above the cells, focus passes through to them
+pub fn create_annotation_layer(parent: &mut ChildBuilder) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(0.0),
+                        top: Val::Px(0.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            Name::new("Annotations"),
+            AnnotationLayer,
+        ))
+        .insert(FocusPolicy::Pass);
+}
+
+fn draw_select_events(
+    mut select_events: EventReader<BoardSelect>,
+    entry_mode: Res<EntryMode>,
+    draw_tool: Res<DrawTool>,
+    game_status: Res<GameStatus>,
+    mut anchor: ResMut<DrawAnchor>,
+    mut annotations: ResMut<Annotations>,
+    mut history: ResMut<History>,
+    query: Query<&CellPosition>,
+) {
+    for event in select_events.iter() {
+        if *entry_mode != EntryMode::Draw || *game_status != GameStatus::Playing {
+            continue;
+        }
+        let pos = match query.get(event.0) {
+            Ok(pos) => *pos,
+            Err(_) => continue,
+        };
+
+        let annotation = match (*draw_tool, anchor.0) {
+            (DrawTool::Circle, _) => Annotation::Circle(pos),
+            // selecting the anchor again lets go of it
+            (_, Some(from)) if from == pos => {
+                anchor.0 = None;
+                continue;
+            }
+            (_, None) => {
+                anchor.0 = Some(pos);
+                continue;
+            }
+            (DrawTool::Line, Some(from)) => Annotation::Line { from, to: pos },
+            (DrawTool::Arrow, Some(from)) => Annotation::Arrow { from, to: pos },
+        };
+        anchor.0 = None;
+
+        // drawing the same annotation again erases it
+        let added = annotations.toggle(annotation);
+        history.record_annotation(annotation, added);
+    }
+}
+
+fn clear_draw_anchor(
+    entry_mode: Res<EntryMode>,
+    draw_tool: Res<DrawTool>,
+    mut anchor: ResMut<DrawAnchor>,
+) {
+    if (entry_mode.is_changed() || draw_tool.is_changed()) && anchor.0.is_some() {
+        anchor.0 = None;
+    }
+}
+
+fn clear_annotations(
+    mut new_game: EventReader<NewGame>,
+    mut reset_event: EventReader<Reset>,
+    mut annotations: ResMut<Annotations>,
+    mut anchor: ResMut<DrawAnchor>,
+) {
+    if new_game.iter().count() > 0 || reset_event.iter().count() > 0 {
+        annotations.0.clear();
+        anchor.0 = None;
+    }
+}
+
+/// The draw button shows the current tool while drawing
+fn update_draw_button_label(
+    entry_mode: Res<EntryMode>,
+    draw_tool: Res<DrawTool>,
+    button_query: Query<(&EntryModeButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    if !entry_mode.is_changed() && !draw_tool.is_changed() {
+        return;
+    }
+    let label = match *entry_mode {
+        EntryMode::Draw => draw_tool.label(),
+        _ => "Draw",
+    };
+    for (button, children) in button_query.iter() {
+        if button.0 != EntryMode::Draw {
+            continue;
+        }
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value = label.to_string();
+            }
+        }
+    }
+}
+
+/// Rebuild the overlay when annotations change, or cells are resized by `resize_board_cells`
+fn render_annotations(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    annotations: Res<Annotations>,
+    highlights: Res<Highlights>,
+    anchor: Res<DrawAnchor>,
+    layer_query: Query<Entity, With<AnnotationLayer>>,
+    added_layer_query: Query<(), Added<AnnotationLayer>>,
+    resized_query: Query<(), (With<Cell>, Changed<Style>)>,
+    cell_query: Query<&Style, With<Cell>>,
+) {
+    if !annotations.is_changed()
+        && !highlights.is_changed()
+        && !anchor.is_changed()
+        && !theme.is_changed()
+        && added_layer_query.is_empty()
+        && resized_query.is_empty()
+    {
+        return;
+    }
+    let cell_size = match cell_query.iter().next().map(|style| style.size.width) {
+        Some(Val::Px(size)) => size,
+        _ => return,
+    };
+
+    let thickness = (cell_size * 0.06).max(2.0);
+    for layer in layer_query.iter() {
+        commands.entity(layer).despawn_descendants();
+        commands.entity(layer).with_children(|parent| {
+            for (kind, pos) in highlights.0.iter() {
+                spawn_outline(parent, cell_center(*pos, cell_size), cell_size * 0.5 - 3.0, thickness, kind.color(&theme));
+            }
+
+            for annotation in annotations.0.iter() {
+                match *annotation {
+                    Annotation::Line { from, to } => spawn_segment(
+                        parent,
+                        cell_center(from, cell_size),
+                        cell_center(to, cell_size),
+                        thickness,
+                        theme.annotation,
+                    ),
+                    Annotation::Arrow { from, to } => {
+                        let start = cell_center(from, cell_size);
+                        let end = cell_center(to, cell_size);
+                        spawn_segment(parent, start, end, thickness, theme.annotation);
+
+                        // two short strokes back from the tip make the head
+                        let back = (start - end).normalize_or_zero() * cell_size * 0.3;
+                        for angle in [PI / 6.0, -PI / 6.0] {
+                            let side = Vec2::from_angle(angle).rotate(back);
+                            spawn_segment(parent, end, end + side, thickness, theme.annotation);
+                        }
+                    }
+                    Annotation::Circle(pos) => spawn_circle(
+                        parent,
+                        cell_center(pos, cell_size),
+                        cell_size * 0.4,
+                        thickness,
+                        theme.annotation,
+                    ),
+                }
+            }
+
+            // mark where a pending line or arrow starts
+            if let Some(pos) = anchor.0 {
+                let mut color = theme.annotation;
+                color.set_a(color.a() * 0.5);
+                spawn_circle(parent, cell_center(pos, cell_size), cell_size * 0.15, thickness, color);
+            }
+        });
+    }
+}
+
+fn spawn_outline(parent: &mut ChildBuilder, center: Vec2, half_size: f32, thickness: f32, color: Color) {
+    let corners = [
+        Vec2::new(-half_size, -half_size),
+        Vec2::new(half_size, -half_size),
+        Vec2::new(half_size, half_size),
+        Vec2::new(-half_size, half_size),
+    ];
+    for i in 0..corners.len() {
+        let from = center + corners[i];
+        let to = center + corners[(i + 1) % corners.len()];
+        spawn_segment(parent, from, to, thickness, color);
+    }
+}
+
+const CIRCLE_SEGMENTS: usize = 24;
+
+pub fn spawn_circle(parent: &mut ChildBuilder, center: Vec2, radius: f32, thickness: f32, color: Color) {
+    let point = |i: usize| center + Vec2::from_angle(i as f32 * TAU / CIRCLE_SEGMENTS as f32) * radius;
+    for i in 0..CIRCLE_SEGMENTS {
+        spawn_segment(parent, point(i), point(i + 1), thickness, color);
+    }
+}
+
+/// UI nodes can't draw lines, so each stroke is a thin node rotated about its center
+pub fn spawn_segment(parent: &mut ChildBuilder, from: Vec2, to: Vec2, thickness: f32, color: Color) {
+    let delta = to - from;
+    let length = delta.length();
+    let center = (from + to) * 0.5;
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(center.x - length * 0.5),
+                    top: Val::Px(center.y - thickness * 0.5),
+                    ..default()
+                },
+                size: Size::new(Val::Px(length), Val::Px(thickness)),
+                ..default()
+            },
+            background_color: color.into(),
+            // layout only sets translation, so the rotation is kept, y points up when rendering
+            transform: Transform::from_rotation(Quat::from_rotation_z(-delta.y.atan2(delta.x))),
+            ..default()
+        })
+        .insert(FocusPolicy::Pass);
+}
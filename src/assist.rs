@@ -0,0 +1,240 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use sudoku_variants::SudokuGrid;
+
+use crate::{
+    board::{BoardSelected, Cell, CellPosition, Notes, Value, ValueOrigin},
+    history::{BoardSnapshot, History},
+    save::restore_saved_game,
+    settings::{InputMode, Preferences},
+    AppState,
+};
+
+pub struct AssistPlugin;
+
+impl Plugin for AssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LegalDigits>()
+            // a resumed game keeps its own notes
+            .add_system(auto_candidates_system.before(restore_saved_game))
+            .add_system(update_legal_digits);
+    }
+}
+
+/// Assists the player picks in settings, applied as part of each move
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssistSettings {
+    /// Start with every empty cell's notes filled, then prune them as values are placed
+    pub auto_candidates: bool,
+    /// Remove a placed value from the notes of its peers
+    pub auto_cleanup: bool,
+    /// Fill the last empty cell of a row, column or box
+    pub auto_fill_singles: bool,
+    /// Only allow values that don't conflict with a peer
+    pub legal_digits_only: bool,
+}
+
+/// The assists in effect, the player's picks only apply to their own games
+pub fn active_assists(preferences: &Preferences, app_state: &State<AppState>) -> AssistSettings {
+    match app_state.current() {
+        AppState::Playing => preferences.assists,
+        _ => AssistSettings::default(),
+    }
+}
+
+/// Resource for the values allowed in the selected cell, None when unrestricted
+#[derive(Resource, Default)]
+pub struct LegalDigits(pub Option<Notes>);
+
+impl LegalDigits {
+    pub fn allows(&self, value: Value) -> bool {
+        match self.0 {
+            Some(notes) => notes.contains(value),
+            None => true,
+        }
+    }
+}
+
+/// Values on the board, indexed by row then column
+pub type Grid = [[Option<Value>; 9]; 9];
+
+pub fn grid_values<'a>(cells: impl Iterator<Item = (&'a Cell, &'a CellPosition)>) -> Grid {
+    let mut grid = Grid::default();
+    for (cell, pos) in cells {
+        grid[pos.y][pos.x] = cell.value();
+    }
+    grid
+}
+
+/// Values of a puzzle grid
+pub fn grid_from(grid: &SudokuGrid) -> Grid {
+    std::array::from_fn(|y| {
+        std::array::from_fn(|x| grid.get_cell(x, y).ok().flatten().map(Value::from))
+    })
+}
+
+/// Rows, columns and boxes as lists of (x, y)
+pub fn units() -> Vec<[(usize, usize); 9]> {
+    let mut units = Vec::with_capacity(27);
+    for i in 0..9 {
+        units.push(std::array::from_fn(|j| (j, i)));
+        units.push(std::array::from_fn(|j| (i, j)));
+        units.push(std::array::from_fn(|j| ((i % 3) * 3 + j % 3, (i / 3) * 3 + j / 3)));
+    }
+    units
+}
+
+/// Cells sharing a row, column or box
+pub fn is_peer(a: (usize, usize), b: (usize, usize)) -> bool {
+    a != b && (a.0 == b.0 || a.1 == b.1 || (a.0 / 3 == b.0 / 3 && a.1 / 3 == b.1 / 3))
+}
+
+/// Values that don't conflict with any peer of (x, y)
+pub fn candidates(grid: &Grid, x: usize, y: usize) -> Notes {
+    let mut notes = Notes::all();
+    for py in 0..9 {
+        for px in 0..9 {
+            if is_peer((x, y), (px, py)) {
+                if let Some(value) = grid[py][px] {
+                    notes.remove(value);
+                }
+            }
+        }
+    }
+    notes
+}
+
+/// Apply the enabled assists after a player move at `origin`, so they become part of that move
+pub fn apply_assists(
+    settings: &AssistSettings,
+    query: &mut Query<(&mut Cell, &CellPosition)>,
+    origin: CellPosition,
+) {
+    let mut placed = Vec::new();
+    if let Some((cell, _)) = query.iter().find(|(_, pos)| **pos == origin) {
+        if let Some(value) = cell.value() {
+            placed.push((origin, value));
+        }
+    }
+
+    if settings.auto_fill_singles {
+        placed.extend(fill_singles(query));
+    }
+
+    if settings.auto_cleanup || settings.auto_candidates {
+        for (cell_pos, value) in placed {
+            for (mut cell, pos) in query.iter_mut() {
+                if is_peer((cell_pos.x, cell_pos.y), (pos.x, pos.y)) && cell.notes().contains(value) {
+                    let mut notes = cell.notes();
+                    notes.remove(value);
+                    cell.set_notes(notes);
+                }
+            }
+        }
+    }
+}
+
+/// Fill any unit with a single empty cell, repeating until nothing changes
+fn fill_singles(query: &mut Query<(&mut Cell, &CellPosition)>) -> Vec<(CellPosition, Value)> {
+    let mut placed = Vec::new();
+    loop {
+        let grid = grid_values(query.iter());
+        let mut single = None;
+        for unit in units() {
+            let empty: Vec<_> = unit.iter().filter(|(x, y)| grid[*y][*x].is_none()).collect();
+            if empty.len() != 1 {
+                continue;
+            }
+            let mut missing = Notes::all();
+            for (x, y) in unit.iter() {
+                if let Some(value) = grid[*y][*x] {
+                    missing.remove(value);
+                }
+            }
+            // a unit holding a duplicate has no single answer
+            let mut values = Value::iter().filter(|value| missing.contains(*value));
+            if let (Some(value), None) = (values.next(), values.next()) {
+                let (x, y) = *empty[0];
+                single = Some((CellPosition::new(x, y), value));
+                break;
+            }
+        }
+
+        match single {
+            Some((single_pos, value)) => {
+                for (mut cell, pos) in query.iter_mut() {
+                    if *pos == single_pos {
+                        cell.set_value(value, ValueOrigin::Hint);
+                    }
+                }
+                placed.push((single_pos, value));
+            }
+            None => return placed,
+        }
+    }
+}
+
+/// Set the notes of every empty cell to its candidates
+pub fn fill_candidates(query: &mut Query<(&mut Cell, &CellPosition)>) {
+    let grid = grid_values(query.iter());
+    for (mut cell, pos) in query.iter_mut() {
+        if cell.value().is_some() {
+            continue;
+        }
+        let notes = candidates(&grid, pos.x, pos.y);
+        if cell.notes() != notes {
+            cell.set_notes(notes);
+        }
+    }
+}
+
+/// Fill candidates for a new board, or when the assist is switched on
+fn auto_candidates_system(
+    preferences: Res<Preferences>,
+    app_state: Res<State<AppState>>,
+    mut history: ResMut<History>,
+    mut enabled: Local<bool>,
+    added_query: Query<(), Added<CellPosition>>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    let was_enabled = std::mem::replace(&mut *enabled, preferences.assists.auto_candidates);
+    if !active_assists(&preferences, &app_state).auto_candidates {
+        return;
+    }
+    if !added_query.is_empty() {
+        fill_candidates(&mut query);
+    } else if !was_enabled {
+        // switched on mid game, so a single undo takes the notes back out
+        let before = BoardSnapshot::new(query.iter());
+        fill_candidates(&mut query);
+        history.record(&before, &BoardSnapshot::new(query.iter()));
+    }
+}
+
+fn update_legal_digits(
+    preferences: Res<Preferences>,
+    app_state: Res<State<AppState>>,
+    board_selected: Res<BoardSelected>,
+    changed_query: Query<(), Changed<Cell>>,
+    query: Query<(&Cell, &CellPosition)>,
+    mut legal_digits: ResMut<LegalDigits>,
+) {
+    if !preferences.is_changed() && !board_selected.is_changed() && changed_query.is_empty() {
+        return;
+    }
+
+    let allowed = match board_selected.entity.and_then(|e| query.get(e).ok()) {
+        Some((_, pos))
+            if active_assists(&preferences, &app_state).legal_digits_only
+                && preferences.input_mode == InputMode::CellFirst =>
+        {
+            Some(candidates(&grid_values(query.iter()), pos.x, pos.y))
+        }
+        _ => None,
+    };
+    if legal_digits.0 != allowed {
+        legal_digits.0 = allowed;
+    }
+}
@@ -1,7 +1,24 @@
-use bevy::{prelude::*, window::WindowResized};
+use std::time::Duration;
+
+use bevy::{prelude::*, ui::FocusPolicy, window::WindowResized};
+use bevy_tweening::{lens::TransformScaleLens, Animator, EaseFunction, Tween};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 use sudoku_variants::SudokuGrid;
 
-use crate::ui::*;
+use crate::{
+    annotation::{create_annotation_layer, DrawTool},
+    assist::{active_assists, apply_assists, candidates, grid_values, LegalDigits},
+    game::GameStatus,
+    heatmap::spawn_heatmap_shade,
+    history::{BoardSnapshot, History},
+    lens::BackgroundColorLens,
+    settings::{InputMode, Preferences},
+    ui::*,
+    variant::{spawn_outside_clue_layer, spawn_variant_layer},
+    AppState,
+};
 use std::fmt::{Display, Formatter};
 pub struct BoardPlugin;
 
@@ -9,14 +26,27 @@ impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<BoardSelect>()
             .add_event::<CellSelect>()
+            .add_event::<ValueEntered>()
             .init_resource::<BoardSelected>()
             .init_resource::<CellMenuKeys>()
+            .init_resource::<LockedDigit>()
+            .init_resource::<CellLock>()
+            .init_resource::<EntryMode>()
+            .init_resource::<DigitCounts>()
             .add_system(board_cell_button_system)
             .add_system(cell_menu_button_system)
+            .add_system(entry_mode_button_system)
             .add_system(cell_select_event)
             .add_system(cell_menu_select_event)
+            .add_system(digit_first_select_event)
             .add_system(cell_keyboard_input)
+            .add_system(entry_mode_keyboard_input)
+            .add_system(update_cell_menu_colors)
+            .add_system(update_digit_counts)
+            .add_system(update_digit_count_badges.after(update_digit_counts))
             .add_system(update_cell_text)
+            .add_system(update_cell_colors)
+            .add_system(update_user_color_palette)
             .add_system(resize_board_cells)
             .add_system(resize_cell_menu);
     }
@@ -28,12 +58,104 @@ pub struct BoardSelect(pub Entity);
 /// Event for when a cell menu button is selected
 pub struct CellSelect(pub Option<Value>);
 
+/// Event for when the player places a value in a cell
+pub struct ValueEntered {
+    pub entity: Entity,
+    pub value: Value,
+}
+
 /// Resource for currently selected board entity
 #[derive(Default, Resource)]
 pub struct BoardSelected {
     pub entity: Option<Entity>,
 }
 
+/// Resource for the digit locked in the cell menu, used by [`InputMode::DigitFirst`]
+#[derive(Default, Resource)]
+pub struct LockedDigit(pub Option<Value>);
+
+/// Resource limiting selection to one cell, used by the tutorial to guide input
+#[derive(Default, Resource)]
+pub struct CellLock(pub Option<CellPosition>);
+
+/// Resource for whether input places values, toggles notes or colors, or draws annotations
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EntryMode {
+    Value,
+    Note,
+    /// Values 1 to [`USER_COLOR_COUNT`] pick from [`Theme::user_colors`]
+    Color,
+    /// Selecting cells draws with the current [`DrawTool`]
+    Draw,
+}
+
+impl Default for EntryMode {
+    fn default() -> Self {
+        Self::Value
+    }
+}
+
+impl EntryMode {
+    /// Switch to `mode`, or back to values if already in it
+    fn toggled(self, mode: EntryMode) -> EntryMode {
+        if self == mode {
+            EntryMode::Value
+        } else {
+            mode
+        }
+    }
+}
+
+/// Resource for how many of each value are left to place on the board
+#[derive(Resource)]
+pub struct DigitCounts {
+    remaining: [usize; 9],
+}
+
+impl Default for DigitCounts {
+    fn default() -> Self {
+        Self { remaining: [9; 9] }
+    }
+}
+
+impl DigitCounts {
+    pub fn remaining(&self, value: Value) -> usize {
+        self.remaining[usize::from(value) - 1]
+    }
+
+    pub fn is_complete(&self, value: Value) -> bool {
+        self.remaining(value) == 0
+    }
+}
+
+/// The node holding the whole grid
+#[derive(Component)]
+pub struct Board;
+
+/// Space before the `i`th cell of a row or column, matching the margins in `create_board`
+fn gap_before(i: usize) -> f32 {
+    if i % 3 == 0 {
+        4.0
+    } else {
+        2.0
+    }
+}
+
+/// Center of a cell relative to the top left of the board
+pub fn cell_center(pos: CellPosition, cell_size: f32) -> Vec2 {
+    let offset = |i: usize| (0..=i).map(gap_before).sum::<f32>() + i as f32 * cell_size;
+    Vec2::new(offset(pos.x), offset(pos.y)) + Vec2::splat(cell_size * 0.5)
+}
+
+/// Middle of the border `pos` shares with the neighbor above or to its left, relative to its top left
+pub fn border_center(pos: CellPosition, neighbor: CellPosition, cell_size: f32) -> Vec2 {
+    if neighbor.y == pos.y {
+        Vec2::new(-gap_before(pos.x) * 0.5, cell_size * 0.5)
+    } else {
+        Vec2::new(cell_size * 0.5, -gap_before(pos.y) * 0.5)
+    }
+}
+
 pub fn create_board(
     parent: &mut ChildBuilder,
     theme: &Theme,
@@ -62,6 +184,7 @@ pub fn create_board(
             ..default()
         })
         .insert(ThickLine)
+        .insert(Board)
         .insert(Name::new("Board"))
         .with_children(|parent| {
             // since I want the grid lines to look right, I need to create the grid
@@ -183,16 +306,29 @@ pub fn create_board(
                                                         ))
                                                         .insert(CellPosition::new(pos_x, pos_y))
                                                         .with_children(|parent| {
-                                                            parent.spawn(TextBundle {
-                                                                transform: Transform::from_scale(
-                                                                    Vec3::splat(font_scale),
-                                                                ),
-                                                                text: font_assets.btn(
-                                                                    " ",//format!("({pos_x},{pos_y})"),
-                                                                    &theme,
-                                                                ),
-                                                                ..default()
-                                                            });
+                                                            spawn_heatmap_shade(
+                                                                parent,
+                                                                CellPosition::new(pos_x, pos_y),
+                                                            );
+                                                            spawn_cell_color_layer(parent, theme);
+                                                            spawn_variant_layer(
+                                                                parent,
+                                                                CellPosition::new(pos_x, pos_y),
+                                                            );
+                                                            parent.spawn((
+                                                                TextBundle {
+                                                                    transform: Transform::from_scale(
+                                                                        Vec3::splat(font_scale),
+                                                                    ),
+                                                                    text: font_assets.cell(
+                                                                        " ",//format!("({pos_x},{pos_y})"),
+                                                                        "",
+                                                                        &theme,
+                                                                    ),
+                                                                    ..default()
+                                                                },
+                                                                CellText,
+                                                            ));
                                                         });
                                                 }
                                             });
@@ -201,6 +337,43 @@ pub fn create_board(
                         }
                     });
             }
+
+            create_annotation_layer(parent);
+            spawn_outside_clue_layer(parent);
+        });
+}
+
+/// User colors sit under the text, inset so hover and selection still show around them
+fn spawn_cell_color_layer(parent: &mut ChildBuilder, theme: &Theme) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect::all(Val::Px(3.0)),
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(FocusPolicy::Pass)
+        .insert(CellColorLayer)
+        .with_children(|parent| {
+            for (i, color) in theme.user_colors.iter().enumerate() {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            flex_grow: 1.0,
+                            size: Size::new(Val::Auto, Val::Percent(100.0)),
+                            ..default()
+                        },
+                        background_color: (*color).into(),
+                        ..default()
+                    })
+                    .insert(FocusPolicy::Pass)
+                    .insert(CellColorSegment(i));
+            }
         });
 }
 
@@ -234,6 +407,26 @@ fn get_cell_sizes(width: f32, height: f32) -> (f32, Val) {
 #[derive(Component)]
 pub struct CellMenuButton(pub Option<Value>);
 
+/// Cell menu button toggling an [`EntryMode`]
+#[derive(Component)]
+pub struct EntryModeButton(pub EntryMode);
+
+/// Value and notes text of a cell
+#[derive(Component)]
+pub struct CellText;
+
+/// Container for the color segments of a cell
+#[derive(Component)]
+pub struct CellColorLayer;
+
+/// One segment per user color, shown when the cell has that color
+#[derive(Component)]
+pub struct CellColorSegment(pub usize);
+
+/// Badge showing how many of a value remain, child of a [`CellMenuButton`]
+#[derive(Component)]
+pub struct DigitCountText(pub Value);
+
 pub fn create_cell_menu(
     parent: &mut ChildBuilder,
     theme: &Theme,
@@ -247,6 +440,7 @@ pub fn create_cell_menu(
             style: Style {
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
+                flex_wrap: FlexWrap::Wrap,
                 padding: UiRect::all(Val::Px(2.0)),
                 margin: UiRect {
                     top: Val::Px(20.0),
@@ -302,6 +496,60 @@ pub fn create_cell_menu(
                             ),
                             ..default()
                         });
+
+                        if i > 0 {
+                            parent.spawn((
+                                TextBundle {
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        position: UiRect {
+                                            top: Val::Px(2.0),
+                                            right: Val::Px(4.0),
+                                            ..default()
+                                        },
+                                        ..default()
+                                    },
+                                    transform: Transform::from_scale(Vec3::splat(font_size)),
+                                    text: font_assets.badge("9", &theme),
+                                    ..default()
+                                },
+                                DigitCountText(Value::from(i)),
+                            ));
+                        }
+                    });
+            }
+
+            for (mode, label) in [
+                (EntryMode::Note, "Notes"),
+                (EntryMode::Color, "Color"),
+                (EntryMode::Draw, "Draw"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Px(2.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                size: Size {
+                                    width: cell_size,
+                                    height: cell_size,
+                                },
+                                aspect_ratio: Some(1.0),
+                                ..default()
+                            },
+                            background_color: theme.btn_normal.into(),
+                            ..default()
+                        },
+                        Name::new(format!("Option {label}")),
+                        EntryModeButton(mode),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            transform: Transform::from_scale(Vec3::splat(font_size * 0.5)),
+                            text: font_assets.btn(label, &theme),
+                            ..default()
+                        });
                     });
             }
         });
@@ -309,20 +557,25 @@ pub fn create_cell_menu(
 
 pub fn resize_cell_menu(
     mut window_resize: EventReader<WindowResized>,
-    mut query: Query<(&mut Style, &Children), With<CellMenuButton>>,
+    mut query: Query<
+        (&mut Style, &Children, Option<&EntryModeButton>),
+        Or<(With<CellMenuButton>, With<EntryModeButton>)>,
+    >,
     mut text_query: Query<&mut Transform, With<Text>>,
 ) {
     for resize in window_resize.iter() {
         let size = resize.width.min(resize.height) / 11.0;
         let value = Val::Px(size);
 
-        for (mut style, children) in query.iter_mut() {
+        for (mut style, children, mode_button) in query.iter_mut() {
             style.size.width = value;
             style.size.height = value;
 
+            // the mode labels are longer, so they get a smaller font
+            let scale = if mode_button.is_some() { 0.5 } else { 1.0 };
             for c in children.iter() {
                 if let Ok(mut trans) = text_query.get_mut(*c) {
-                    trans.scale = Vec3::splat(size / 80.0);
+                    trans.scale = Vec3::splat(scale * size / 80.0);
                 }
             }
         }
@@ -331,19 +584,18 @@ pub fn resize_cell_menu(
 
 fn board_cell_button_system(
     mut interaction_query: Query<
-        (Entity, &Interaction, &mut BackgroundColor, &Cell),
-        (Changed<Interaction>, With<Button>),
+        (Entity, &Interaction, &mut BackgroundColor, &CellPosition),
+        (Changed<Interaction>, With<Button>, With<Cell>),
     >,
     theme: Res<ThemeMode>,
     mut select_event: EventWriter<BoardSelect>,
     selected: Res<BoardSelected>,
+    cell_lock: Res<CellLock>,
 ) {
-    for (e, interaction, mut color, cell) in &mut interaction_query {
+    for (e, interaction, mut color, pos) in &mut interaction_query {
         let theme = theme.theme();
-        if cell.is_disabled() {
-            continue;
-        }
         match *interaction {
+            Interaction::Clicked if cell_lock.0.map_or(false, |locked| locked != *pos) => {}
             Interaction::Clicked => {
                 *color = theme.btn_selected.into();
                 select_event.send(BoardSelect(e));
@@ -370,26 +622,166 @@ fn cell_menu_button_system(
         (Changed<Interaction>, With<Button>),
     >,
     theme: Res<ThemeMode>,
+    locked_digit: Res<LockedDigit>,
+    entry_mode: Res<EntryMode>,
+    digit_counts: Res<DigitCounts>,
+    legal_digits: Res<LegalDigits>,
 ) {
     for (interaction, mut color, cell_menu) in &mut interaction_query {
         let theme = theme.theme();
+        if let Some(value) = cell_menu.0 {
+            if is_digit_disabled(value, *entry_mode, &digit_counts, &legal_digits) {
+                continue;
+            }
+        }
         match *interaction {
             Interaction::Clicked => {
                 *color = theme.btn_selected.into();
                 select_event.send(CellSelect(cell_menu.0));
             }
-            _ => {}
+            Interaction::Hovered => {
+                *color = theme.btn_hovered.into();
+            }
+            Interaction::None => {
+                *color = cell_menu_color(
+                    theme,
+                    cell_menu,
+                    &locked_digit,
+                    *entry_mode,
+                    &digit_counts,
+                    &legal_digits,
+                )
+                .into();
+            }
         }
     }
 }
 
-struct KeyValues {
+fn entry_mode_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &EntryModeButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    theme: Res<ThemeMode>,
+    mut entry_mode: ResMut<EntryMode>,
+    mut draw_tool: ResMut<DrawTool>,
+) {
+    for (interaction, mut color, mode_button) in &mut interaction_query {
+        let theme = theme.theme();
+        match *interaction {
+            Interaction::Clicked => {
+                // selecting draw again cycles through the tools before leaving the mode
+                if mode_button.0 == EntryMode::Draw && *entry_mode == EntryMode::Draw {
+                    if let Some(next) = draw_tool.next() {
+                        *draw_tool = next;
+                        continue;
+                    }
+                    *draw_tool = DrawTool::default();
+                }
+                *entry_mode = entry_mode.toggled(mode_button.0);
+            }
+            Interaction::Hovered => {
+                *color = theme.btn_hovered.into();
+            }
+            Interaction::None => {
+                *color = entry_mode_color(theme, mode_button, *entry_mode).into();
+            }
+        }
+    }
+}
+
+/// Fully placed and illegal digits are disabled, in color mode only the palette is available
+fn is_digit_disabled(
+    value: Value,
+    entry_mode: EntryMode,
+    digit_counts: &DigitCounts,
+    legal_digits: &LegalDigits,
+) -> bool {
+    match entry_mode {
+        EntryMode::Color => usize::from(value) > USER_COLOR_COUNT,
+        EntryMode::Draw => true,
+        _ => digit_counts.is_complete(value) || !legal_digits.allows(value),
+    }
+}
+
+fn cell_menu_color(
+    theme: &Theme,
+    cell_menu: &CellMenuButton,
+    locked_digit: &LockedDigit,
+    entry_mode: EntryMode,
+    digit_counts: &DigitCounts,
+    legal_digits: &LegalDigits,
+) -> Color {
+    match (cell_menu.0, locked_digit.0) {
+        (Some(value), _) if is_digit_disabled(value, entry_mode, digit_counts, legal_digits) => {
+            theme.btn_disabled
+        }
+        (Some(value), Some(locked)) if value == locked => theme.btn_selected,
+        // show the palette while coloring
+        (Some(value), _) if entry_mode == EntryMode::Color => {
+            theme.user_colors[usize::from(value) - 1]
+        }
+        _ => theme.btn_normal,
+    }
+}
+
+fn entry_mode_color(theme: &Theme, mode_button: &EntryModeButton, entry_mode: EntryMode) -> Color {
+    if mode_button.0 == entry_mode {
+        theme.btn_selected
+    } else {
+        theme.btn_normal
+    }
+}
+
+/// Keeps the locked digit and note mode highlighted, and disabled digits greyed, in the cell menu
+fn update_cell_menu_colors(
+    theme: Res<ThemeMode>,
+    locked_digit: Res<LockedDigit>,
+    entry_mode: Res<EntryMode>,
+    digit_counts: Res<DigitCounts>,
+    legal_digits: Res<LegalDigits>,
+    mut menu_query: Query<(&Interaction, &mut BackgroundColor, &CellMenuButton)>,
+    mut mode_query: Query<
+        (&Interaction, &mut BackgroundColor, &EntryModeButton),
+        Without<CellMenuButton>,
+    >,
+) {
+    let theme = theme.theme();
+    if locked_digit.is_changed()
+        || legal_digits.is_changed()
+        || digit_counts.is_changed()
+        || entry_mode.is_changed()
+    {
+        for (interaction, mut color, cell_menu) in &mut menu_query {
+            if *interaction == Interaction::None {
+                *color = cell_menu_color(
+                    theme,
+                    cell_menu,
+                    &locked_digit,
+                    *entry_mode,
+                    &digit_counts,
+                    &legal_digits,
+                )
+                .into();
+            }
+        }
+    }
+    if entry_mode.is_changed() {
+        for (interaction, mut color, mode_button) in &mut mode_query {
+            if *interaction != Interaction::Hovered {
+                *color = entry_mode_color(theme, mode_button, *entry_mode).into();
+            }
+        }
+    }
+}
+
+pub struct KeyValues {
     pub key: Vec<KeyCode>,
     pub value: Option<Value>,
 }
 
 #[derive(Resource)]
-struct CellMenuKeys(pub Vec<KeyValues>);
+pub struct CellMenuKeys(pub Vec<KeyValues>);
 
 impl Default for CellMenuKeys {
     fn default() -> Self {
@@ -457,6 +849,120 @@ fn cell_keyboard_input(
     }
 }
 
+/// Count the values on the board, runs whenever a cell changes so undo, reset and solve stay in sync
+fn update_digit_counts(
+    changed_query: Query<(), Changed<Cell>>,
+    query: Query<&Cell>,
+    mut digit_counts: ResMut<DigitCounts>,
+) {
+    if changed_query.is_empty() {
+        return;
+    }
+
+    let mut remaining = [9; 9];
+    for cell in query.iter() {
+        if let Some(value) = cell.value() {
+            let index = usize::from(value) - 1;
+            remaining[index] = remaining[index].saturating_sub(1);
+        }
+    }
+    if digit_counts.remaining != remaining {
+        digit_counts.remaining = remaining;
+    }
+}
+
+fn update_digit_count_badges(
+    mut commands: Commands,
+    digit_counts: Res<DigitCounts>,
+    theme: Res<ThemeMode>,
+    mut locked_digit: ResMut<LockedDigit>,
+    mut previous: Local<Option<[usize; 9]>>,
+    mut badge_query: Query<(&DigitCountText, &mut Text)>,
+    button_query: Query<(Entity, &CellMenuButton)>,
+) {
+    if !digit_counts.is_changed() {
+        return;
+    }
+    let theme = theme.theme();
+
+    for (badge, mut text) in badge_query.iter_mut() {
+        text.sections[0].value = format!("{}", digit_counts.remaining(badge.0));
+    }
+
+    for value in Value::iter() {
+        let complete = digit_counts.is_complete(value);
+        let was_complete = match *previous {
+            Some(remaining) => remaining[usize::from(value) - 1] == 0,
+            None => complete,
+        };
+
+        if complete && locked_digit.0 == Some(value) {
+            locked_digit.0 = None;
+        }
+
+        for (e, cell_menu) in button_query.iter() {
+            if cell_menu.0 != Some(value) {
+                continue;
+            }
+            if complete && !was_complete {
+                // pop the button and fade it out when the last one is placed
+                let ease_fn = EaseFunction::QuadraticInOut;
+                let duration = Duration::from_secs_f32(0.2);
+                commands.entity(e).insert((
+                    Animator::new(
+                        Tween::new(
+                            ease_fn,
+                            duration,
+                            TransformScaleLens {
+                                start: Vec3::ONE,
+                                end: Vec3::splat(1.2),
+                            },
+                        )
+                        .then(Tween::new(
+                            ease_fn,
+                            duration,
+                            TransformScaleLens {
+                                start: Vec3::splat(1.2),
+                                end: Vec3::ONE,
+                            },
+                        )),
+                    ),
+                    Animator::new(Tween::new(
+                        ease_fn,
+                        duration * 2,
+                        BackgroundColorLens {
+                            start: theme.btn_normal,
+                            end: theme.btn_disabled,
+                        },
+                    )),
+                ));
+            }
+        }
+    }
+
+    *previous = Some(digit_counts.remaining);
+}
+
+fn entry_mode_keyboard_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    app_state: Res<State<AppState>>,
+    mut entry_mode: ResMut<EntryMode>,
+) {
+    // the samurai board only takes values
+    if app_state.current() == &AppState::Samurai {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::N) {
+        *entry_mode = entry_mode.toggled(EntryMode::Note);
+    }
+    if keyboard_input.just_pressed(KeyCode::C) {
+        *entry_mode = entry_mode.toggled(EntryMode::Color);
+    }
+    if keyboard_input.just_pressed(KeyCode::D) {
+        *entry_mode = entry_mode.toggled(EntryMode::Draw);
+    }
+}
+
 fn cell_select_event(
     mut select_events: EventReader<BoardSelect>,
     mut board_selected: ResMut<BoardSelected>,
@@ -481,35 +987,191 @@ fn cell_select_event(
 fn cell_menu_select_event(
     mut select_events: EventReader<CellSelect>,
     board_selected: Res<BoardSelected>,
-    mut query: Query<&mut Cell>,
+    preferences: Res<Preferences>,
+    app_state: Res<State<AppState>>,
+    entry_mode: Res<EntryMode>,
+    digit_counts: Res<DigitCounts>,
+    legal_digits: Res<LegalDigits>,
+    game_status: Res<GameStatus>,
+    mut locked_digit: ResMut<LockedDigit>,
+    mut history: ResMut<History>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+    mut value_entered: EventWriter<ValueEntered>,
 ) {
     for event in select_events.iter() {
+        if *game_status != GameStatus::Playing || *entry_mode == EntryMode::Draw {
+            continue;
+        }
+
+        // fully placed digits are disabled, keyboard input included
+        if let Some(value) = event.0 {
+            if digit_counts.is_complete(value) && *entry_mode == EntryMode::Value {
+                continue;
+            }
+            if preferences.input_mode == InputMode::CellFirst
+                && *entry_mode != EntryMode::Color
+                && !legal_digits.allows(value)
+            {
+                continue;
+            }
+        }
+
+        if preferences.input_mode == InputMode::DigitFirst {
+            // lock the digit, selecting it again or the empty button releases it
+            locked_digit.0 = match (locked_digit.0, event.0) {
+                (Some(locked), Some(value)) if locked == value => None,
+                (_, value) => value,
+            };
+            continue;
+        }
+
         // set new selection
         if let Some(selected_entity) = board_selected.entity {
-            if let Ok(mut cell) = query.get_mut(selected_entity) {
-                cell.value = event.0;
+            let before = BoardSnapshot::new(query.iter());
+            let pos = match query.get_mut(selected_entity) {
+                Ok((mut cell, pos)) => {
+                    cell.input(event.0, *entry_mode);
+                    *pos
+                }
+                Err(_) => continue,
+            };
+            if let (EntryMode::Value, Some(value)) = (*entry_mode, event.0) {
+                value_entered.send(ValueEntered {
+                    entity: selected_entity,
+                    value,
+                });
             }
+
+            // assists are part of the same move, so a single undo reverts both
+            apply_assists(&active_assists(&preferences, &app_state), &mut query, pos);
+            history.record(&before, &BoardSnapshot::new(query.iter()));
         }
     }
 }
 
+/// In digit first mode, selecting a cell places the locked digit
+fn digit_first_select_event(
+    mut select_events: EventReader<BoardSelect>,
+    preferences: Res<Preferences>,
+    app_state: Res<State<AppState>>,
+    entry_mode: Res<EntryMode>,
+    locked_digit: Res<LockedDigit>,
+    game_status: Res<GameStatus>,
+    mut history: ResMut<History>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+    mut value_entered: EventWriter<ValueEntered>,
+) {
+    for event in select_events.iter() {
+        if preferences.input_mode != InputMode::DigitFirst
+            || *game_status != GameStatus::Playing
+            || *entry_mode == EntryMode::Draw
+            || !app_state.current().solves_board()
+        {
+            continue;
+        }
+        if let Some(value) = locked_digit.0 {
+            let grid = grid_values(query.iter());
+            let before = BoardSnapshot::new(query.iter());
+            let pos = match query.get_mut(event.0) {
+                Ok((mut cell, pos)) => {
+                    // givens only take colors, so there's nothing to enter or record
+                    if cell.is_disabled() && *entry_mode != EntryMode::Color {
+                        continue;
+                    }
+                    if active_assists(&preferences, &app_state).legal_digits_only
+                        && *entry_mode != EntryMode::Color
+                        && cell.value() != Some(value)
+                        && !candidates(&grid, pos.x, pos.y).contains(value)
+                    {
+                        continue;
+                    }
+                    cell.toggle(value, *entry_mode);
+                    if *entry_mode == EntryMode::Value && cell.value() == Some(value) {
+                        value_entered.send(ValueEntered {
+                            entity: event.0,
+                            value,
+                        });
+                    }
+                    *pos
+                }
+                Err(_) => continue,
+            };
+
+            apply_assists(&active_assists(&preferences, &app_state), &mut query, pos);
+            history.record(&before, &BoardSnapshot::new(query.iter()));
+        }
+    }
+}
+
+/// Cell text is styled by value origin, so the theme toggle leaves it to this
 fn update_cell_text(
-    query: Query<(&Cell, &Children), Changed<Cell>>,
-    mut text_query: Query<&mut Text>,
+    query: Query<(&Cell, &Children, ChangeTrackers<Cell>)>,
+    mut text_query: Query<&mut Text, With<CellText>>,
+    theme: Res<Theme>,
 ) {
-    for (cell, children) in &mut query.iter() {
+    for (cell, children, tracker) in query.iter() {
+        if !tracker.is_changed() && !theme.is_changed() {
+            continue;
+        }
+        let style = theme.value_style(cell.origin);
         for child in children.iter() {
             if let Ok(mut text) = text_query.get_mut(*child) {
                 text.sections[0].value = match cell.value {
                     Some(v) => format!("{v}"),
                     None => "".to_string(),
                 };
+                text.sections[0].style.color = if cell.mistake {
+                    theme.text_error
+                } else {
+                    style.color
+                };
+                text.sections[0].style.font_size = style.font_size;
+                text.sections[1].value = match cell.value {
+                    Some(_) => "".to_string(),
+                    None => format!("{}", cell.notes),
+                };
+                text.sections[1].style.color = theme.text;
             }
         }
     }
 }
 
-#[derive(Component)]
+fn update_cell_colors(
+    query: Query<(&Cell, &Children), Changed<Cell>>,
+    layer_query: Query<&Children, With<CellColorLayer>>,
+    mut segment_query: Query<(&CellColorSegment, &mut Style)>,
+) {
+    for (cell, children) in query.iter() {
+        for layer in layer_query.iter_many(children) {
+            for child in layer.iter() {
+                if let Ok((segment, mut style)) = segment_query.get_mut(*child) {
+                    let display = if cell.colors().contains(segment.0) {
+                        Display::Flex
+                    } else {
+                        Display::None
+                    };
+                    if style.display != display {
+                        style.display = display;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn update_user_color_palette(
+    theme: Res<Theme>,
+    mut query: Query<(&CellColorSegment, &mut BackgroundColor)>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    for (segment, mut color) in query.iter_mut() {
+        *color = theme.user_colors[segment.0].into();
+    }
+}
+
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CellPosition {
     pub x: usize,
     pub y: usize,
@@ -524,14 +1186,22 @@ impl CellPosition {
 #[derive(Component)]
 pub struct Cell {
     value: Option<Value>,
+    origin: ValueOrigin,
+    notes: Notes,
+    colors: CellColors,
     enabled: bool,
+    mistake: bool,
 }
 
 impl Default for Cell {
     fn default() -> Self {
         Self {
             value: None,
+            origin: ValueOrigin::default(),
+            notes: Notes::default(),
+            colors: CellColors::default(),
             enabled: true,
+            mistake: false,
         }
     }
 }
@@ -541,12 +1211,11 @@ impl Cell {
         match value {
             Some(v) => Self {
                 value: Some(Value::from(v)),
+                origin: ValueOrigin::Given,
                 enabled: false,
+                ..default()
             },
-            None => Self {
-                value: None,
-                enabled: true,
-            },
+            None => Self::default(),
         }
     }
 
@@ -554,8 +1223,103 @@ impl Cell {
         self.value
     }
 
-    pub fn set_value(&mut self, value: Value) {
-        self.value = Some(value);
+    /// Who placed the value
+    pub fn origin(&self) -> ValueOrigin {
+        self.origin
+    }
+
+    /// Place a value on behalf of an assist or the solver, givens never change
+    pub fn set_value(&mut self, value: Value, origin: ValueOrigin) {
+        if self.enabled {
+            self.value = Some(value);
+            self.origin = origin;
+            self.mistake = false;
+        }
+    }
+
+    /// Take back a value placed on behalf of the solver
+    pub fn clear_value(&mut self) {
+        if self.enabled {
+            self.value = None;
+            self.mistake = false;
+        }
+    }
+
+    /// Flagged as disagreeing with the solution
+    pub fn is_mistake(&self) -> bool {
+        self.mistake
+    }
+
+    pub fn set_mistake(&mut self, mistake: bool) {
+        self.mistake = mistake;
+    }
+
+    pub fn notes(&self) -> Notes {
+        self.notes
+    }
+
+    pub fn set_notes(&mut self, notes: Notes) {
+        self.notes = notes;
+    }
+
+    pub fn colors(&self) -> CellColors {
+        self.colors
+    }
+
+    /// The part of the cell the player can change
+    pub fn state(&self) -> CellState {
+        CellState {
+            value: self.value,
+            origin: self.origin,
+            notes: self.notes,
+            colors: self.colors,
+        }
+    }
+
+    /// Restore a previous state, givens only change color
+    pub fn restore(&mut self, state: CellState) {
+        self.colors = state.colors;
+        if self.enabled {
+            self.value = state.value;
+            self.origin = state.origin;
+            self.notes = state.notes;
+            self.mistake = false;
+        }
+    }
+
+    /// Apply a cell menu selection, placing the value or toggling it as a note or color
+    pub fn input(&mut self, value: Option<Value>, mode: EntryMode) {
+        match (mode, value) {
+            (EntryMode::Color, Some(value)) => {
+                let index = usize::from(value) - 1;
+                if index < USER_COLOR_COUNT {
+                    self.colors.toggle(index);
+                }
+            }
+            (EntryMode::Color, None) => self.colors.clear(),
+            (EntryMode::Draw, _) => {}
+            // givens can be colored but not edited
+            _ if !self.enabled => {}
+            (EntryMode::Value, value) => {
+                self.value = value;
+                self.origin = ValueOrigin::Player;
+                self.mistake = false;
+            }
+            (EntryMode::Note, Some(value)) => {
+                if self.value.is_none() {
+                    self.notes.toggle(value);
+                }
+            }
+            (EntryMode::Note, None) => self.notes.clear(),
+        }
+    }
+
+    /// Toggle a value, clearing the cell if it already holds it
+    pub fn toggle(&mut self, value: Value, mode: EntryMode) {
+        match mode {
+            EntryMode::Value if self.value == Some(value) => self.input(None, mode),
+            _ => self.input(Some(value), mode),
+        }
     }
 
     #[allow(dead_code)]
@@ -574,13 +1338,148 @@ impl Cell {
     }
 
     pub fn reset(&mut self) {
+        self.colors.clear();
         if self.enabled {
             self.value = None;
+            self.notes.clear();
+            self.mistake = false;
+        }
+    }
+}
+
+/// Snapshot of a cell used for undo and saves
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CellState {
+    pub value: Option<Value>,
+    pub origin: ValueOrigin,
+    pub notes: Notes,
+    pub colors: CellColors,
+}
+
+/// Where a cell's value came from, each is styled by [`Theme::value_style`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValueOrigin {
+    Given,
+    Player,
+    /// Revealed, or filled in by an assist
+    Hint,
+    Solver,
+}
+
+impl Default for ValueOrigin {
+    fn default() -> Self {
+        Self::Player
+    }
+}
+
+/// User colors marked on a cell, one bit per index into [`Theme::user_colors`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellColors(u8);
+
+impl CellColors {
+    pub fn contains(&self, index: usize) -> bool {
+        self.0 & (1 << index) != 0
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        self.0 ^= 1 << index;
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = 0;
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Pencil marks for a cell, one bit per value
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notes(u16);
+
+impl Notes {
+    fn bit(value: Value) -> u16 {
+        1 << usize::from(value)
+    }
+
+    /// Every value noted
+    pub fn all() -> Self {
+        let mut notes = Self::default();
+        for value in Value::iter() {
+            notes.insert(value);
+        }
+        notes
+    }
+
+    pub fn contains(&self, value: Value) -> bool {
+        self.0 & Self::bit(value) != 0
+    }
+
+    pub fn insert(&mut self, value: Value) {
+        self.0 |= Self::bit(value);
+    }
+
+    pub fn remove(&mut self, value: Value) {
+        self.0 &= !Self::bit(value);
+    }
+
+    pub fn toggle(&mut self, value: Value) {
+        self.0 ^= Self::bit(value);
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = 0;
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of values noted
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Noted values in order
+    pub fn values(self) -> impl Iterator<Item = Value> {
+        Value::iter().filter(move |value| self.contains(*value))
+    }
+}
+
+/// Same width as a digit, so missing notes keep the others in their columns
+const FIGURE_SPACE: char = '\u{2007}';
+
+impl Display for Notes {
+    /// Lays the notes out as a 3x3 block of digits
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0 == 0 {
+            return Ok(());
+        }
+        for row in 0..3 {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            for col in 0..3 {
+                let value = Value::from(row * 3 + col + 1);
+                if col > 0 {
+                    write!(f, " ")?;
+                }
+                if self.contains(value) {
+                    write!(f, "{value}")?;
+                } else {
+                    write!(f, "{FIGURE_SPACE}")?;
+                }
+            }
         }
+        Ok(())
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 pub enum Value {
     One,
     Two,
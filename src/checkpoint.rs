@@ -0,0 +1,460 @@
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::{HighlightKind, Highlights},
+    board::{Cell, CellPosition},
+    events::NewGame,
+    game::GameStatus,
+    history::{BoardSnapshot, History},
+    ui::{FontAssets, Theme},
+};
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CreateCheckpoint>()
+            .add_event::<RestoreCheckpoint>()
+            .add_event::<CompareCheckpoint>()
+            .add_event::<DeleteCheckpoint>()
+            .add_event::<RenameCheckpoint>()
+            .init_resource::<Checkpoints>()
+            // typing a name runs before gameplay shortcuts read the keyboard
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                checkpoint_name_input.after(InputSystem),
+            )
+            .add_system(checkpoint_button_system)
+            .add_system(create_checkpoint_events)
+            .add_system(restore_checkpoint_events)
+            .add_system(compare_checkpoint_events)
+            .add_system(delete_checkpoint_events)
+            .add_system(rename_checkpoint_events)
+            .add_system(end_rename_on_click)
+            .add_system(clear_checkpoints)
+            .add_system(update_compare_highlights)
+            .add_system(update_checkpoint_list);
+    }
+}
+
+/// Event to snapshot the board before a guess
+pub struct CreateCheckpoint;
+/// Event to return the board to a checkpoint
+pub struct RestoreCheckpoint(pub usize);
+/// Event to toggle outlining the cells that differ from a checkpoint
+pub struct CompareCheckpoint(pub usize);
+/// Event to drop a checkpoint
+pub struct DeleteCheckpoint(pub usize);
+/// Event to start or stop typing a checkpoint's name
+pub struct RenameCheckpoint(pub usize);
+
+/// Values, notes and colors of every cell at a point the player may want to return to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub name: String,
+    pub cells: BoardSnapshot,
+}
+
+/// Resource for this game's checkpoints, and the one being compared against
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Checkpoints {
+    pub list: Vec<Checkpoint>,
+    #[serde(skip)]
+    pub comparing: Option<usize>,
+    /// Used to name checkpoints, so names stay unique after deletes
+    pub created: usize,
+    /// Checkpoint whose name is being typed
+    #[serde(skip)]
+    pub renaming: Option<usize>,
+    /// Whether anything was typed yet, the first key replaces the old name
+    #[serde(skip)]
+    typed: bool,
+}
+
+#[derive(Component)]
+pub struct CheckpointList;
+
+#[derive(Component, Debug, Copy, Clone)]
+enum CheckpointButton {
+    Create,
+    Restore(usize),
+    Compare(usize),
+    Rename(usize),
+    Delete(usize),
+}
+
+/// Branch list on the left of the board, filled in by [`update_checkpoint_list`]
+pub fn create_checkpoint_list(parent: &mut ChildBuilder) {
+    parent.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        },
+        Name::new("Checkpoints"),
+        CheckpointList,
+    ));
+}
+
+fn spawn_checkpoint_button(
+    parent: &mut ChildBuilder,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    button: CheckpointButton,
+    label: String,
+    width: f32,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(width), Val::Px(40.0)),
+                    margin: UiRect::all(Val::Px(2.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: theme.btn_normal.into(),
+                ..default()
+            },
+            Name::new(label.clone()),
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: font_assets.status(label, theme),
+                ..default()
+            });
+        });
+}
+
+fn update_checkpoint_list(
+    mut commands: Commands,
+    checkpoints: Res<Checkpoints>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    list_query: Query<Entity, With<CheckpointList>>,
+    added_query: Query<(), Added<CheckpointList>>,
+    changed_query: Query<(), Changed<Cell>>,
+    cell_query: Query<(&Cell, &CellPosition)>,
+) {
+    // difference counts follow the board while comparing
+    let compare_changed = checkpoints.comparing.is_some() && !changed_query.is_empty();
+    if !checkpoints.is_changed() && added_query.is_empty() && !compare_changed {
+        return;
+    }
+    let current = BoardSnapshot::new(cell_query.iter());
+
+    for list in list_query.iter() {
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            spawn_checkpoint_button(
+                parent,
+                &theme,
+                &font_assets,
+                CheckpointButton::Create,
+                "+ Checkpoint".to_string(),
+                252.0,
+            );
+            for (i, checkpoint) in checkpoints.list.iter().enumerate() {
+                let name = if checkpoints.renaming == Some(i) {
+                    format!("{}_", checkpoint.name)
+                } else {
+                    checkpoint.name.clone()
+                };
+                let compare_label = if checkpoints.comparing == Some(i) {
+                    format!("{}", differences(&checkpoint.cells, &current).count())
+                } else {
+                    "Diff".to_string()
+                };
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        spawn_checkpoint_button(
+                            parent,
+                            &theme,
+                            &font_assets,
+                            CheckpointButton::Restore(i),
+                            name,
+                            110.0,
+                        );
+                        spawn_checkpoint_button(
+                            parent,
+                            &theme,
+                            &font_assets,
+                            CheckpointButton::Compare(i),
+                            compare_label,
+                            50.0,
+                        );
+                        spawn_checkpoint_button(
+                            parent,
+                            &theme,
+                            &font_assets,
+                            CheckpointButton::Rename(i),
+                            "Aa".to_string(),
+                            40.0,
+                        );
+                        spawn_checkpoint_button(
+                            parent,
+                            &theme,
+                            &font_assets,
+                            CheckpointButton::Delete(i),
+                            "X".to_string(),
+                            40.0,
+                        );
+                    });
+            }
+        });
+    }
+}
+
+/// Cells whose value, notes or colors differ between two snapshots
+fn differences<'a>(
+    a: &'a BoardSnapshot,
+    b: &'a BoardSnapshot,
+) -> impl Iterator<Item = CellPosition> + 'a {
+    (0..9)
+        .flat_map(|y| (0..9).map(move |x| CellPosition::new(x, y)))
+        .filter(move |pos| {
+            let (a, b) = (a.0[pos.y][pos.x], b.0[pos.y][pos.x]);
+            a.value != b.value || a.notes != b.notes || a.colors != b.colors
+        })
+}
+
+fn checkpoint_button_system(
+    interaction_query: Query<(&Interaction, &CheckpointButton), (Changed<Interaction>, With<Button>)>,
+    mut create_event: EventWriter<CreateCheckpoint>,
+    mut restore_event: EventWriter<RestoreCheckpoint>,
+    mut compare_event: EventWriter<CompareCheckpoint>,
+    mut delete_event: EventWriter<DeleteCheckpoint>,
+    mut rename_event: EventWriter<RenameCheckpoint>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match *button {
+            CheckpointButton::Create => create_event.send(CreateCheckpoint),
+            CheckpointButton::Restore(i) => restore_event.send(RestoreCheckpoint(i)),
+            CheckpointButton::Compare(i) => compare_event.send(CompareCheckpoint(i)),
+            CheckpointButton::Rename(i) => rename_event.send(RenameCheckpoint(i)),
+            CheckpointButton::Delete(i) => delete_event.send(DeleteCheckpoint(i)),
+        }
+    }
+}
+
+fn create_checkpoint_events(
+    mut create_events: EventReader<CreateCheckpoint>,
+    status: Res<GameStatus>,
+    mut checkpoints: ResMut<Checkpoints>,
+    query: Query<(&Cell, &CellPosition)>,
+) {
+    for _ in create_events.iter() {
+        if *status != GameStatus::Playing {
+            continue;
+        }
+        checkpoints.created += 1;
+        let name = format!("Checkpoint {}", checkpoints.created);
+        checkpoints.list.push(Checkpoint {
+            name,
+            cells: BoardSnapshot::new(query.iter()),
+        });
+    }
+}
+
+/// Restoring is recorded as a move, so it can be undone like any other
+fn restore_checkpoint_events(
+    mut restore_events: EventReader<RestoreCheckpoint>,
+    status: Res<GameStatus>,
+    checkpoints: Res<Checkpoints>,
+    mut history: ResMut<History>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    for event in restore_events.iter() {
+        if *status != GameStatus::Playing {
+            continue;
+        }
+        if let Some(checkpoint) = checkpoints.list.get(event.0) {
+            let before = BoardSnapshot::new(query.iter());
+            for (mut cell, pos) in query.iter_mut() {
+                let state = checkpoint.cells.0[pos.y][pos.x];
+                if cell.state() != state {
+                    cell.restore(state);
+                }
+            }
+            history.record(&before, &BoardSnapshot::new(query.iter()));
+        }
+    }
+}
+
+fn compare_checkpoint_events(
+    mut compare_events: EventReader<CompareCheckpoint>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    for event in compare_events.iter() {
+        checkpoints.comparing = match checkpoints.comparing {
+            Some(i) if i == event.0 => None,
+            _ => Some(event.0),
+        };
+    }
+}
+
+fn delete_checkpoint_events(
+    mut delete_events: EventReader<DeleteCheckpoint>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    for event in delete_events.iter() {
+        if event.0 >= checkpoints.list.len() {
+            continue;
+        }
+        finish_rename(&mut checkpoints);
+        checkpoints.list.remove(event.0);
+        // keep comparing against the same checkpoint
+        checkpoints.comparing = match checkpoints.comparing {
+            Some(i) if i == event.0 => None,
+            Some(i) if i > event.0 => Some(i - 1),
+            comparing => comparing,
+        };
+    }
+}
+
+fn rename_checkpoint_events(
+    mut rename_events: EventReader<RenameCheckpoint>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    for event in rename_events.iter() {
+        let was_renaming = checkpoints.renaming == Some(event.0);
+        finish_rename(&mut checkpoints);
+        if !was_renaming && event.0 < checkpoints.list.len() {
+            checkpoints.renaming = Some(event.0);
+            checkpoints.typed = false;
+        }
+    }
+}
+
+/// Longest name that still fits on the restore button
+const MAX_NAME_LENGTH: usize = 12;
+
+/// Keys held for shortcuts, typing with them held is left to the shortcuts
+const SHORTCUT_MODIFIERS: [KeyCode; 4] = [
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LWin,
+    KeyCode::RWin,
+];
+
+/// Stop renaming, an emptied name falls back to a numbered one
+fn finish_rename(checkpoints: &mut Checkpoints) {
+    if let Some(i) = checkpoints.renaming.take() {
+        if let Some(checkpoint) = checkpoints.list.get_mut(i) {
+            if checkpoint.name.is_empty() {
+                checkpoint.name = format!("Checkpoint {}", i + 1);
+            }
+        }
+    }
+}
+
+/// Types into the checkpoint being renamed, Enter or Escape finish.
+/// The keys that typed are consumed so digits don't also place values on the board
+fn checkpoint_name_input(
+    mut characters: EventReader<ReceivedCharacter>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    let typed: Vec<char> = characters.iter().map(|event| event.char).collect();
+    let i = match checkpoints.renaming {
+        Some(i) if i < checkpoints.list.len() => i,
+        _ => return,
+    };
+    if keyboard_input.any_pressed(SHORTCUT_MODIFIERS) {
+        return;
+    }
+    let finish = [KeyCode::Return, KeyCode::NumpadEnter, KeyCode::Escape];
+    if keyboard_input.any_just_pressed(finish) {
+        finish_rename(&mut checkpoints);
+    } else if !typed.is_empty() {
+        if !checkpoints.typed {
+            checkpoints.typed = true;
+            checkpoints.list[i].name.clear();
+        }
+        let name = &mut checkpoints.list[i].name;
+        for c in typed {
+            if c == '\u{8}' || c == '\u{7f}' {
+                name.pop();
+            } else if !c.is_control() && name.chars().count() < MAX_NAME_LENGTH {
+                name.push(c);
+            }
+        }
+    } else {
+        return;
+    }
+    let consumed: Vec<KeyCode> = keyboard_input.get_just_pressed().copied().collect();
+    for key in consumed {
+        keyboard_input.reset(key);
+    }
+}
+
+/// Clicking anywhere but the rename button, on the board or a menu, ends renaming
+fn end_rename_on_click(
+    interaction_query: Query<
+        (&Interaction, Option<&CheckpointButton>),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    if checkpoints.renaming.is_none() {
+        return;
+    }
+    let clicked_away = interaction_query.iter().any(|(interaction, button)| {
+        *interaction == Interaction::Clicked && !matches!(button, Some(CheckpointButton::Rename(_)))
+    });
+    if clicked_away {
+        finish_rename(&mut checkpoints);
+    }
+}
+
+/// Checkpoints belong to a puzzle, so a new one starts without any
+fn clear_checkpoints(mut new_game: EventReader<NewGame>, mut checkpoints: ResMut<Checkpoints>) {
+    if new_game.iter().count() > 0 {
+        *checkpoints = Checkpoints::default();
+    }
+}
+
+fn update_compare_highlights(
+    checkpoints: Res<Checkpoints>,
+    changed_query: Query<(), Changed<Cell>>,
+    query: Query<(&Cell, &CellPosition)>,
+    mut highlights: ResMut<Highlights>,
+) {
+    if !checkpoints.is_changed() && changed_query.is_empty() {
+        return;
+    }
+    match checkpoints.comparing.and_then(|i| checkpoints.list.get(i)) {
+        Some(checkpoint) => {
+            let current = BoardSnapshot::new(query.iter());
+            let positions: Vec<_> = differences(&checkpoint.cells, &current).collect();
+            highlights.set(HighlightKind::Compare, positions);
+        }
+        None if checkpoints.is_changed() => highlights.clear(HighlightKind::Compare),
+        None => {}
+    }
+}
@@ -1,4 +1,20 @@
-use crate::{board::{Cell, CellPosition}, SudokuContainer};
+use crate::{
+    analysis::FindWrongMove,
+    assist::grid_from,
+    board::{BoardSelected, Cell, CellPosition, ValueOrigin},
+    game::{AssistUsage, GameStatus},
+    history::{BoardSnapshot, History},
+    save::fill_grid,
+    settings::Preferences,
+    state::BuilderRequest,
+    trainer::{needs_technique, Training, MAX_ATTEMPTS},
+    variant::{
+        find_solution, random_borders, random_compare_clues, random_lines, random_outside_clues,
+        BorderSetting, CompareSetting, ExtraRegions, Feasibility, GlobalRules, OutsideSetting,
+        VariantRules, SEARCH_BUDGET,
+    },
+    AppState, SudokuContainer, SudokuSolution,
+};
 use bevy::{
     prelude::*,
     tasks::{AsyncComputeTaskPool, Task},
@@ -14,9 +30,13 @@ impl Plugin for EventPlugin {
             .add_event::<NewGame>()
             .add_event::<NewGameReady>()
             .add_event::<Solve>()
+            .add_event::<Check>()
+            .add_event::<Reveal>()
             .add_system(reset_events)
             .add_system(new_game_events)
             .add_system(solve_events)
+            .add_system(check_events)
+            .add_system(reveal_events)
             .add_system(handle_new_game_task);
     }
 }
@@ -26,23 +46,129 @@ pub struct NewGame;
 pub struct NewGameReady;
 pub struct Reset;
 pub struct Solve;
+/// Flag wrong entries without revealing the answer
+pub struct Check;
+/// Fill in part of the solution around the selected cell
+pub struct Reveal(pub RevealScope);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RevealScope {
+    Cell,
+    Box,
+    Row,
+}
+
+impl RevealScope {
+    fn contains(&self, selected: &CellPosition, pos: &CellPosition) -> bool {
+        match self {
+            RevealScope::Cell => selected == pos,
+            RevealScope::Box => selected.x / 3 == pos.x / 3 && selected.y / 3 == pos.y / 3,
+            RevealScope::Row => selected.y == pos.y,
+        }
+    }
+}
+
+/// The puzzle, its solution and rules, or why no grid fits the rules
+type NewGameResult = Result<(Sudoku<DefaultConstraint>, SudokuGrid, VariantRules), String>;
 
 #[derive(Resource)]
-pub struct NewGameTask(Task<Sudoku<DefaultConstraint>>);
+pub struct NewGameTask(Task<NewGameResult>);
 
 fn new_game_events(
     mut commands: Commands, 
     mut new_game_event: EventReader<NewGame>,
-
+    training: Res<Training>,
+    preferences: Res<Preferences>,
+    mut request: ResMut<BuilderRequest>,
 ) {
     for _ in new_game_event.iter() {
         let thread_pool = AsyncComputeTaskPool::get();
+        let target = training.target;
+        // techniques are trained on classic puzzles
+        let (kinds, border_setting, outside_setting, regions, compare_setting, global) =
+            match target {
+                Some(_) => (
+                    Vec::new(),
+                    BorderSetting::Off,
+                    OutsideSetting::Off,
+                    ExtraRegions::default(),
+                    CompareSetting::Off,
+                    GlobalRules::default(),
+                ),
+                None => (
+                    preferences.lines.kinds(),
+                    preferences.borders,
+                    preferences.outside,
+                    preferences.regions.regions(),
+                    preferences.compare,
+                    preferences.global,
+                ),
+            };
+        // the builder already searched for a grid keeping its rules
+        let mut seed = request.grid.take().filter(|_| target.is_none());
         let task = thread_pool.spawn(async move {
             let mut generator = Generator::new_default();
-            let mut sudoku = generator.generate(3, 3, DefaultConstraint).unwrap();
             let mut reducer = Reducer::new_default();
-            reducer.reduce(&mut sudoku);
-            sudoku
+            let mut rng = rand::thread_rng();
+            let mut attempts = 0;
+            // extra regions and global rules need a solution that already keeps them
+            let base = VariantRules {
+                regions,
+                global,
+                ..default()
+            };
+            loop {
+                let mut sudoku = if base.is_classic() {
+                    generator.generate(3, 3, DefaultConstraint).unwrap()
+                } else {
+                    let values = match seed.take() {
+                        Some(values) => values,
+                        None => match find_solution(&base, &mut rng, SEARCH_BUDGET) {
+                            Feasibility::Solvable(values) => values,
+                            feasibility => {
+                                return Err(feasibility.problem().unwrap_or_default().to_string())
+                            }
+                        },
+                    };
+                    let mut sudoku = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+                    fill_grid(sudoku.grid_mut(), &values);
+                    sudoku
+                };
+                // keep the full grid for mistake checking before clues are removed
+                let solution = sudoku.grid().clone();
+                // variant clues are picked to fit the solution, then givens removed while it stays unique with them
+                let values = grid_from(&solution);
+                let (borders, negative) = random_borders(&values, border_setting, &mut rng);
+                let (parity, signs) = random_compare_clues(&values, compare_setting, &mut rng);
+                let rules = VariantRules {
+                    lines: random_lines(&values, &kinds, &mut rng),
+                    borders,
+                    negative,
+                    outside: random_outside_clues(&values, outside_setting, &mut rng),
+                    regions,
+                    parity,
+                    signs,
+                    global,
+                };
+                if rules.is_classic() {
+                    reducer.reduce(&mut sudoku);
+                } else {
+                    let mut puzzle = Sudoku::new_empty(3, 3, rules.constraint()).unwrap();
+                    *puzzle.grid_mut() = solution.clone();
+                    reducer.reduce(&mut puzzle);
+                    *sudoku.grid_mut() = puzzle.grid().clone();
+                }
+                attempts += 1;
+                // training keeps generating until the technique is the hardest step
+                if needs_technique(&grid_from(sudoku.grid()), &rules, target) {
+                    return Ok((sudoku, solution, rules));
+                }
+                if let Some(target) = target.filter(|_| attempts >= MAX_ATTEMPTS) {
+                    return Err(format!(
+                        "No puzzle needing {target} turned up in {attempts} tries, pick another technique"
+                    ));
+                }
+            }
         });
 
         commands.insert_resource(NewGameTask(task));
@@ -53,14 +179,33 @@ fn handle_new_game_task(
     mut commands: Commands, 
     task: Option<ResMut<NewGameTask>>,
     mut new_game_ready_event: EventWriter<NewGameReady>,
+    mut request: ResMut<BuilderRequest>,
+    mut training: ResMut<Training>,
+    mut app_state: ResMut<State<AppState>>,
 ) {
     if let Some(mut task) = task {
-        if let Some(sudoku) = future::block_on(future::poll_once(&mut task.0)) {
-            commands.insert_resource(SudokuContainer(sudoku));
+        if let Some(result) = future::block_on(future::poll_once(&mut task.0)) {
             commands.remove_resource::<NewGameTask>();
-            new_game_ready_event.send(NewGameReady);
+            match result {
+                Ok((sudoku, solution, rules)) => {
+                    commands.insert_resource(SudokuContainer(sudoku));
+                    commands.insert_resource(rules);
+                    commands.insert_resource(SudokuSolution(Some(solution)));
+                    new_game_ready_event.send(NewGameReady);
+                }
+                // back to where the rules or technique can be changed
+                Err(problem) if training.target.is_some() => {
+                    training.target = None;
+                    training.problem = Some(problem);
+                    app_state.set(AppState::Menu).unwrap();
+                }
+                Err(problem) => {
+                    request.problem = Some(problem);
+                    app_state.set(AppState::Builder).unwrap();
+                }
+            }
         }
-    }    
+    }
 }
 
 fn reset_events(mut reset_event: EventReader<Reset>, mut query: Query<&mut Cell>) {
@@ -74,9 +219,13 @@ fn reset_events(mut reset_event: EventReader<Reset>, mut query: Query<&mut Cell>
 fn solve_events(
     mut solve_events: EventReader<Solve>,
     mut query: Query<(&mut Cell, &CellPosition)>,
+    mut assists: ResMut<AssistUsage>,
+    mut history: ResMut<History>,
+    mut find_wrong_move: EventWriter<FindWrongMove>,
+    rules: Res<VariantRules>,
 ) {
     for _ in solve_events.iter() {
-        let mut sudoku = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+        let mut sudoku = Sudoku::new_empty(3, 3, rules.constraint()).unwrap();
         let grid = sudoku.grid_mut();
         for (cell, cell_pos) in query.iter_mut() {
             if let Some(value) = cell.value() {
@@ -85,20 +234,25 @@ fn solve_events(
         }
         if !sudoku.is_valid() {
             error!("Sudoku is not valid");
+            find_wrong_move.send(FindWrongMove);
             return;
         }
 
         let solver = BacktrackingSolver;
         match solver.solve(&sudoku) {
             Solution::Impossible => {
-                error!("Sudoku is Ambiguous");
+                error!("Sudoku is Impossible");
+                find_wrong_move.send(FindWrongMove);
             }
             Solution::Unique(solution) => {
+                assists.solves += 1;
+                let before = BoardSnapshot::new(query.iter());
                 for (mut cell, cell_pos) in query.iter_mut() {
                     if let Ok(grid_cell) = solution.get_cell(cell_pos.x, cell_pos.y) {
-                        cell.set_value(grid_cell.unwrap().into());
+                        cell.set_value(grid_cell.unwrap().into(), ValueOrigin::Solver);
                     }
                 }
+                history.record(&before, &BoardSnapshot::new(query.iter()));
             }
             Solution::Ambiguous => {
                 error!("Sudoku is Ambiguous");
@@ -106,3 +260,70 @@ fn solve_events(
         }
     }
 }
+
+fn check_events(
+    mut check_events: EventReader<Check>,
+    solution: Res<SudokuSolution>,
+    status: Res<GameStatus>,
+    mut assists: ResMut<AssistUsage>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    for _ in check_events.iter() {
+        if *status != GameStatus::Playing {
+            continue;
+        }
+
+        assists.checks += 1;
+        for (mut cell, pos) in query.iter_mut() {
+            if cell.is_disabled() || cell.is_mistake() {
+                continue;
+            }
+            if let (Some(value), Some(expected)) = (cell.value(), solution.get(pos.x, pos.y)) {
+                if value != expected {
+                    cell.set_mistake(true);
+                }
+            }
+        }
+    }
+}
+
+fn reveal_events(
+    mut reveal_events: EventReader<Reveal>,
+    board_selected: Res<BoardSelected>,
+    solution: Res<SudokuSolution>,
+    status: Res<GameStatus>,
+    mut assists: ResMut<AssistUsage>,
+    mut history: ResMut<History>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    for event in reveal_events.iter() {
+        if *status != GameStatus::Playing {
+            continue;
+        }
+        let selected = match board_selected.entity.and_then(|e| query.get(e).ok()) {
+            Some((_, pos)) => *pos,
+            None => {
+                warn!("No cell selected to reveal");
+                continue;
+            }
+        };
+
+        let before = BoardSnapshot::new(query.iter());
+        let mut revealed = false;
+        for (mut cell, pos) in query.iter_mut() {
+            if cell.is_disabled() || !event.0.contains(&selected, pos) {
+                continue;
+            }
+            if let Some(expected) = solution.get(pos.x, pos.y) {
+                if cell.value() != Some(expected) {
+                    cell.set_value(expected, ValueOrigin::Hint);
+                    revealed = true;
+                }
+            }
+        }
+        if revealed {
+            assists.record_reveal(event.0);
+            history.record(&before, &BoardSnapshot::new(query.iter()));
+        }
+    }
+}
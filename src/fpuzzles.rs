@@ -0,0 +1,1187 @@
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+use serde_json::{json, Map, Value as Json};
+use sudoku_variants::{constraint::DefaultConstraint, Sudoku, SudokuGrid};
+
+use crate::{
+    assist::{grid_from, Grid},
+    board::{CellPosition, Value},
+    save::fill_grid,
+    storage,
+    trainer::Training,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::{
+        find_solutions, BorderClue, BorderKind, ExtraRegions, Line, LineKind, NegativeBorders,
+        OutsideClue, OutsideKind, ParityClue, Side, VariantRules, SEARCH_BUDGET,
+    },
+    AppState, SudokuContainer, SudokuSolution,
+};
+
+/// Puzzle read by the import button, in the data directory
+const IMPORT_FILE: &str = "import.json";
+const EXPORT_FILE: &str = "export.json";
+/// Keys that only change how a puzzle looks, or settings of the editor
+const COSMETIC_KEYS: [&str; 9] = [
+    "title",
+    "author",
+    "ruleset",
+    "solution",
+    "text",
+    "circle",
+    "rectangle",
+    "disabledlogic",
+    "truecandidatesoptions",
+];
+
+pub struct FPuzzlesPlugin;
+
+impl Plugin for FPuzzlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ImportPuzzle>()
+            .add_event::<ExportPuzzle>()
+            .add_system(import_events)
+            .add_system(handle_import_task)
+            .add_system(export_events)
+            .add_system(report_button_system)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(show_import_report));
+    }
+}
+
+/// Event to start a game from the f-puzzles file in the data directory
+pub struct ImportPuzzle;
+/// Event to write the current puzzle as f-puzzles JSON
+pub struct ExportPuzzle;
+
+/// Resource marking the puzzle resources as imported, so loading starts them instead of generating
+#[derive(Resource)]
+pub struct ImportedPuzzle;
+
+/// Givens and rules of a puzzle read from a file, and the features it had to leave out
+type Imported = (Grid, VariantRules, Vec<String>);
+
+/// The puzzle ready to start, its solution and rules, and the features left out
+type ImportedGame = (
+    Sudoku<DefaultConstraint>,
+    SudokuGrid,
+    VariantRules,
+    Vec<String>,
+);
+type ImportResult = Result<ImportedGame, Vec<String>>;
+
+#[derive(Resource)]
+struct ImportTask(Task<ImportResult>);
+
+/// Resource for the features an imported puzzle was loaded without, shown once it is on the board
+#[derive(Resource)]
+struct ImportReport(Vec<String>);
+
+#[derive(Component)]
+struct ReportPanel;
+
+#[derive(Component)]
+struct ReportClose;
+
+/// "R3C5" as (row, column), rows and columns 0 and 10 are just outside the grid
+fn parse_cell(name: &str) -> Option<(i32, i32)> {
+    let (row, column) = name.strip_prefix('R')?.split_once('C')?;
+    Some((row.parse().ok()?, column.parse().ok()?))
+}
+
+fn inside((row, column): (i32, i32)) -> Option<CellPosition> {
+    ((1..=9).contains(&row) && (1..=9).contains(&column))
+        .then(|| CellPosition::new(column as usize - 1, row as usize - 1))
+}
+
+fn cell_name(pos: CellPosition) -> String {
+    format!("R{}C{}", pos.y + 1, pos.x + 1)
+}
+
+fn cell_names(cells: &[CellPosition]) -> Vec<String> {
+    cells.iter().map(|pos| cell_name(*pos)).collect()
+}
+
+/// Numbers are written as strings as often as not
+fn number(value: &Json) -> Option<usize> {
+    match value {
+        Json::Number(number) => number.as_u64().map(|n| n as usize),
+        Json::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn read_cell(cell: &Json) -> Result<CellPosition, String> {
+    cell.as_str()
+        .and_then(parse_cell)
+        .and_then(inside)
+        .ok_or_else(|| format!("{cell} isn't a cell in the grid"))
+}
+
+fn read_cells(list: Option<&Json>) -> Result<Vec<CellPosition>, String> {
+    list.and_then(Json::as_array)
+        .ok_or_else(|| "Expected a list of cells".to_string())?
+        .iter()
+        .map(read_cell)
+        .collect()
+}
+
+fn read_grid(grid: &Json, givens: &mut Grid) -> Result<(), String> {
+    let rows = grid
+        .as_array()
+        .ok_or_else(|| "The grid isn't a list of rows".to_string())?;
+    let mut irregular = false;
+    for (y, row) in rows.iter().enumerate().take(9) {
+        for (x, cell) in row.as_array().into_iter().flatten().enumerate().take(9) {
+            // values the setter didn't give are someone's solving progress
+            if cell.get("given").and_then(Json::as_bool) == Some(true) {
+                givens[y][x] = cell
+                    .get("value")
+                    .and_then(number)
+                    .filter(|value| (1..=9).contains(value))
+                    .map(Value::from);
+            }
+            if let Some(region) = cell.get("region").and_then(number) {
+                irregular |= region != y / 3 * 3 + x / 3;
+            }
+        }
+    }
+    if irregular {
+        return Err("Irregular regions aren't supported".to_string());
+    }
+    Ok(())
+}
+
+fn read_lines(entries: &[Json], kind: LineKind, lines: &mut Vec<Line>) -> Result<(), String> {
+    for entry in entries {
+        for path in entry
+            .get("lines")
+            .and_then(Json::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let cells = read_cells(Some(path))?;
+            if cells.len() < 2 {
+                return Err(format!("A {kind} line needs at least two cells"));
+            }
+            lines.push(Line { kind, cells });
+        }
+    }
+    Ok(())
+}
+
+/// The circle is the first cell of an arrow line
+fn read_arrows(entries: &[Json], lines: &mut Vec<Line>) -> Result<(), String> {
+    for entry in entries {
+        let circle = read_cells(entry.get("cells"))?;
+        let shafts = entry
+            .get("lines")
+            .and_then(Json::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if circle.len() != 1 || shafts.len() != 1 {
+            return Err("Arrows with a long circle or several shafts aren't supported".to_string());
+        }
+        let mut cells = read_cells(Some(&shafts[0]))?;
+        if cells.first() != Some(&circle[0]) {
+            cells.insert(0, circle[0]);
+        }
+        lines.push(Line {
+            kind: LineKind::Arrow,
+            cells,
+        });
+    }
+    Ok(())
+}
+
+fn read_pair(entry: &Json) -> Result<(CellPosition, CellPosition), String> {
+    match read_cells(entry.get("cells"))?.as_slice() {
+        [a, b] if a.x.abs_diff(b.x) + a.y.abs_diff(b.y) == 1 => Ok((*a, *b)),
+        _ => Err("Border clues must sit between two neighboring cells".to_string()),
+    }
+}
+
+/// Kropki dots, a dot may carry a difference or ratio other than the usual one
+fn read_dots(
+    entries: &[Json],
+    kind: BorderKind,
+    usual: usize,
+    borders: &mut Vec<BorderClue>,
+) -> Result<(), String> {
+    for entry in entries {
+        let (a, b) = read_pair(entry)?;
+        match entry.get("value").and_then(number) {
+            Some(value) if value != usual => {
+                return Err(format!("Dots with a value of {value} aren't supported"));
+            }
+            _ => borders.push(BorderClue::new(kind, a, b)),
+        }
+    }
+    Ok(())
+}
+
+fn read_xv(entries: &[Json], borders: &mut Vec<BorderClue>) -> Result<(), String> {
+    for entry in entries {
+        let (a, b) = read_pair(entry)?;
+        let kind = match entry.get("value").and_then(Json::as_str) {
+            Some("X") => BorderKind::X,
+            Some("V") => BorderKind::V,
+            other => return Err(format!("{other:?} isn't an X or a V")),
+        };
+        borders.push(BorderClue::new(kind, a, b));
+    }
+    Ok(())
+}
+
+fn read_clue_value(entry: &Json) -> Result<usize, String> {
+    entry
+        .get("value")
+        .and_then(number)
+        .ok_or_else(|| "An outside clue has no value".to_string())
+}
+
+fn read_outside(
+    entries: &[Json],
+    kind: OutsideKind,
+    outside: &mut Vec<OutsideClue>,
+) -> Result<(), String> {
+    for entry in entries {
+        let cell = entry
+            .get("cell")
+            .and_then(Json::as_str)
+            .and_then(parse_cell);
+        let (side, index) = match cell {
+            Some((0, column @ 1..=9)) => (Side::Top, column as usize - 1),
+            Some((10, column @ 1..=9)) => (Side::Bottom, column as usize - 1),
+            Some((row @ 1..=9, 0)) => (Side::Left, row as usize - 1),
+            Some((row @ 1..=9, 10)) => (Side::Right, row as usize - 1),
+            _ => return Err("An outside clue isn't next to a row or column".to_string()),
+        };
+        outside.push(OutsideClue {
+            kind,
+            side,
+            index,
+            value: read_clue_value(entry)?,
+        });
+    }
+    Ok(())
+}
+
+fn read_little_killers(entries: &[Json], outside: &mut Vec<OutsideClue>) -> Result<(), String> {
+    let invalid = || "A little killer clue doesn't point along a diagonal".to_string();
+    for entry in entries {
+        let (row, column) = entry
+            .get("cell")
+            .and_then(Json::as_str)
+            .and_then(parse_cell)
+            .ok_or_else(invalid)?;
+        let (down, right) = match entry.get("direction").and_then(Json::as_str) {
+            Some("UL") => (-1, -1),
+            Some("UR") => (-1, 1),
+            Some("DL") => (1, -1),
+            Some("DR") => (1, 1),
+            _ => return Err(invalid()),
+        };
+        let first = inside((row + down, column + right)).ok_or_else(invalid)?;
+        let (side, index, turn) = match (row, column) {
+            (0, _) => (Side::Top, first.x, right),
+            (10, _) => (Side::Bottom, first.x, right),
+            (_, 0) => (Side::Left, first.y, down),
+            (_, 10) => (Side::Right, first.y, down),
+            _ => return Err(invalid()),
+        };
+        let clue = OutsideClue {
+            kind: OutsideKind::LittleKiller { turn },
+            side,
+            index,
+            value: read_clue_value(entry)?,
+        };
+        // the diagonal has to come in through the side the clue is on
+        if clue.cells().first() != Some(&first) {
+            return Err(invalid());
+        }
+        outside.push(clue);
+    }
+    Ok(())
+}
+
+fn read_parity(entries: &[Json], even: bool, parity: &mut Vec<ParityClue>) -> Result<(), String> {
+    for entry in entries {
+        let cell = read_cell(entry.get("cell").unwrap_or(&Json::Null))?;
+        parity.push(ParityClue { cell, even });
+    }
+    Ok(())
+}
+
+/// Only the four Windoku windows have a rule of their own here
+fn read_extra_regions(entries: &[Json], regions: &mut ExtraRegions) -> Result<(), String> {
+    let sorted = |mut cells: Vec<CellPosition>| {
+        cells.sort_by_key(|pos| (pos.y, pos.x));
+        cells
+    };
+    let mut found = entries
+        .iter()
+        .map(|entry| read_cells(entry.get("cells")).map(sorted))
+        .collect::<Result<Vec<_>, _>>()?;
+    let windoku = ExtraRegions {
+        windoku: true,
+        disjoint: false,
+    };
+    let mut windows = windoku
+        .regions()
+        .into_iter()
+        .map(sorted)
+        .collect::<Vec<_>>();
+    found.sort_by_key(|cells| cells.first().map(|pos| (pos.y, pos.x)));
+    windows.sort_by_key(|cells| cells.first().map(|pos| (pos.y, pos.x)));
+    if found != windows {
+        return Err(
+            "Extra regions other than the four Windoku windows aren't supported".to_string(),
+        );
+    }
+    regions.windoku = true;
+    Ok(())
+}
+
+fn feature_name(key: &str) -> &str {
+    match key {
+        "killercage" => "Killer cages",
+        "minimum" => "Minimum cells",
+        "maximum" => "Maximum cells",
+        "quadruple" => "Quadruples",
+        "clone" => "Clones",
+        "betweenline" => "Between lines",
+        "cage" => "Cages",
+        "line" => "Custom lines",
+        _ => key,
+    }
+}
+
+/// Givens and rules from f-puzzles or SudokuPad JSON, with the features that were left out.
+/// Anything that makes the puzzle unreadable is an error instead
+pub fn import(text: &str) -> Result<Imported, Vec<String>> {
+    let text = text.trim();
+    if !text.starts_with('{') {
+        return Err(vec![
+            "Expected f-puzzles or SudokuPad JSON, shared links need decompressing first"
+                .to_string(),
+        ]);
+    }
+    let puzzle: Map<String, Json> =
+        serde_json::from_str(text).map_err(|e| vec![format!("Invalid JSON: {e}")])?;
+    if puzzle.contains_key("cells") && !puzzle.contains_key("grid") {
+        return import_sudokupad(&puzzle);
+    }
+    let size = puzzle.get("size").and_then(number).unwrap_or(9);
+    if size != 9 {
+        return Err(vec![format!(
+            "Only 9x9 grids are supported, this one is {size}x{size}"
+        )]);
+    }
+
+    let flag = |key: &str| puzzle.get(key).and_then(Json::as_bool).unwrap_or(false);
+    let mut problems = Vec::new();
+    let mut left_out = Vec::new();
+    let mut givens = Grid::default();
+    let mut rules = VariantRules::default();
+    if let Err(problem) = read_grid(puzzle.get("grid").unwrap_or(&Json::Null), &mut givens) {
+        problems.push(problem);
+    }
+
+    match (flag("diagonal+"), flag("diagonal-")) {
+        (true, true) => rules.global.diagonal = true,
+        (false, false) => {}
+        _ => left_out.push("A single diagonal, only both are supported".to_string()),
+    }
+    rules.global.anti_knight = flag("antiknight");
+    rules.global.anti_king = flag("antiking");
+    rules.regions.disjoint = flag("disjointgroups");
+
+    // non-consecutive exempts the pairs with a white dot, so with dots it is a negative constraint
+    let negative = puzzle
+        .get("negative")
+        .and_then(Json::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let is_negative = |key: &str| negative.iter().any(|entry| entry.as_str() == Some(key));
+    let white_negative = flag("nonconsecutive") || is_negative("difference");
+    let white_dots = puzzle
+        .get("difference")
+        .and_then(Json::as_array)
+        .map_or(false, |dots| !dots.is_empty());
+    rules.negative = NegativeBorders {
+        kropki: is_negative("ratio") && white_negative,
+        white: white_negative && !is_negative("ratio") && white_dots,
+        xv: is_negative("xv"),
+    };
+    rules.global.non_consecutive = white_negative && !is_negative("ratio") && !white_dots;
+    if is_negative("ratio") && !white_negative {
+        left_out.push("Negative black dots without negative white dots".to_string());
+    }
+
+    for (key, value) in puzzle.iter() {
+        let entries = match value {
+            Json::Array(entries) if !entries.is_empty() => entries,
+            Json::Bool(true) => {
+                let known = [
+                    "diagonal+",
+                    "diagonal-",
+                    "antiknight",
+                    "antiking",
+                    "nonconsecutive",
+                    "disjointgroups",
+                ];
+                if !known.contains(&key.as_str()) && !COSMETIC_KEYS.contains(&key.as_str()) {
+                    left_out.push(format!("The {key} rule"));
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let read = match key.as_str() {
+            "grid" | "negative" => Ok(()),
+            "thermometer" => read_lines(entries, LineKind::Thermo, &mut rules.lines),
+            "palindrome" => read_lines(entries, LineKind::Palindrome, &mut rules.lines),
+            "renban" => read_lines(entries, LineKind::Renban, &mut rules.lines),
+            "whispers" => read_lines(entries, LineKind::Whisper, &mut rules.lines),
+            "arrow" => read_arrows(entries, &mut rules.lines),
+            "difference" => read_dots(entries, BorderKind::White, 1, &mut rules.borders),
+            "ratio" => read_dots(entries, BorderKind::Black, 2, &mut rules.borders),
+            "xv" => read_xv(entries, &mut rules.borders),
+            "sandwichsum" => read_outside(entries, OutsideKind::Sandwich, &mut rules.outside),
+            "xsum" => read_outside(entries, OutsideKind::XSum, &mut rules.outside),
+            "skyscraper" => read_outside(entries, OutsideKind::Skyscraper, &mut rules.outside),
+            "littlekillersum" => read_little_killers(entries, &mut rules.outside),
+            "odd" => read_parity(entries, false, &mut rules.parity),
+            "even" => read_parity(entries, true, &mut rules.parity),
+            "extraregion" => read_extra_regions(entries, &mut rules.regions),
+            key if COSMETIC_KEYS.contains(&key) => Ok(()),
+            key => {
+                left_out.push(format!("{} ({})", feature_name(key), entries.len()));
+                Ok(())
+            }
+        };
+        if let Err(problem) = read {
+            problems.push(problem);
+        }
+    }
+
+    if problems.is_empty() {
+        Ok((givens, rules, left_out))
+    } else {
+        Err(problems)
+    }
+}
+
+/// SudokuPad keys that hold no rules
+const SUDOKUPAD_IGNORED_KEYS: [&str; 4] = ["id", "cellSize", "metadata", "settings"];
+/// Cages SudokuPad uses to carry the title, rules and the like, not killer cages
+const SUDOKUPAD_METADATA: [&str; 5] = ["title:", "author:", "rules:", "solution:", "msgcorrect:"];
+
+/// SudokuPad draws every line the same way, puzzles converted from f-puzzles keep its colors
+fn sudokupad_line_kind(color: &str) -> Option<LineKind> {
+    match color.get(..7)?.to_uppercase().as_str() {
+        "#CFCFCF" => Some(LineKind::Thermo),
+        "#F067F0" => Some(LineKind::Renban),
+        "#67F067" => Some(LineKind::Whisper),
+        _ => None,
+    }
+}
+
+/// SudokuPad points are [row, column] in cells from the top left corner
+fn sudokupad_point(point: &Json) -> Option<(f64, f64)> {
+    match point.as_array()?.as_slice() {
+        [row, column] => Some((row.as_f64()?, column.as_f64()?)),
+        _ => None,
+    }
+}
+
+/// The cell a point is the center of
+fn point_cell((row, column): (f64, f64)) -> Option<CellPosition> {
+    let centered = |n: f64| (n - n.floor() - 0.5).abs() < 0.01;
+    if !centered(row) || !centered(column) {
+        return None;
+    }
+    inside((row.floor() as i32 + 1, column.floor() as i32 + 1))
+}
+
+/// The two cells either side of the border a point is the middle of
+fn point_border((row, column): (f64, f64)) -> Option<(CellPosition, CellPosition)> {
+    let edge = |n: f64| (n - n.round()).abs() < 0.01;
+    let (r, c) = (row.floor() as i32 + 1, column.floor() as i32 + 1);
+    if edge(column) && !edge(row) {
+        let c = column.round() as i32;
+        Some((inside((r, c))?, inside((r, c + 1))?))
+    } else if edge(row) && !edge(column) {
+        let r = row.round() as i32;
+        Some((inside((r, c))?, inside((r + 1, c))?))
+    } else {
+        None
+    }
+}
+
+fn sudokupad_list<'a>(puzzle: &'a Map<String, Json>, key: &str) -> &'a [Json] {
+    puzzle
+        .get(key)
+        .and_then(Json::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Givens, regions, lines and border marks from SudokuPad JSON.
+/// Lines are told apart by the colors f-puzzles gives them, anything else is left out
+fn import_sudokupad(puzzle: &Map<String, Json>) -> Result<Imported, Vec<String>> {
+    let rows = sudokupad_list(puzzle, "cells");
+    if rows.len() != 9
+        || rows
+            .iter()
+            .any(|row| row.as_array().map(Vec::len) != Some(9))
+    {
+        return Err(vec!["Only 9x9 grids are supported".to_string()]);
+    }
+    let mut givens = Grid::default();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, cell) in row.as_array().into_iter().flatten().enumerate() {
+            givens[y][x] = cell
+                .get("value")
+                .and_then(number)
+                .filter(|value| (1..=9).contains(value))
+                .map(Value::from);
+        }
+    }
+
+    for region in sudokupad_list(puzzle, "regions") {
+        let cells = region
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|cell| match cell.as_array().map(Vec::as_slice) {
+                Some([row, column]) => Some((row.as_u64()?, column.as_u64()?)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        let boxed = cells.len() == 9
+            && cells
+                .iter()
+                .all(|(row, column)| (row / 3, column / 3) == (cells[0].0 / 3, cells[0].1 / 3));
+        if !boxed {
+            return Err(vec!["Irregular regions aren't supported".to_string()]);
+        }
+    }
+
+    let mut rules = VariantRules::default();
+    let mut left_out = Vec::new();
+    let mut unknown_lines = 0;
+    let mut unknown_marks = 0;
+    let marks: Vec<&Json> = sudokupad_list(puzzle, "overlays")
+        .iter()
+        .chain(sudokupad_list(puzzle, "underlays"))
+        .collect();
+    let mut bulbs = Vec::new();
+    let mut used_bulbs = Vec::new();
+    for mark in marks.iter() {
+        let center = mark.get("center").and_then(sudokupad_point);
+        let rounded = mark.get("rounded").and_then(Json::as_bool) == Some(true);
+        let color = mark
+            .get("backgroundColor")
+            .and_then(Json::as_str)
+            .and_then(|color| color.get(..7))
+            .map(str::to_uppercase);
+        let text = mark.get("text").and_then(Json::as_str).map(str::trim);
+        let border = center.and_then(point_border);
+        let kind = match (border, text, rounded, color.as_deref()) {
+            (Some(_), Some("X"), _, _) => Some(BorderKind::X),
+            (Some(_), Some("V"), _, _) => Some(BorderKind::V),
+            (Some(_), None | Some(""), true, Some("#FFFFFF")) => Some(BorderKind::White),
+            (Some(_), None | Some(""), true, Some("#000000")) => Some(BorderKind::Black),
+            _ => None,
+        };
+        match (kind, border) {
+            (Some(kind), Some((a, b))) => rules.borders.push(BorderClue::new(kind, a, b)),
+            _ => match center.and_then(point_cell) {
+                Some(cell) if rounded && text.map_or(true, str::is_empty) => bulbs.push(cell),
+                _ => unknown_marks += 1,
+            },
+        }
+    }
+
+    for line in sudokupad_list(puzzle, "lines") {
+        let cells = line
+            .get("wayPoints")
+            .and_then(Json::as_array)
+            .into_iter()
+            .flatten()
+            .map(|point| sudokupad_point(point).and_then(point_cell))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        let kind = line
+            .get("color")
+            .and_then(Json::as_str)
+            .and_then(sudokupad_line_kind);
+        let bulb = cells.first().copied().filter(|first| bulbs.contains(first));
+        match kind {
+            // a grey line is only a thermometer with a bulb at its start
+            Some(LineKind::Thermo) if bulb.is_none() => unknown_lines += 1,
+            Some(kind) if cells.len() >= 2 => {
+                used_bulbs.extend(bulb.filter(|_| kind == LineKind::Thermo));
+                rules.lines.push(Line { kind, cells });
+            }
+            _ => unknown_lines += 1,
+        }
+    }
+    // circles that didn't turn out to be thermometer bulbs may be clues of their own
+    unknown_marks += bulbs
+        .iter()
+        .filter(|bulb| !used_bulbs.contains(*bulb))
+        .count();
+
+    let cages = sudokupad_list(puzzle, "cages")
+        .iter()
+        .filter(|cage| {
+            let value = cage.get("value").and_then(Json::as_str).unwrap_or_default();
+            !SUDOKUPAD_METADATA.iter().any(|key| value.starts_with(key))
+        })
+        .count();
+    for (name, count) in [
+        ("Lines of other kinds", unknown_lines),
+        ("Shapes and text that may be clues", unknown_marks),
+        ("Cages", cages),
+        ("Arrows", sudokupad_list(puzzle, "arrows").len()),
+    ] {
+        if count > 0 {
+            left_out.push(format!("{name} ({count})"));
+        }
+    }
+    let handled = [
+        "cells",
+        "regions",
+        "lines",
+        "overlays",
+        "underlays",
+        "cages",
+        "arrows",
+    ];
+    for (key, value) in puzzle.iter() {
+        let listed = value
+            .as_array()
+            .map_or(false, |entries| !entries.is_empty());
+        if listed
+            && !handled.contains(&key.as_str())
+            && !SUDOKUPAD_IGNORED_KEYS.contains(&key.as_str())
+        {
+            left_out.push(format!("{key} ({})", value.as_array().map_or(0, Vec::len)));
+        }
+    }
+
+    Ok((givens, rules, left_out))
+}
+
+fn push_entry(puzzle: &mut Map<String, Json>, key: &str, entry: Json) {
+    if let Json::Array(entries) = puzzle.entry(key).or_insert_with(|| json!([])) {
+        entries.push(entry);
+    }
+}
+
+fn outside_cell_name(clue: &OutsideClue) -> String {
+    match clue.side {
+        Side::Top => format!("R0C{}", clue.index + 1),
+        Side::Bottom => format!("R10C{}", clue.index + 1),
+        Side::Left => format!("R{}C0", clue.index + 1),
+        Side::Right => format!("R{}C10", clue.index + 1),
+    }
+}
+
+/// f-puzzles JSON for the givens and rules, and the features it had to leave out
+pub fn export(givens: &Grid, rules: &VariantRules) -> (String, Vec<String>) {
+    let mut puzzle = Map::new();
+    let mut left_out = Vec::new();
+    puzzle.insert("size".to_string(), json!(9));
+    let grid = givens
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| match value {
+                    Some(value) => json!({ "value": usize::from(*value), "given": true }),
+                    None => json!({}),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    puzzle.insert("grid".to_string(), json!(grid));
+
+    let global = rules.global;
+    for (key, enabled) in [
+        ("diagonal+", global.diagonal),
+        ("diagonal-", global.diagonal),
+        ("antiknight", global.anti_knight),
+        ("antiking", global.anti_king),
+        (
+            "nonconsecutive",
+            global.non_consecutive || rules.negative.kropki || rules.negative.white,
+        ),
+        ("disjointgroups", rules.regions.disjoint),
+    ] {
+        if enabled {
+            puzzle.insert(key.to_string(), json!(true));
+        }
+    }
+    if rules.regions.windoku {
+        let windoku = ExtraRegions {
+            windoku: true,
+            disjoint: false,
+        };
+        for window in windoku.regions() {
+            push_entry(
+                &mut puzzle,
+                "extraregion",
+                json!({ "cells": cell_names(&window) }),
+            );
+        }
+    }
+
+    for line in rules.lines.iter() {
+        let (key, entry) = match line.kind {
+            LineKind::Thermo => ("thermometer", json!({ "lines": [cell_names(&line.cells)] })),
+            LineKind::Arrow => (
+                "arrow",
+                json!({ "cells": [cell_name(line.cells[0])], "lines": [cell_names(&line.cells)] }),
+            ),
+            LineKind::Palindrome => ("palindrome", json!({ "lines": [cell_names(&line.cells)] })),
+            LineKind::Renban => ("renban", json!({ "lines": [cell_names(&line.cells)] })),
+            LineKind::Whisper => ("whispers", json!({ "lines": [cell_names(&line.cells)] })),
+        };
+        push_entry(&mut puzzle, key, entry);
+    }
+
+    for clue in rules.borders.iter() {
+        let cells = cell_names(&clue.cells);
+        let (key, entry) = match clue.kind {
+            BorderKind::White => ("difference", json!({ "cells": cells })),
+            BorderKind::Black => ("ratio", json!({ "cells": cells })),
+            BorderKind::X => ("xv", json!({ "cells": cells, "value": "X" })),
+            BorderKind::V => ("xv", json!({ "cells": cells, "value": "V" })),
+        };
+        push_entry(&mut puzzle, key, entry);
+    }
+    let mut negative = Vec::new();
+    if rules.negative.kropki {
+        negative.push("ratio");
+    }
+    if rules.negative.xv {
+        negative.push("xv");
+    }
+    if !negative.is_empty() {
+        puzzle.insert("negative".to_string(), json!(negative));
+    }
+
+    for clue in rules.outside.iter() {
+        let cell = outside_cell_name(clue);
+        let value = clue.value.to_string();
+        let (key, entry) = match clue.kind {
+            OutsideKind::Sandwich => ("sandwichsum", json!({ "cell": cell, "value": value })),
+            OutsideKind::Skyscraper => ("skyscraper", json!({ "cell": cell, "value": value })),
+            OutsideKind::XSum => ("xsum", json!({ "cell": cell, "value": value })),
+            OutsideKind::LittleKiller { turn } => {
+                // the clue sits one diagonal step back from the first cell it reads
+                let first = clue.cells()[0];
+                let (down, right) = match clue.side {
+                    Side::Top => (1, turn),
+                    Side::Bottom => (-1, turn),
+                    Side::Left => (turn, 1),
+                    Side::Right => (turn, -1),
+                };
+                let cell = format!(
+                    "R{}C{}",
+                    first.y as i32 + 1 - down,
+                    first.x as i32 + 1 - right
+                );
+                let direction = match (down, right) {
+                    (-1, -1) => "UL",
+                    (-1, _) => "UR",
+                    (_, -1) => "DL",
+                    _ => "DR",
+                };
+                (
+                    "littlekillersum",
+                    json!({ "cell": cell, "direction": direction, "value": value }),
+                )
+            }
+        };
+        push_entry(&mut puzzle, key, entry);
+    }
+
+    for clue in rules.parity.iter() {
+        let key = if clue.even { "even" } else { "odd" };
+        push_entry(&mut puzzle, key, json!({ "cell": cell_name(clue.cell) }));
+    }
+    if !rules.signs.is_empty() {
+        left_out.push(format!("Greater-than signs ({})", rules.signs.len()));
+    }
+
+    let text = serde_json::to_string_pretty(&Json::Object(puzzle)).unwrap_or_default();
+    (text, left_out)
+}
+
+/// Read and solve the puzzle, a puzzle without exactly one solution can't be checked or scored
+fn import_puzzle(text: &str) -> ImportResult {
+    let (givens, rules, left_out) = import(text)?;
+    // the rules left out may be what the puzzle needed, so say which
+    let failed = |problem: &str| {
+        let mut lines = vec![problem.to_string()];
+        if !left_out.is_empty() {
+            lines.push("It was read without these, which may be why:".to_string());
+            lines.extend(left_out.iter().cloned());
+        }
+        lines
+    };
+    let mut puzzle = Sudoku::new_empty(3, 3, rules.constraint()).unwrap();
+    fill_grid(puzzle.grid_mut(), &givens);
+    if !puzzle.is_valid() {
+        return Err(failed("The givens break the puzzle's rules"));
+    }
+    let solution = match find_solutions(&givens, &rules, 2, SEARCH_BUDGET).as_deref() {
+        Some([solution]) => {
+            fill_grid(puzzle.grid_mut(), solution);
+            puzzle.grid().clone()
+        }
+        Some([]) => return Err(failed("The puzzle has no solution")),
+        Some(_) => return Err(failed("The puzzle has more than one solution")),
+        None => {
+            return Err(failed(
+                "The puzzle is too open to check it has one solution",
+            ))
+        }
+    };
+    let mut sudoku = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+    fill_grid(sudoku.grid_mut(), &givens);
+    Ok((sudoku, solution, rules, left_out))
+}
+
+fn import_events(
+    mut commands: Commands,
+    mut import_events: EventReader<ImportPuzzle>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    panel_query: Query<Entity, With<ReportPanel>>,
+) {
+    for _ in import_events.iter() {
+        let text = match storage::load_text(IMPORT_FILE) {
+            Some(text) => text,
+            None => {
+                let path = storage::data_path(IMPORT_FILE);
+                let lines = vec![format!(
+                    "Save f-puzzles or SudokuPad JSON to {}",
+                    path.display()
+                )];
+                spawn_report(
+                    &mut commands,
+                    &panel_query,
+                    &theme,
+                    &font_assets,
+                    "Nothing to import",
+                    &lines,
+                );
+                continue;
+            }
+        };
+        let task = AsyncComputeTaskPool::get().spawn(async move { import_puzzle(&text) });
+        commands.insert_resource(ImportTask(task));
+    }
+}
+
+fn handle_import_task(
+    mut commands: Commands,
+    task: Option<ResMut<ImportTask>>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    panel_query: Query<Entity, With<ReportPanel>>,
+    mut training: ResMut<Training>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let mut task = match task {
+        Some(task) => task,
+        None => return,
+    };
+    let result = match future::block_on(future::poll_once(&mut task.0)) {
+        Some(result) => result,
+        None => return,
+    };
+    commands.remove_resource::<ImportTask>();
+    match result {
+        Ok((sudoku, solution, rules, left_out)) => {
+            if !left_out.is_empty() {
+                commands.insert_resource(ImportReport(left_out));
+            }
+            commands.insert_resource(SudokuContainer(sudoku));
+            commands.insert_resource(rules);
+            commands.insert_resource(SudokuSolution(Some(solution)));
+            commands.insert_resource(ImportedPuzzle);
+            training.target = None;
+            app_state.set(AppState::Loading).unwrap();
+        }
+        Err(problems) => {
+            spawn_report(
+                &mut commands,
+                &panel_query,
+                &theme,
+                &font_assets,
+                "Couldn't import the puzzle, nothing was loaded",
+                &problems,
+            );
+        }
+    }
+}
+
+/// The puzzle still has one solution without them, but the player should know they aren't checked
+fn show_import_report(
+    mut commands: Commands,
+    report: Option<Res<ImportReport>>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    panel_query: Query<Entity, With<ReportPanel>>,
+) {
+    let report = match report {
+        Some(report) => report,
+        None => return,
+    };
+    let mut lines = vec!["These aren't shown or checked while playing:".to_string()];
+    lines.extend(report.0.iter().cloned());
+    spawn_report(
+        &mut commands,
+        &panel_query,
+        &theme,
+        &font_assets,
+        "Imported without some rules",
+        &lines,
+    );
+    commands.remove_resource::<ImportReport>();
+}
+
+fn export_events(
+    mut commands: Commands,
+    mut export_events: EventReader<ExportPuzzle>,
+    sudoku: Res<SudokuContainer>,
+    rules: Res<VariantRules>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    panel_query: Query<Entity, With<ReportPanel>>,
+) {
+    for _ in export_events.iter() {
+        let (text, left_out) = export(&grid_from(sudoku.0.grid()), &rules);
+        let (title, mut lines) = match storage::save_text(EXPORT_FILE, &text) {
+            Some(path) => ("Exported", vec![format!("Saved to {}", path.display())]),
+            None => ("Couldn't export the puzzle", Vec::new()),
+        };
+        if !left_out.is_empty() {
+            lines.push("Left out, f-puzzles has no rule for them:".to_string());
+            lines.extend(left_out);
+        }
+        spawn_report(
+            &mut commands,
+            &panel_query,
+            &theme,
+            &font_assets,
+            title,
+            &lines,
+        );
+    }
+}
+
+fn spawn_report(
+    commands: &mut Commands,
+    panel_query: &Query<Entity, With<ReportPanel>>,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    title: &str,
+    lines: &[String],
+) {
+    for panel in panel_query.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(ReportPanel)
+        .insert(Name::new("Puzzle Report"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: theme.line_thick.into(),
+                    ..default()
+                })
+                .insert(ThickLine)
+                .with_children(|parent| {
+                    for text in std::iter::once(title).chain(lines.iter().map(String::as_str)) {
+                        parent.spawn(TextBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            text: font_assets.status(text, theme),
+                            ..default()
+                        });
+                    }
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                                    margin: UiRect::all(Val::Px(2.0)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: theme.btn_normal.into(),
+                                ..default()
+                            },
+                            Name::new("Close"),
+                            ReportClose,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle {
+                                text: font_assets.status("Close", theme),
+                                ..default()
+                            });
+                        });
+                });
+        });
+}
+
+fn report_button_system(
+    mut commands: Commands,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<ReportClose>)>,
+    panel_query: Query<Entity, With<ReportPanel>>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            for panel in panel_query.iter() {
+                commands.entity(panel).despawn_recursive();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_then_import_keeps_the_puzzle() {
+        let mut givens = Grid::default();
+        givens[0][4] = Some(Value::from(5));
+        givens[8][8] = Some(Value::from(1));
+        let at = CellPosition::new;
+        // in the order import reads them, f-puzzles keys come back sorted
+        let mut rules = VariantRules {
+            lines: vec![Line {
+                kind: LineKind::Thermo,
+                cells: vec![at(0, 0), at(1, 0), at(2, 0)],
+            }],
+            borders: vec![
+                BorderClue::new(BorderKind::White, at(3, 3), at(3, 4)),
+                BorderClue::new(BorderKind::X, at(5, 5), at(6, 5)),
+            ],
+            negative: NegativeBorders {
+                white: true,
+                xv: true,
+                ..default()
+            },
+            outside: vec![
+                OutsideClue {
+                    kind: OutsideKind::LittleKiller { turn: 1 },
+                    side: Side::Top,
+                    index: 6,
+                    value: 10,
+                },
+                OutsideClue {
+                    kind: OutsideKind::Sandwich,
+                    side: Side::Left,
+                    index: 2,
+                    value: 12,
+                },
+            ],
+            parity: vec![ParityClue {
+                cell: at(7, 7),
+                even: false,
+            }],
+            ..default()
+        };
+        rules.global.anti_knight = true;
+
+        let (text, left_out) = export(&givens, &rules);
+        assert!(left_out.is_empty());
+        assert_eq!(import(&text), Ok((givens, rules, Vec::new())));
+    }
+
+    #[test]
+    fn unsupported_features_are_left_out_not_refused() {
+        let text = json!({
+            "size": 9,
+            "grid": vec![vec![json!({}); 9]; 9],
+            "disabledlogic": ["tuples"],
+            "killercage": [{ "cells": ["R1C1", "R1C2"], "value": "3" }],
+        })
+        .to_string();
+        let (_, rules, left_out) = import(&text).unwrap();
+        assert!(rules.is_classic());
+        assert_eq!(left_out, vec!["Killer cages (1)".to_string()]);
+    }
+
+    #[test]
+    fn sudokupad_lines_and_dots_are_read() {
+        let mut cells = vec![vec![json!({}); 9]; 9];
+        cells[0][4] = json!({ "value": 5 });
+        let text = json!({
+            "id": "test",
+            "cells": cells,
+            "lines": [{
+                "wayPoints": [[0.5, 0.5], [0.5, 1.5], [0.5, 2.5]],
+                "color": "#F067F0",
+                "thickness": 8,
+            }],
+            "overlays": [{
+                "center": [3.5, 4.0],
+                "rounded": true,
+                "backgroundColor": "#FFFFFF",
+            }],
+            "cages": [
+                { "value": "title: Test" },
+                { "cells": [[1, 1], [1, 2]], "value": "7" },
+            ],
+        })
+        .to_string();
+        let (givens, rules, left_out) = import(&text).unwrap();
+        let at = CellPosition::new;
+        assert_eq!(givens[0][4], Some(Value::from(5)));
+        assert_eq!(
+            rules.lines,
+            vec![Line {
+                kind: LineKind::Renban,
+                cells: vec![at(0, 0), at(1, 0), at(2, 0)],
+            }]
+        );
+        assert_eq!(
+            rules.borders,
+            vec![BorderClue::new(BorderKind::White, at(3, 3), at(4, 3))]
+        );
+        assert_eq!(left_out, vec!["Cages (1)".to_string()]);
+    }
+}
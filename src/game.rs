@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use sudoku_variants::Sudoku;
+
+use crate::{
+    board::{Cell, CellPosition, ValueEntered},
+    events::{NewGameReady, Reset, RevealScope},
+    settings::{MistakeMode, Preferences, MAX_MISTAKES},
+    ui::{FontAssets, Theme},
+    variant::VariantRules,
+    AppState, SudokuSolution,
+};
+
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameStatus>()
+            .init_resource::<Mistakes>()
+            .init_resource::<AssistUsage>()
+            .add_system(new_game_ready_events)
+            .add_system(reset_game_events)
+            .add_system(check_value_entered)
+            .add_system(flag_mistakes)
+            .add_system(check_solved)
+            .add_system(update_status_text);
+    }
+}
+
+/// Resource for whether the current game is still in progress
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameStatus {
+    Playing,
+    Won,
+    Lost,
+}
+
+impl Default for GameStatus {
+    fn default() -> Self {
+        Self::Playing
+    }
+}
+
+/// Resource counting wrong values entered this game
+#[derive(Resource, Default)]
+pub struct Mistakes(pub usize);
+
+/// Resource recording each assist used this game, penalized by [`score`]
+#[derive(Resource, Default, Debug, Clone)]
+pub struct AssistUsage {
+    pub checks: usize,
+    /// Searches for the move that led to a dead end
+    pub wrong_moves: usize,
+    pub cells_revealed: usize,
+    pub boxes_revealed: usize,
+    pub rows_revealed: usize,
+    pub solves: usize,
+}
+
+impl AssistUsage {
+    pub fn record_reveal(&mut self, scope: RevealScope) {
+        match scope {
+            RevealScope::Cell => self.cells_revealed += 1,
+            RevealScope::Box => self.boxes_revealed += 1,
+            RevealScope::Row => self.rows_revealed += 1,
+        }
+    }
+}
+
+const BASE_SCORE: usize = 1000;
+const MISTAKE_PENALTY: usize = 50;
+const CHECK_PENALTY: usize = 25;
+const WRONG_MOVE_PENALTY: usize = 40;
+const REVEAL_CELL_PENALTY: usize = 50;
+const REVEAL_UNIT_PENALTY: usize = 150;
+
+/// Score for a finished game, a full solve scores nothing
+pub fn score(mistakes: usize, assists: &AssistUsage) -> usize {
+    if assists.solves > 0 {
+        return 0;
+    }
+    let penalty = mistakes * MISTAKE_PENALTY
+        + assists.checks * CHECK_PENALTY
+        + assists.wrong_moves * WRONG_MOVE_PENALTY
+        + assists.cells_revealed * REVEAL_CELL_PENALTY
+        + (assists.boxes_revealed + assists.rows_revealed) * REVEAL_UNIT_PENALTY;
+    BASE_SCORE.saturating_sub(penalty)
+}
+
+#[derive(Component)]
+pub struct StatusText;
+
+pub fn create_status(parent: &mut ChildBuilder, theme: &Theme, font_assets: &FontAssets) {
+    parent.spawn((
+        TextBundle {
+            style: Style {
+                margin: UiRect {
+                    top: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            text: font_assets.status("", theme),
+            ..default()
+        },
+        Name::new("Status"),
+        StatusText,
+    ));
+}
+
+fn new_game_ready_events(
+    mut new_game_ready: EventReader<NewGameReady>,
+    mut status: ResMut<GameStatus>,
+    mut mistakes: ResMut<Mistakes>,
+    mut assists: ResMut<AssistUsage>,
+) {
+    for _ in new_game_ready.iter() {
+        *status = GameStatus::Playing;
+        mistakes.0 = 0;
+        *assists = AssistUsage::default();
+    }
+}
+
+/// Mistakes and assists already used stay counted, a reset can't be used to clear them from the
+/// score, and a lost game stays lost
+fn reset_game_events(mut reset_event: EventReader<Reset>, mut status: ResMut<GameStatus>) {
+    for _ in reset_event.iter() {
+        if *status != GameStatus::Lost {
+            *status = GameStatus::Playing;
+        }
+    }
+}
+
+fn check_value_entered(
+    mut value_entered: EventReader<ValueEntered>,
+    solution: Res<SudokuSolution>,
+    preferences: Res<Preferences>,
+    app_state: Res<State<AppState>>,
+    mut mistakes: ResMut<Mistakes>,
+    mut status: ResMut<GameStatus>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    // givens typed in the setter have nothing to be checked against
+    let solving = app_state.current().solves_board();
+    for event in value_entered.iter().filter(|_| solving) {
+        if let Ok((mut cell, pos)) = query.get_mut(event.entity) {
+            // the value may have changed again before we got here
+            if cell.value() != Some(event.value) {
+                continue;
+            }
+            match solution.get(pos.x, pos.y) {
+                Some(expected) if expected != event.value => {}
+                _ => continue,
+            }
+
+            mistakes.0 += 1;
+            if preferences.mistake_mode != MistakeMode::Off {
+                cell.set_mistake(true);
+            }
+            if preferences.mistake_mode == MistakeMode::ThreeStrikes && mistakes.0 >= MAX_MISTAKES {
+                *status = GameStatus::Lost;
+            }
+        }
+    }
+}
+
+/// Flag or clear existing entries when the mistake mode changes
+fn flag_mistakes(
+    preferences: Res<Preferences>,
+    solution: Res<SudokuSolution>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    if !preferences.is_changed() {
+        return;
+    }
+    for (mut cell, pos) in query.iter_mut() {
+        let wrong = preferences.mistake_mode != MistakeMode::Off
+            && cell.is_enabled()
+            && matches!(
+                (cell.value(), solution.get(pos.x, pos.y)),
+                (Some(value), Some(expected)) if value != expected
+            );
+        if cell.is_mistake() != wrong {
+            cell.set_mistake(wrong);
+        }
+    }
+}
+
+fn check_solved(
+    changed_query: Query<(), Changed<Cell>>,
+    query: Query<(&Cell, &CellPosition)>,
+    solution: Res<SudokuSolution>,
+    rules: Res<VariantRules>,
+    app_state: Res<State<AppState>>,
+    mut status: ResMut<GameStatus>,
+) {
+    if changed_query.is_empty()
+        || *status != GameStatus::Playing
+        || !app_state.current().solves_board()
+    {
+        return;
+    }
+    if query.iter().any(|(cell, _)| cell.is_empty()) {
+        return;
+    }
+
+    let solved = match solution.0 {
+        Some(_) => query
+            .iter()
+            .all(|(cell, pos)| cell.value() == solution.get(pos.x, pos.y)),
+        // no known solution, so settle for a full and valid grid
+        None => {
+            let mut sudoku = Sudoku::new_empty(3, 3, rules.constraint()).unwrap();
+            let grid = sudoku.grid_mut();
+            for (cell, pos) in query.iter() {
+                if let Some(value) = cell.value() {
+                    grid.set_cell(pos.x, pos.y, value.into()).unwrap();
+                }
+            }
+            sudoku.is_valid()
+        }
+    };
+    if solved {
+        *status = GameStatus::Won;
+    }
+}
+
+fn update_status_text(
+    preferences: Res<Preferences>,
+    mistakes: Res<Mistakes>,
+    assists: Res<AssistUsage>,
+    status: Res<GameStatus>,
+    added_query: Query<(), Added<StatusText>>,
+    mut query: Query<&mut Text, With<StatusText>>,
+) {
+    if !preferences.is_changed()
+        && !mistakes.is_changed()
+        && !assists.is_changed()
+        && !status.is_changed()
+        && added_query.is_empty()
+    {
+        return;
+    }
+
+    let value = match (*status, preferences.mistake_mode) {
+        (GameStatus::Won, _) => format!("Solved! Score: {}", score(mistakes.0, &assists)),
+        (GameStatus::Lost, _) => format!("Game Over - {} mistakes", mistakes.0),
+        (GameStatus::Playing, MistakeMode::Off) => "".to_string(),
+        (GameStatus::Playing, MistakeMode::Immediate) => format!("Mistakes: {}", mistakes.0),
+        (GameStatus::Playing, MistakeMode::ThreeStrikes) => {
+            format!("Mistakes: {}/{MAX_MISTAKES}", mistakes.0)
+        }
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
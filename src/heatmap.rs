@@ -0,0 +1,257 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+use strum::IntoEnumIterator;
+
+use crate::{
+    annotation::{HighlightKind, Highlights},
+    assist::{candidates, grid_values, units, Grid},
+    board::{Cell, CellPosition, Notes, Value},
+    ui::{FontAssets, Theme},
+};
+
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HeatmapToggle>()
+            .init_resource::<Heatmap>()
+            .add_system(heatmap_toggle_events)
+            .add_system(update_heatmap);
+    }
+}
+
+/// Event to show or hide the candidate heatmap
+pub struct HeatmapToggle;
+
+/// Resource for whether the analysis overlay is shown
+#[derive(Resource, Default)]
+pub struct Heatmap {
+    pub enabled: bool,
+}
+
+/// Shading behind a cell's colors and text, child of the cell at the position
+#[derive(Component)]
+pub struct HeatmapShade(pub CellPosition);
+
+/// Side panel listing empty cells and candidates per unit
+#[derive(Component)]
+pub struct HeatmapPanel;
+
+pub fn spawn_heatmap_shade(parent: &mut ChildBuilder, pos: CellPosition) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect::all(Val::Px(0.0)),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(FocusPolicy::Pass)
+        .insert(HeatmapShade(pos));
+}
+
+/// Text of one unit's count in the panel, the index and 0, 1, 2 for row, column, box
+#[derive(Component)]
+struct HeatmapCount(usize, usize);
+
+/// Widths of the label column and the three unit columns of the panel
+const PANEL_COLUMNS: [f32; 4] = [30.0, 80.0, 80.0, 80.0];
+
+pub fn create_heatmap_panel(parent: &mut ChildBuilder, theme: &Theme, font_assets: &FontAssets) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(20.0),
+                        left: Val::Px(20.0),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: theme.btn_normal.into(),
+                ..default()
+            },
+            Name::new("Heatmap Panel"),
+            HeatmapPanel,
+        ))
+        .with_children(|parent| {
+            let header = ["", "Row", "Column", "Box"].map(|label| (label.to_string(), None));
+            spawn_panel_row(parent, theme, font_assets, header);
+            for i in 0..9 {
+                let row = [0, 1, 2, 3].map(|column| match column {
+                    0 => (format!("{}:", i + 1), None),
+                    _ => (String::new(), Some(HeatmapCount(i, column - 1))),
+                });
+                spawn_panel_row(parent, theme, font_assets, row);
+            }
+            parent.spawn(TextBundle {
+                text: panel_text("empty (candidates)".to_string(), theme, font_assets),
+                ..default()
+            });
+        });
+}
+
+/// One line of the panel, each text in its own fixed width column so they line up
+fn spawn_panel_row(
+    parent: &mut ChildBuilder,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    columns: [(String, Option<HeatmapCount>); 4],
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for ((label, count), width) in columns.into_iter().zip(PANEL_COLUMNS) {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(width), Val::Auto),
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        let mut text = parent.spawn(TextBundle {
+                            text: panel_text(label, theme, font_assets),
+                            ..default()
+                        });
+                        if let Some(count) = count {
+                            text.insert(count);
+                        }
+                    });
+            }
+        });
+}
+
+fn panel_text(value: String, theme: &Theme, font_assets: &FontAssets) -> Text {
+    Text {
+        sections: vec![TextSection {
+            value,
+            style: TextStyle {
+                font: font_assets.ui_font.clone(),
+                font_size: 20.0,
+                color: theme.text,
+            },
+        }],
+        alignment: TextAlignment {
+            vertical: VerticalAlign::Top,
+            horizontal: HorizontalAlign::Left,
+        },
+    }
+}
+
+/// Candidates of each empty cell, None for filled cells
+fn all_candidates(grid: &Grid) -> [[Option<Notes>; 9]; 9] {
+    std::array::from_fn(|y| {
+        std::array::from_fn(|x| match grid[y][x] {
+            Some(_) => None,
+            None => Some(candidates(grid, x, y)),
+        })
+    })
+}
+
+/// Empty cells holding the only place for a value in one of their units
+fn hidden_singles(candidates: &[[Option<Notes>; 9]; 9]) -> Vec<CellPosition> {
+    let mut singles = Vec::new();
+    for unit in units() {
+        for value in Value::iter() {
+            let mut places = unit
+                .iter()
+                .filter(|(x, y)| matches!(candidates[*y][*x], Some(notes) if notes.contains(value)));
+            if let (Some((x, y)), None) = (places.next(), places.next()) {
+                let pos = CellPosition::new(*x, *y);
+                // a naked single isn't hidden
+                if candidates[*y][*x].map(|notes| notes.len()) != Some(1) && !singles.contains(&pos) {
+                    singles.push(pos);
+                }
+            }
+        }
+    }
+    singles
+}
+
+fn heatmap_toggle_events(mut heatmap_toggle: EventReader<HeatmapToggle>, mut heatmap: ResMut<Heatmap>) {
+    for _ in heatmap_toggle.iter() {
+        heatmap.enabled = !heatmap.enabled;
+    }
+}
+
+fn update_heatmap(
+    heatmap: Res<Heatmap>,
+    theme: Res<Theme>,
+    changed_query: Query<(), Changed<Cell>>,
+    added_query: Query<(), Added<HeatmapShade>>,
+    cell_query: Query<(&Cell, &CellPosition)>,
+    mut shade_query: Query<(&HeatmapShade, &mut BackgroundColor), Without<HeatmapPanel>>,
+    mut panel_query: Query<(&mut Style, &mut BackgroundColor), With<HeatmapPanel>>,
+    mut count_query: Query<(&HeatmapCount, &mut Text)>,
+    mut highlights: ResMut<Highlights>,
+) {
+    if !heatmap.is_changed() && !theme.is_changed() && changed_query.is_empty() && added_query.is_empty() {
+        return;
+    }
+
+    for (mut style, mut color) in panel_query.iter_mut() {
+        style.display = if heatmap.enabled { Display::Flex } else { Display::None };
+        *color = theme.btn_normal.into();
+    }
+    if !heatmap.enabled {
+        if heatmap.is_changed() {
+            for (_, mut color) in shade_query.iter_mut() {
+                *color = Color::NONE.into();
+            }
+            highlights.clear(HighlightKind::BiValue);
+            highlights.clear(HighlightKind::HiddenSingle);
+        }
+        return;
+    }
+
+    let grid = grid_values(cell_query.iter());
+    let candidates = all_candidates(&grid);
+
+    // fewer candidates shade darker, a cell with none is a contradiction
+    for (shade, mut color) in shade_query.iter_mut() {
+        *color = match candidates[shade.0.y][shade.0.x].map(|notes| notes.len()) {
+            None => Color::NONE,
+            Some(0) => theme.text_error,
+            Some(n) => {
+                let mut color = theme.heatmap;
+                color.set_a(theme.heatmap.a() * (10 - n) as f32 / 9.0);
+                color
+            }
+        }
+        .into();
+    }
+
+    let bi_value = (0..9)
+        .flat_map(|y| (0..9).map(move |x| CellPosition::new(x, y)))
+        .filter(|pos| candidates[pos.y][pos.x].map(|notes| notes.len()) == Some(2));
+    highlights.set(HighlightKind::BiValue, bi_value);
+    highlights.set(HighlightKind::HiddenSingle, hidden_singles(&candidates));
+
+    // units() goes row, column, box for each index
+    let units = units();
+    for (count, mut text) in count_query.iter_mut() {
+        let unit = &units[count.0 * 3 + count.1];
+        let empty = unit.iter().filter(|(x, y)| grid[*y][*x].is_none()).count();
+        let total: usize = unit
+            .iter()
+            .filter_map(|(x, y)| candidates[*y][*x].map(|notes| notes.len()))
+            .sum();
+        text.sections[0].value = format!("{empty} ({total})");
+    }
+}
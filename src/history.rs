@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::{Annotation, Annotations},
+    board::{Cell, CellPosition, CellState},
+    events::{NewGameReady, Reset},
+    game::GameStatus,
+    AppState,
+};
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Undo>()
+            .add_event::<Redo>()
+            .init_resource::<History>()
+            .add_system(history_keyboard_input)
+            .add_system(undo_events)
+            .add_system(redo_events)
+            .add_system(clear_history);
+    }
+}
+
+/// Event to step back one move
+pub struct Undo;
+/// Event to replay the last undone move
+pub struct Redo;
+
+/// State of every cell, indexed by row then column
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardSnapshot(pub [[CellState; 9]; 9]);
+
+impl BoardSnapshot {
+    pub fn new<'a>(cells: impl Iterator<Item = (&'a Cell, &'a CellPosition)>) -> Self {
+        let mut snapshot = Self::default();
+        for (cell, pos) in cells {
+            snapshot.0[pos.y][pos.x] = cell.state();
+        }
+        snapshot
+    }
+}
+
+/// A single cell going from one state to another
+#[derive(Debug, Copy, Clone)]
+pub struct CellChange {
+    pub pos: CellPosition,
+    pub before: CellState,
+    pub after: CellState,
+}
+
+/// An annotation being drawn or erased
+#[derive(Debug, Copy, Clone)]
+pub enum AnnotationChange {
+    Added(Annotation),
+    Removed(Annotation),
+}
+
+/// A player move, including any changes assists made because of it
+#[derive(Debug, Clone)]
+pub struct Move {
+    pub changes: Vec<CellChange>,
+    pub annotations: Vec<AnnotationChange>,
+}
+
+impl Move {
+    fn apply(
+        &self,
+        query: &mut Query<(&mut Cell, &CellPosition)>,
+        annotations: &mut Annotations,
+        undo: bool,
+    ) {
+        for (mut cell, pos) in query.iter_mut() {
+            if let Some(change) = self.changes.iter().find(|change| change.pos == *pos) {
+                cell.restore(if undo { change.before } else { change.after });
+            }
+        }
+        for change in self.annotations.iter() {
+            match (*change, undo) {
+                (AnnotationChange::Added(annotation), false)
+                | (AnnotationChange::Removed(annotation), true) => annotations.0.push(annotation),
+                (AnnotationChange::Added(annotation), true)
+                | (AnnotationChange::Removed(annotation), false) => annotations.remove(annotation),
+            }
+        }
+    }
+}
+
+/// Resource for the moves made this game
+#[derive(Resource, Default)]
+pub struct History {
+    undo: Vec<Move>,
+    redo: Vec<Move>,
+}
+
+impl History {
+    /// Record the difference between two snapshots as one move
+    pub fn record(&mut self, before: &BoardSnapshot, after: &BoardSnapshot) {
+        let mut changes = Vec::new();
+        for y in 0..9 {
+            for x in 0..9 {
+                if before.0[y][x] != after.0[y][x] {
+                    changes.push(CellChange {
+                        pos: CellPosition::new(x, y),
+                        before: before.0[y][x],
+                        after: after.0[y][x],
+                    });
+                }
+            }
+        }
+        if changes.is_empty() {
+            return;
+        }
+        self.undo.push(Move {
+            changes,
+            annotations: Vec::new(),
+        });
+        self.redo.clear();
+    }
+
+    /// Record an annotation being drawn, or erased if `added` is false
+    pub fn record_annotation(&mut self, annotation: Annotation, added: bool) {
+        let change = if added {
+            AnnotationChange::Added(annotation)
+        } else {
+            AnnotationChange::Removed(annotation)
+        };
+        self.undo.push(Move {
+            changes: Vec::new(),
+            annotations: vec![change],
+        });
+        self.redo.clear();
+    }
+
+    /// Moves that can be undone, oldest first
+    pub fn moves(&self) -> &[Move] {
+        &self.undo
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+fn history_keyboard_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    app_state: Res<State<AppState>>,
+    mut undo_event: EventWriter<Undo>,
+    mut redo_event: EventWriter<Redo>,
+) {
+    // samurai moves aren't recorded
+    if app_state.current() == &AppState::Samurai {
+        return;
+    }
+    let ctrl = keyboard_input.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    let shift = keyboard_input.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    if !ctrl {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Y) || (shift && keyboard_input.just_pressed(KeyCode::Z)) {
+        redo_event.send(Redo);
+    } else if keyboard_input.just_pressed(KeyCode::Z) {
+        undo_event.send(Undo);
+    }
+}
+
+fn undo_events(
+    mut undo_events: EventReader<Undo>,
+    mut history: ResMut<History>,
+    status: Res<GameStatus>,
+    mut annotations: ResMut<Annotations>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    for _ in undo_events.iter() {
+        if *status != GameStatus::Playing {
+            continue;
+        }
+        if let Some(last) = history.undo.pop() {
+            last.apply(&mut query, &mut annotations, true);
+            history.redo.push(last);
+        }
+    }
+}
+
+fn redo_events(
+    mut redo_events: EventReader<Redo>,
+    mut history: ResMut<History>,
+    status: Res<GameStatus>,
+    mut annotations: ResMut<Annotations>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    for _ in redo_events.iter() {
+        if *status != GameStatus::Playing {
+            continue;
+        }
+        if let Some(next) = history.redo.pop() {
+            next.apply(&mut query, &mut annotations, false);
+            history.undo.push(next);
+        }
+    }
+}
+
+fn clear_history(
+    mut new_game_ready: EventReader<NewGameReady>,
+    mut reset_event: EventReader<Reset>,
+    mut history: ResMut<History>,
+) {
+    if new_game_ready.iter().count() > 0 || reset_event.iter().count() > 0 {
+        history.clear();
+    }
+}
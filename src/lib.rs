@@ -1,22 +1,59 @@
+mod analysis;
+mod annotation;
+mod assist;
 mod board;
+mod checkpoint;
 mod lens;
+mod library;
+mod logic;
 mod menu;
+mod settings;
 mod state;
+mod storage;
+mod trainer;
+mod tutorial;
 mod ui;
+mod variant;
 mod events;
+mod fpuzzles;
+mod game;
+mod heatmap;
+mod history;
+mod samurai;
+mod save;
+mod setter;
+mod watch;
 
 #[cfg(not(target_os = "android"))]
 use bevy_inspector_egui::WorldInspectorPlugin;
+use analysis::AnalysisPlugin;
+use annotation::AnnotationPlugin;
+use assist::AssistPlugin;
 use bevy_tweening::TweeningPlugin;
 use board::*;
+use checkpoint::CheckpointPlugin;
 use events::EventPlugin;
+use fpuzzles::FPuzzlesPlugin;
+use game::GamePlugin;
+use heatmap::HeatmapPlugin;
+use history::HistoryPlugin;
 use lens::*;
+use library::LibraryPlugin;
+use logic::LogicPlugin;
 use menu::*;
+use samurai::SamuraiPlugin;
+use save::SavePlugin;
+use setter::SetterPlugin;
+use settings::*;
 use state::*;
+use trainer::TrainerPlugin;
+use tutorial::TutorialPlugin;
 use ui::*;
+use variant::VariantPlugin;
+use watch::WatchPlugin;
 
 use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*};
-use sudoku_variants::{constraint::DefaultConstraint, Sudoku};
+use sudoku_variants::{constraint::DefaultConstraint, Sudoku, SudokuGrid};
 
 #[derive(Deref, DerefMut, Resource)]
 pub struct SudokuContainer(pub Sudoku<DefaultConstraint>);
@@ -27,6 +64,17 @@ impl Default for SudokuContainer {
     }
 }
 
+/// The full solution the generator produced before removing clues
+#[derive(Default, Resource)]
+pub struct SudokuSolution(pub Option<SudokuGrid>);
+
+impl SudokuSolution {
+    pub fn get(&self, x: usize, y: usize) -> Option<Value> {
+        let grid = self.0.as_ref()?;
+        grid.get_cell(x, y).ok().flatten().map(Value::from)
+    }
+}
+
 #[derive(Component)]
 pub struct Keep;
 
@@ -35,6 +83,18 @@ pub enum AppState {
     Loading,
     Menu,
     Playing,
+    Tutorial,
+    Samurai,
+    Builder,
+    Setter,
+    Library,
+}
+
+impl AppState {
+    /// Whether the board is being solved, the setter uses it to type givens instead
+    pub fn solves_board(&self) -> bool {
+        *self != AppState::Setter
+    }
 }
 
 #[bevy_main]
@@ -62,15 +122,34 @@ pub fn main() {
         //.add_plugin(bevy_infinite_grid::InfiniteGridPlugin)
         //.add_startup_system(spawn_grid)
         // Setup Resources
-        .add_state(AppState::Loading)
+        .add_state(AppState::Menu)
         .init_resource::<SudokuContainer>()
+        .init_resource::<SudokuSolution>()
         // Local Plugins
+        .add_plugin(SettingsPlugin)
         .add_plugin(UIPlugin)
         .add_plugin(BoardPlugin)
         .add_plugin(MenuPlugin)
         .add_plugin(LensPlugin)
         .add_plugin(StatePlugin)
         .add_plugin(EventPlugin)
+        .add_plugin(GamePlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(AssistPlugin)
+        .add_plugin(AnnotationPlugin)
+        .add_plugin(CheckpointPlugin)
+        .add_plugin(AnalysisPlugin)
+        .add_plugin(HeatmapPlugin)
+        .add_plugin(LogicPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(WatchPlugin)
+        .add_plugin(TutorialPlugin)
+        .add_plugin(TrainerPlugin)
+        .add_plugin(VariantPlugin)
+        .add_plugin(SamuraiPlugin)
+        .add_plugin(FPuzzlesPlugin)
+        .add_plugin(SetterPlugin)
+        .add_plugin(LibraryPlugin)
 
         // global setup
         .add_startup_system(setup_camera)
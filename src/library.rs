@@ -0,0 +1,541 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use sudoku_variants::{constraint::DefaultConstraint, Sudoku};
+
+use crate::{
+    assist::Grid,
+    board::Value,
+    cleanup,
+    fpuzzles::ImportedPuzzle,
+    game::{AssistUsage, GameStatus},
+    save::fill_grid,
+    storage,
+    trainer::Training,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::{find_solutions, VariantRules, SEARCH_BUDGET},
+    AppState, SudokuContainer, SudokuSolution,
+};
+
+/// Packs shipped with the game, listed in this order
+const PACK_PATHS: [&str; 2] = ["packs/classics.pack", "packs/variants.pack"];
+const PROGRESS_FILE: &str = "library.json";
+
+pub struct LibraryPlugin;
+
+impl Plugin for LibraryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<PuzzlePack>()
+            .init_asset_loader::<PuzzlePackLoader>()
+            .init_resource::<LibraryAssets>()
+            .insert_resource(LibraryProgress::load())
+            .add_system_set(SystemSet::on_enter(AppState::Library).with_system(setup_library))
+            .add_system_set(
+                SystemSet::on_update(AppState::Library)
+                    .with_system(fill_library)
+                    .with_system(library_button_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Library).with_system(cleanup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(time_library_game)
+                    .with_system(record_library_solve),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(stop_library_game));
+    }
+}
+
+/// A named, ordered set of puzzles, read from a `.pack` file
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "a3f0c7d2-58e1-4b9a-8d6f-1e2c4b7a9f53"]
+pub struct PuzzlePack {
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    pub puzzles: Vec<PackPuzzle>,
+    /// The solution of each puzzle, found when the pack loads
+    #[serde(skip)]
+    pub solutions: Vec<Grid>,
+}
+
+/// A puzzle in a pack, either just its givens or with a title and variant rules
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PackPuzzle {
+    /// 81 digits row by row, 0 or . for an empty cell
+    Text(String),
+    Full {
+        #[serde(default)]
+        title: Option<String>,
+        givens: String,
+        #[serde(default)]
+        rules: VariantRules,
+    },
+}
+
+impl PackPuzzle {
+    fn text(&self) -> &str {
+        match self {
+            PackPuzzle::Text(text) | PackPuzzle::Full { givens: text, .. } => text,
+        }
+    }
+
+    /// The givens as 81 digits, best times are kept under it so they follow the puzzle around
+    pub fn key(&self) -> String {
+        self.text().replace('.', "0")
+    }
+
+    pub fn givens(&self) -> Grid {
+        let mut grid = Grid::default();
+        for (i, digit) in self.text().chars().take(81).enumerate() {
+            grid[i / 9][i % 9] = digit
+                .to_digit(10)
+                .filter(|digit| *digit != 0)
+                .map(|digit| Value::from(digit as usize));
+        }
+        grid
+    }
+
+    pub fn rules(&self) -> VariantRules {
+        match self {
+            PackPuzzle::Text(_) => VariantRules::default(),
+            PackPuzzle::Full { rules, .. } => rules.clone(),
+        }
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        match self {
+            PackPuzzle::Text(_) => None,
+            PackPuzzle::Full { title, .. } => title.as_deref(),
+        }
+    }
+}
+
+fn is_givens(text: &str) -> bool {
+    text.len() == 81 && text.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// The solution of a pack puzzle, or why it can't be played
+fn solve_pack_puzzle(puzzle: &PackPuzzle) -> Result<Grid, &'static str> {
+    if !is_givens(puzzle.text()) {
+        return Err("its givens aren't 81 digits");
+    }
+    let givens = puzzle.givens();
+    let rules = puzzle.rules();
+    let mut check = Sudoku::new_empty(3, 3, rules.constraint()).unwrap();
+    fill_grid(check.grid_mut(), &givens);
+    if !check.is_valid() {
+        return Err("its givens break its rules");
+    }
+    match find_solutions(&givens, &rules, 2, SEARCH_BUDGET).as_deref() {
+        Some([solution]) => Ok(*solution),
+        Some(_) => Err("it doesn't have a unique solution"),
+        None => Err("it's too open to check for a unique solution"),
+    }
+}
+
+/// The plain text form, `name:` and `author:` lines then one puzzle per line
+fn parse_text_pack(text: &str) -> Result<PuzzlePack, bevy::asset::Error> {
+    let mut pack = PuzzlePack {
+        name: "Untitled".to_string(),
+        author: String::new(),
+        puzzles: Vec::new(),
+        solutions: Vec::new(),
+    };
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("name:") {
+            pack.name = name.trim().to_string();
+        } else if let Some(author) = line.strip_prefix("author:") {
+            pack.author = author.trim().to_string();
+        } else if is_givens(line) {
+            pack.puzzles.push(PackPuzzle::Text(line.to_string()));
+        } else {
+            return Err(bevy::asset::Error::msg(format!(
+                "Line {} isn't a puzzle",
+                number + 1
+            )));
+        }
+    }
+    Ok(pack)
+}
+
+#[derive(Default)]
+pub struct PuzzlePackLoader;
+
+impl AssetLoader for PuzzlePackLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let is_json = bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+            let mut pack: PuzzlePack = if is_json {
+                serde_json::from_slice(bytes)?
+            } else {
+                parse_text_pack(std::str::from_utf8(bytes)?)?
+            };
+            // a pack only lists puzzles that can be played, so nothing is solved on a click
+            for (index, puzzle) in pack.puzzles.iter().enumerate() {
+                let solution = solve_pack_puzzle(puzzle).map_err(|problem| {
+                    bevy::asset::Error::msg(format!(
+                        "Puzzle {} can't be played, {problem}",
+                        index + 1
+                    ))
+                })?;
+                pack.solutions.push(solution);
+            }
+            load_context.set_default_asset(LoadedAsset::new(pack));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pack"]
+    }
+}
+
+#[derive(Resource)]
+pub struct LibraryAssets {
+    pub packs: Vec<(&'static str, Handle<PuzzlePack>)>,
+}
+
+impl FromWorld for LibraryAssets {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+        Self {
+            packs: PACK_PATHS
+                .iter()
+                .map(|path| (*path, asset_server.load(*path)))
+                .collect(),
+        }
+    }
+}
+
+/// Resource for the best time in seconds of each solved pack puzzle, by its givens
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct LibraryProgress(pub BTreeMap<String, f32>);
+
+impl LibraryProgress {
+    pub fn load() -> Self {
+        storage::load(PROGRESS_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(PROGRESS_FILE, self);
+    }
+
+    pub fn best(&self, puzzle: &PackPuzzle) -> Option<f32> {
+        self.0.get(&puzzle.key()).copied()
+    }
+
+    /// Keep the time if it beats the best so far
+    pub fn record(&mut self, key: String, seconds: f32) {
+        let time = self.0.entry(key).or_insert(seconds);
+        *time = time.min(seconds);
+    }
+}
+
+/// Resource for the pack puzzle being played, and the time spent on it so far
+#[derive(Resource, Debug)]
+pub struct LibraryGame {
+    /// The givens of the puzzle, see `PackPuzzle::key`
+    pub key: String,
+    pub elapsed: f32,
+}
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds as usize;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[derive(Component)]
+struct LibraryList {
+    filled: bool,
+}
+
+#[derive(Component)]
+struct LibraryStatus;
+
+#[derive(Component, Debug, Copy, Clone)]
+enum LibraryButton {
+    Puzzle { pack: usize, index: usize },
+    Back,
+}
+
+fn spawn_library_button(
+    parent: &mut ChildBuilder,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    button: LibraryButton,
+    label: String,
+    width: f32,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(width), Val::Px(50.0)),
+                    margin: UiRect::all(Val::Px(2.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: theme.btn_normal.into(),
+                ..default()
+            },
+            Name::new(label.clone()),
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: font_assets.status(label, theme),
+                ..default()
+            });
+        });
+}
+
+fn setup_library(mut commands: Commands, font_assets: Res<FontAssets>, theme: Res<Theme>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Library"))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: theme.line_thick.into(),
+                    ..default()
+                },
+                ThickLine,
+                Name::new("Packs"),
+                LibraryList { filled: false },
+            ));
+            parent.spawn((
+                TextBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    text: font_assets.status("Loading packs...", &theme),
+                    ..default()
+                },
+                LibraryStatus,
+            ));
+            spawn_library_button(
+                parent,
+                &theme,
+                &font_assets,
+                LibraryButton::Back,
+                "Back".to_string(),
+                300.0,
+            );
+        });
+}
+
+/// List every pack once they're loaded, or have failed to
+fn fill_library(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    assets: Res<LibraryAssets>,
+    packs: Res<Assets<PuzzlePack>>,
+    progress: Res<LibraryProgress>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    mut list_query: Query<(Entity, &mut LibraryList)>,
+    mut status_query: Query<&mut Text, With<LibraryStatus>>,
+) {
+    let load_state =
+        asset_server.get_group_load_state(assets.packs.iter().map(|(_, handle)| handle.id()));
+    if !matches!(load_state, LoadState::Loaded | LoadState::Failed) {
+        return;
+    }
+    for (list, mut library_list) in list_query.iter_mut() {
+        if library_list.filled {
+            continue;
+        }
+        library_list.filled = true;
+        commands.entity(list).with_children(|parent| {
+            for (pack_index, (path, handle)) in assets.packs.iter().enumerate() {
+                let pack = match packs.get(handle) {
+                    Some(pack) => pack,
+                    None => {
+                        parent.spawn(TextBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Px(6.0)),
+                                ..default()
+                            },
+                            text: font_assets.status(format!("Couldn't load {path}"), &theme),
+                            ..default()
+                        });
+                        continue;
+                    }
+                };
+                let solved = pack
+                    .puzzles
+                    .iter()
+                    .filter(|puzzle| progress.best(puzzle).is_some())
+                    .count();
+                let mut heading = format!("{} ({solved}/{})", pack.name, pack.puzzles.len());
+                if !pack.author.is_empty() {
+                    heading += &format!(" by {}", pack.author);
+                }
+                parent.spawn(TextBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    text: font_assets.status(heading, &theme),
+                    ..default()
+                });
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            flex_wrap: FlexWrap::Wrap,
+                            max_size: Size::new(Val::Px(560.0), Val::Undefined),
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for (index, puzzle) in pack.puzzles.iter().enumerate() {
+                            let name = match puzzle.title() {
+                                Some(title) => title.to_string(),
+                                None => format!("Puzzle {}", index + 1),
+                            };
+                            // solved puzzles show their best time
+                            let label = match progress.best(puzzle) {
+                                Some(best) => format!("{name} - {}", format_time(best)),
+                                None => name,
+                            };
+                            let button = LibraryButton::Puzzle {
+                                pack: pack_index,
+                                index,
+                            };
+                            spawn_library_button(
+                                parent,
+                                &theme,
+                                &font_assets,
+                                button,
+                                label,
+                                272.0,
+                            );
+                        }
+                    });
+            }
+        });
+        for mut text in status_query.iter_mut() {
+            text.sections[0].value = String::new();
+        }
+    }
+}
+
+/// Put the puzzle and the solution found at load time in place for loading to start
+fn start_pack_puzzle(commands: &mut Commands, puzzle: &PackPuzzle, solution: &Grid) {
+    let mut sudoku = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+    fill_grid(sudoku.grid_mut(), solution);
+    let solution = sudoku.grid().clone();
+    let mut sudoku = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+    fill_grid(sudoku.grid_mut(), &puzzle.givens());
+    commands.insert_resource(SudokuContainer(sudoku));
+    commands.insert_resource(puzzle.rules());
+    commands.insert_resource(SudokuSolution(Some(solution)));
+    commands.insert_resource(ImportedPuzzle);
+    commands.insert_resource(LibraryGame {
+        key: puzzle.key(),
+        elapsed: 0.0,
+    });
+}
+
+fn library_button_system(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &LibraryButton), (Changed<Interaction>, With<Button>)>,
+    assets: Res<LibraryAssets>,
+    packs: Res<Assets<PuzzlePack>>,
+    mut training: ResMut<Training>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let (pack, index) = match *button {
+            LibraryButton::Puzzle { pack, index } => (pack, index),
+            LibraryButton::Back => {
+                app_state.set(AppState::Menu).unwrap();
+                continue;
+            }
+        };
+        let (_, handle) = &assets.packs[pack];
+        let pack = match packs.get(handle) {
+            Some(pack) => pack,
+            None => continue,
+        };
+        if let (Some(puzzle), Some(solution)) = (pack.puzzles.get(index), pack.solutions.get(index))
+        {
+            start_pack_puzzle(&mut commands, puzzle, solution);
+            training.target = None;
+            app_state.set(AppState::Loading).unwrap();
+        }
+    }
+}
+
+fn time_library_game(time: Res<Time>, status: Res<GameStatus>, game: Option<ResMut<LibraryGame>>) {
+    if let Some(mut game) = game {
+        if *status == GameStatus::Playing {
+            game.elapsed += time.delta_seconds();
+        }
+    }
+}
+
+/// Keep the time of a pack puzzle once it's solved, a full solve by the solver doesn't count
+fn record_library_solve(
+    mut commands: Commands,
+    status: Res<GameStatus>,
+    assists: Res<AssistUsage>,
+    game: Option<Res<LibraryGame>>,
+    mut progress: ResMut<LibraryProgress>,
+) {
+    let game = match game {
+        Some(game) => game,
+        None => return,
+    };
+    if !status.is_changed() || *status == GameStatus::Playing {
+        return;
+    }
+    if *status == GameStatus::Won && assists.solves == 0 {
+        progress.record(game.key.clone(), game.elapsed);
+        progress.save();
+    }
+    commands.remove_resource::<LibraryGame>();
+}
+
+/// The time only counts while the puzzle stays on screen, a resumed game isn't timed
+fn stop_library_game(mut commands: Commands) {
+    commands.remove_resource::<LibraryGame>();
+}
@@ -0,0 +1,757 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{
+    annotation::{HighlightKind, Highlights},
+    assist::{candidates, grid_values, is_peer, units, Grid},
+    board::{Cell, CellPosition, Notes, Value},
+    storage,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::VariantRules,
+};
+
+const EXPORT_FILE: &str = "logic-path.txt";
+const STEPS_PER_PAGE: usize = 10;
+
+pub struct LogicPlugin;
+
+impl Plugin for LogicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LogicToggle>()
+            .init_resource::<LogicView>()
+            .add_system(update_logic_path)
+            .add_system(logic_button_system)
+            .add_system(update_logic_panel)
+            .add_system(update_step_preview);
+    }
+}
+
+/// Event to open or close the logic path panel
+pub struct LogicToggle;
+
+/// Solving techniques, simplest first, in the order they are tried
+#[derive(
+    EnumIter, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    /// Candidates in a box confined to one line clear the rest of that line
+    Pointing,
+    /// Candidates in a line confined to one box clear the rest of that box
+    Claiming,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    /// Two lines with the same two places for a value clear it from the crossing lines
+    XWing,
+}
+
+impl Display for Technique {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Technique::NakedSingle => write!(f, "Naked Single"),
+            Technique::HiddenSingle => write!(f, "Hidden Single"),
+            Technique::Pointing => write!(f, "Pointing"),
+            Technique::Claiming => write!(f, "Claiming"),
+            Technique::NakedPair => write!(f, "Naked Pair"),
+            Technique::HiddenPair => write!(f, "Hidden Pair"),
+            Technique::NakedTriple => write!(f, "Naked Triple"),
+            Technique::XWing => write!(f, "X-Wing"),
+        }
+    }
+}
+
+/// One logical deduction, either placing a value or removing candidates
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub technique: Technique,
+    /// Index into [`units`] the deduction is made in
+    pub unit: Option<usize>,
+    /// Cells forming the pattern
+    pub cells: Vec<CellPosition>,
+    pub values: Vec<Value>,
+    pub placement: Option<(CellPosition, Value)>,
+    pub eliminations: Vec<(CellPosition, Value)>,
+}
+
+fn cell_name(pos: CellPosition) -> String {
+    format!("r{}c{}", pos.y + 1, pos.x + 1)
+}
+
+/// Name of a unit from [`units`], which goes row, column, box for each index
+fn unit_name(unit: usize) -> String {
+    let kind = match unit % 3 {
+        0 => "row",
+        1 => "column",
+        _ => "box",
+    };
+    format!("{kind} {}", unit / 3 + 1)
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.technique)?;
+        if let Some(unit) = self.unit {
+            write!(f, " in {}", unit_name(unit))?;
+        }
+        if self.placement.is_none() {
+            let cells: Vec<_> = self.cells.iter().map(|pos| cell_name(*pos)).collect();
+            let values: Vec<_> = self.values.iter().map(|value| value.to_string()).collect();
+            write!(f, " {} on {}", values.join("/"), cells.join(", "))?;
+        }
+        if let Some((pos, value)) = self.placement {
+            write!(f, ": {} = {value}", cell_name(pos))?;
+        }
+        if !self.eliminations.is_empty() {
+            let removed: Vec<_> = self
+                .eliminations
+                .iter()
+                .map(|(pos, value)| format!("{}-{value}", cell_name(*pos)))
+                .collect();
+            write!(f, ": removes {}", removed.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The steps found from a position, and whether they reach the solution
+#[derive(Debug, Clone, Default)]
+pub struct LogicPath {
+    pub steps: Vec<Step>,
+    pub solved: bool,
+}
+
+impl LogicPath {
+    /// The hardest technique the solve needed
+    pub fn hardest(&self) -> Option<Technique> {
+        self.steps.iter().map(|step| step.technique).max()
+    }
+}
+
+impl Display for LogicPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{}. {step}", i + 1)?;
+        }
+        if !self.solved {
+            writeln!(f, "No further logical steps")?;
+        }
+        Ok(())
+    }
+}
+
+/// Values and remaining candidates while stepping through a solve
+struct SolveState<'a> {
+    grid: Grid,
+    candidates: [[Notes; 9]; 9],
+    rules: &'a VariantRules,
+}
+
+impl<'a> SolveState<'a> {
+    fn new(grid: &Grid, rules: &'a VariantRules) -> Self {
+        let mut state = Self {
+            grid: *grid,
+            candidates: std::array::from_fn(|y| {
+                std::array::from_fn(|x| match grid[y][x] {
+                    Some(_) => Notes::default(),
+                    None => candidates(grid, x, y),
+                })
+            }),
+            rules,
+        };
+        state.apply_rules();
+        state
+    }
+
+    /// Drop the candidates the variant clues rule out with the values placed so far
+    fn apply_rules(&mut self) {
+        if self.rules.is_classic() {
+            return;
+        }
+        for y in 0..9 {
+            for x in 0..9 {
+                let pos = CellPosition::new(x, y);
+                for value in self.candidates[y][x].values().collect::<Vec<_>>() {
+                    if !self.rules.allows(&self.grid, pos, value) {
+                        self.candidates[y][x].remove(value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_solved(&self) -> bool {
+        self.grid.iter().all(|row| row.iter().all(|value| value.is_some()))
+    }
+
+    /// An empty cell with no candidates left
+    fn is_broken(&self) -> bool {
+        (0..9).any(|y| (0..9).any(|x| self.grid[y][x].is_none() && self.candidates[y][x].is_empty()))
+    }
+
+    fn apply(&mut self, step: &Step) {
+        if let Some((pos, value)) = step.placement {
+            self.grid[pos.y][pos.x] = Some(value);
+            self.candidates[pos.y][pos.x].clear();
+            for y in 0..9 {
+                for x in 0..9 {
+                    if is_peer((pos.x, pos.y), (x, y)) {
+                        self.candidates[y][x].remove(value);
+                    }
+                }
+            }
+            self.apply_rules();
+        }
+        for (pos, value) in step.eliminations.iter() {
+            self.candidates[pos.y][pos.x].remove(*value);
+        }
+    }
+
+    /// Cells in the unit that can still hold the value
+    fn places(&self, unit: &[(usize, usize); 9], value: Value) -> Vec<CellPosition> {
+        unit.iter()
+            .filter(|(x, y)| self.candidates[*y][*x].contains(value))
+            .map(|(x, y)| CellPosition::new(*x, *y))
+            .collect()
+    }
+
+    fn find(&self, technique: Technique) -> Option<Step> {
+        match technique {
+            Technique::NakedSingle => self.naked_single(),
+            Technique::HiddenSingle => self.hidden_single(),
+            Technique::Pointing => self.locked_candidates(true),
+            Technique::Claiming => self.locked_candidates(false),
+            Technique::NakedPair => self.naked_subset(Technique::NakedPair, 2),
+            Technique::HiddenPair => self.hidden_pair(),
+            Technique::NakedTriple => self.naked_subset(Technique::NakedTriple, 3),
+            Technique::XWing => self.x_wing(),
+        }
+    }
+
+    /// The simplest step from here, None once solved, broken or stuck
+    fn next_step(&self) -> Option<Step> {
+        if self.is_solved() || self.is_broken() {
+            return None;
+        }
+        Technique::iter().find_map(|technique| self.find(technique))
+    }
+
+    fn naked_single(&self) -> Option<Step> {
+        for y in 0..9 {
+            for x in 0..9 {
+                let notes = self.candidates[y][x];
+                if self.grid[y][x].is_none() && notes.len() == 1 {
+                    let pos = CellPosition::new(x, y);
+                    let value = notes.values().next()?;
+                    return Some(Step {
+                        technique: Technique::NakedSingle,
+                        unit: None,
+                        cells: vec![pos],
+                        values: vec![value],
+                        placement: Some((pos, value)),
+                        eliminations: Vec::new(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn hidden_single(&self) -> Option<Step> {
+        for (i, unit) in units().iter().enumerate() {
+            for value in Value::iter() {
+                let places = self.places(unit, value);
+                if places.len() == 1 {
+                    return Some(Step {
+                        technique: Technique::HiddenSingle,
+                        unit: Some(i),
+                        cells: places.clone(),
+                        values: vec![value],
+                        placement: Some((places[0], value)),
+                        eliminations: Vec::new(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Pointing looks from boxes out to lines, claiming from lines in to boxes
+    fn locked_candidates(&self, pointing: bool) -> Option<Step> {
+        let all_units = units();
+        for (i, unit) in all_units.iter().enumerate() {
+            let is_box = i % 3 == 2;
+            if is_box != pointing {
+                continue;
+            }
+            for value in Value::iter() {
+                let places = self.places(unit, value);
+                if places.len() < 2 {
+                    continue;
+                }
+                // other units holding every place, besides this one
+                for (j, other) in all_units.iter().enumerate() {
+                    if j == i || (j % 3 == 2) == is_box {
+                        continue;
+                    }
+                    let contains = |pos: &CellPosition| other.contains(&(pos.x, pos.y));
+                    if !places.iter().all(contains) {
+                        continue;
+                    }
+                    let eliminations: Vec<_> = self
+                        .places(other, value)
+                        .into_iter()
+                        .filter(|pos| !unit.contains(&(pos.x, pos.y)))
+                        .map(|pos| (pos, value))
+                        .collect();
+                    if !eliminations.is_empty() {
+                        return Some(Step {
+                            technique: if pointing {
+                                Technique::Pointing
+                            } else {
+                                Technique::Claiming
+                            },
+                            unit: Some(i),
+                            cells: places,
+                            values: vec![value],
+                            placement: None,
+                            eliminations,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// `size` cells in a unit sharing `size` candidates between them
+    fn naked_subset(&self, technique: Technique, size: usize) -> Option<Step> {
+        for (i, unit) in units().iter().enumerate() {
+            let open: Vec<_> = unit
+                .iter()
+                .map(|(x, y)| CellPosition::new(*x, *y))
+                .filter(|pos| (2..=size).contains(&self.candidates[pos.y][pos.x].len()))
+                .collect();
+            for subset in combinations(open.len(), size) {
+                let cells: Vec<_> = subset.iter().map(|k| open[*k]).collect();
+                let mut union = Notes::default();
+                for pos in cells.iter() {
+                    for value in self.candidates[pos.y][pos.x].values() {
+                        union.insert(value);
+                    }
+                }
+                if union.len() != size {
+                    continue;
+                }
+                let mut eliminations = Vec::new();
+                for (x, y) in unit.iter() {
+                    let pos = CellPosition::new(*x, *y);
+                    if cells.contains(&pos) {
+                        continue;
+                    }
+                    for value in union.values() {
+                        if self.candidates[*y][*x].contains(value) {
+                            eliminations.push((pos, value));
+                        }
+                    }
+                }
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        technique,
+                        unit: Some(i),
+                        cells,
+                        values: union.values().collect(),
+                        placement: None,
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Two values confined to the same two cells of a unit clear everything else from them
+    fn hidden_pair(&self) -> Option<Step> {
+        for (i, unit) in units().iter().enumerate() {
+            let values: Vec<_> = Value::iter().collect();
+            for pair in combinations(values.len(), 2) {
+                let (a, b) = (values[pair[0]], values[pair[1]]);
+                let places = self.places(unit, a);
+                if places.len() != 2 || places != self.places(unit, b) {
+                    continue;
+                }
+                let eliminations: Vec<_> = places
+                    .iter()
+                    .flat_map(|pos| {
+                        self.candidates[pos.y][pos.x]
+                            .values()
+                            .filter(|value| *value != a && *value != b)
+                            .map(|value| (*pos, value))
+                    })
+                    .collect();
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        technique: Technique::HiddenPair,
+                        unit: Some(i),
+                        cells: places,
+                        values: vec![a, b],
+                        placement: None,
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Rows first, then columns, as the base lines of the pattern
+    fn x_wing(&self) -> Option<Step> {
+        let all_units = units();
+        for value in Value::iter() {
+            // units() goes row, column, box, so kind 0 is rows and 1 is columns
+            for kind in 0..2 {
+                let lines: Vec<Vec<CellPosition>> = (0..9)
+                    .map(|n| self.places(&all_units[n * 3 + kind], value))
+                    .filter(|places| places.len() == 2)
+                    .collect();
+                let cross = |pos: &CellPosition| if kind == 0 { pos.x } else { pos.y };
+                for pair in combinations(lines.len(), 2) {
+                    let (a, b) = (&lines[pair[0]], &lines[pair[1]]);
+                    let crosses: Vec<usize> = a.iter().map(cross).collect();
+                    if crosses != b.iter().map(cross).collect::<Vec<_>>() {
+                        continue;
+                    }
+                    let eliminations: Vec<_> = crosses
+                        .iter()
+                        .flat_map(|n| self.places(&all_units[n * 3 + 1 - kind], value))
+                        .filter(|pos| !a.contains(pos) && !b.contains(pos))
+                        .map(|pos| (pos, value))
+                        .collect();
+                    if !eliminations.is_empty() {
+                        return Some(Step {
+                            technique: Technique::XWing,
+                            unit: None,
+                            cells: a.iter().chain(b.iter()).copied().collect(),
+                            values: vec![value],
+                            placement: None,
+                            eliminations,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Every ascending choice of `size` indices below `n`
+fn combinations(n: usize, size: usize) -> Vec<Vec<usize>> {
+    fn extend(start: usize, n: usize, size: usize, current: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        if current.len() == size {
+            out.push(current.clone());
+            return;
+        }
+        for i in start..n {
+            current.push(i);
+            extend(i + 1, n, size, current, out);
+            current.pop();
+        }
+    }
+    let mut out = Vec::new();
+    extend(0, n, size, &mut Vec::new(), &mut out);
+    out
+}
+
+/// Solve as far as the techniques allow, taking the simplest step each time.
+/// Variant clues only narrow the candidates, the steps are all classic techniques
+pub fn solve_path(grid: &Grid, rules: &VariantRules) -> LogicPath {
+    let mut state = SolveState::new(grid, rules);
+    let mut path = LogicPath::default();
+    while let Some(step) = state.next_step() {
+        state.apply(&step);
+        path.steps.push(step);
+    }
+    path.solved = state.is_solved();
+    path
+}
+
+/// The position where a technique is first needed, with the candidates left at that point
+#[derive(Debug, Clone)]
+pub struct KeyPosition {
+    /// Values placed by the easier steps leading up to it
+    pub placements: Vec<(CellPosition, Value)>,
+    pub candidates: [[Notes; 9]; 9],
+    pub step: Step,
+}
+
+/// Follow the simplest steps until one needs `target`, None if the solve never does
+pub fn key_position(grid: &Grid, rules: &VariantRules, target: Technique) -> Option<KeyPosition> {
+    let mut state = SolveState::new(grid, rules);
+    let mut placements = Vec::new();
+    while let Some(step) = state.next_step() {
+        if step.technique == target {
+            return Some(KeyPosition {
+                placements,
+                candidates: state.candidates,
+                step,
+            });
+        }
+        placements.extend(step.placement);
+        state.apply(&step);
+    }
+    None
+}
+
+/// Resource for the logic path panel, the path follows the board while open
+#[derive(Resource, Default)]
+pub struct LogicView {
+    pub open: bool,
+    pub path: LogicPath,
+    pub page: usize,
+    pub selected: Option<usize>,
+    /// Where the last export was written
+    pub exported: Option<String>,
+}
+
+#[derive(Component)]
+struct LogicPanel;
+
+#[derive(Component, Debug, Copy, Clone)]
+enum LogicButton {
+    Step(usize),
+    Previous,
+    Next,
+    Export,
+    Close,
+}
+
+/// The path is rebuilt when the panel opens and whenever the board changes while it is open
+fn update_logic_path(
+    mut logic_toggle: EventReader<LogicToggle>,
+    mut view: ResMut<LogicView>,
+    rules: Res<VariantRules>,
+    changed_query: Query<(), Changed<Cell>>,
+    query: Query<(&Cell, &CellPosition)>,
+) {
+    let mut refresh = view.open && !changed_query.is_empty();
+    for _ in logic_toggle.iter() {
+        view.open = !view.open;
+        refresh = view.open;
+    }
+    if !refresh {
+        return;
+    }
+    view.path = solve_path(&grid_values(query.iter()), &rules);
+    view.page = 0;
+    view.selected = None;
+    view.exported = None;
+}
+
+fn logic_button_system(
+    interaction_query: Query<(&Interaction, &LogicButton), (Changed<Interaction>, With<Button>)>,
+    mut view: ResMut<LogicView>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let pages = (view.path.steps.len() + STEPS_PER_PAGE - 1) / STEPS_PER_PAGE;
+        match *button {
+            LogicButton::Step(i) => {
+                view.selected = if view.selected == Some(i) { None } else { Some(i) };
+                view.exported = None;
+            }
+            LogicButton::Previous => view.page = view.page.saturating_sub(1),
+            LogicButton::Next => view.page = (view.page + 1).min(pages.saturating_sub(1)),
+            LogicButton::Export => {
+                let text = view.path.to_string();
+                view.exported = storage::save_text(EXPORT_FILE, &text)
+                    .map(|path| path.display().to_string());
+            }
+            LogicButton::Close => {
+                view.open = false;
+                view.selected = None;
+            }
+        }
+    }
+}
+
+fn spawn_logic_button(
+    parent: &mut ChildBuilder,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    button: LogicButton,
+    label: String,
+    width: Val,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(width, Val::Px(32.0)),
+                    margin: UiRect::all(Val::Px(2.0)),
+                    padding: UiRect::horizontal(Val::Px(6.0)),
+                    justify_content: JustifyContent::FlexStart,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: theme.btn_normal.into(),
+                ..default()
+            },
+            Name::new(format!("{button:?}")),
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: Text::from_section(
+                    label,
+                    TextStyle {
+                        font: font_assets.ui_font.clone(),
+                        font_size: 18.0,
+                        color: theme.text,
+                    },
+                ),
+                ..default()
+            });
+        });
+}
+
+fn update_logic_panel(
+    mut commands: Commands,
+    view: Res<LogicView>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    panel_query: Query<Entity, With<LogicPanel>>,
+) {
+    if !view.is_changed() {
+        return;
+    }
+    for panel in panel_query.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+    if !view.open {
+        return;
+    }
+
+    let steps = &view.path.steps;
+    let first = view.page * STEPS_PER_PAGE;
+    let last = (first + STEPS_PER_PAGE).min(steps.len());
+    let selected = view.selected.and_then(|i| steps.get(i));
+    let status = match (selected, &view.exported) {
+        (_, Some(path)) => format!("Exported to {path}"),
+        (Some(step), None) => step.to_string(),
+        (None, None) if view.path.solved => format!("Solved in {} steps", steps.len()),
+        (None, None) => format!("Stuck after {} steps", steps.len()),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(20.0),
+                        right: Val::Px(190.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Px(420.0), Val::Auto),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                background_color: theme.line_thick.into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            ThickLine,
+            Name::new("Logic Path"),
+            LogicPanel,
+        ))
+        .with_children(|parent| {
+            for (i, step) in steps.iter().enumerate().take(last).skip(first) {
+                let marker = if view.selected == Some(i) { "> " } else { "" };
+                let mut label = format!("{marker}{}. {step}", i + 1);
+                // keep long eliminations from widening the panel
+                if label.len() > 48 {
+                    label.truncate(45);
+                    label.push_str("...");
+                }
+                spawn_logic_button(
+                    parent,
+                    &theme,
+                    &font_assets,
+                    LogicButton::Step(i),
+                    label,
+                    Val::Percent(100.0),
+                );
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (LogicButton::Previous, "<"),
+                        (LogicButton::Next, ">"),
+                        (LogicButton::Export, "Export"),
+                        (LogicButton::Close, "Close"),
+                    ] {
+                        spawn_logic_button(
+                            parent,
+                            &theme,
+                            &font_assets,
+                            button,
+                            label.to_string(),
+                            Val::Px(98.0),
+                        );
+                    }
+                });
+            // the full step, with every elimination, wraps below the list
+            parent.spawn(TextBundle {
+                style: Style {
+                    margin: UiRect::all(Val::Px(4.0)),
+                    max_size: Size::new(Val::Px(410.0), Val::Undefined),
+                    ..default()
+                },
+                text: Text::from_section(
+                    status,
+                    TextStyle {
+                        font: font_assets.ui_font.clone(),
+                        font_size: 18.0,
+                        color: theme.line_thin,
+                    },
+                ),
+                ..default()
+            });
+        });
+}
+
+/// Outline the selected step's pattern and the cells it removes candidates from
+fn update_step_preview(view: Res<LogicView>, mut highlights: ResMut<Highlights>) {
+    if !view.is_changed() {
+        return;
+    }
+    match view.selected.and_then(|i| view.path.steps.get(i)) {
+        Some(step) => {
+            highlights.set(HighlightKind::LogicPattern, step.cells.iter().copied());
+            highlights.set(
+                HighlightKind::LogicElimination,
+                step.eliminations.iter().map(|(pos, _)| *pos),
+            );
+        }
+        None => {
+            highlights.clear(HighlightKind::LogicPattern);
+            highlights.clear(HighlightKind::LogicElimination);
+        }
+    }
+}
@@ -6,7 +6,14 @@ use strum_macros::EnumIter;
 use std::fmt::{Display, Formatter};
 
 use crate::{
+    analysis::FindWrongMove,
+    fpuzzles::{ExportPuzzle, ImportPuzzle},
+    heatmap::HeatmapToggle,
+    logic::LogicToggle,
+    history::{Redo, Undo},
+    settings::SettingsToggle,
     ui::{FontAssets, Theme, ThemeToggle, ThickLine}, 
+    watch::WatchSolveStart,
     events::*, AppState,
 };
 
@@ -14,7 +21,8 @@ pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(menu_button_system);
+        app.add_system(menu_button_system)
+            .add_system(file_menu_button_system);
     }
 }
 
@@ -23,8 +31,22 @@ impl Plugin for MenuPlugin {
 enum MenuButton {
     New,
     Reset,
+    Undo,
+    Redo,
+    Check,
+    FindMistake,
+    Heatmap,
+    Logic,
+    RevealCell,
+    RevealBox,
+    RevealRow,
     Solve,
+    WatchSolve,
+    /// Open or close the f-puzzles import and export buttons
+    Files,
+    Settings,
     ThemeToggle,
+    Title,
     Quit,
 }
 
@@ -33,13 +55,95 @@ impl Display for MenuButton {
         match self {
             MenuButton::New => write!(f, "New Game"),
             MenuButton::Reset => write!(f, "Reset"),
+            MenuButton::Undo => write!(f, "Undo"),
+            MenuButton::Redo => write!(f, "Redo"),
+            MenuButton::Check => write!(f, "Check"),
+            MenuButton::FindMistake => write!(f, "Find Mistake"),
+            MenuButton::Heatmap => write!(f, "Heatmap"),
+            MenuButton::Logic => write!(f, "Logic"),
+            MenuButton::RevealCell => write!(f, "Reveal Cell"),
+            MenuButton::RevealBox => write!(f, "Reveal Box"),
+            MenuButton::RevealRow => write!(f, "Reveal Row"),
             MenuButton::Solve => write!(f, "Solve"),
+            MenuButton::WatchSolve => write!(f, "Watch Solve"),
+            MenuButton::Files => write!(f, "Files"),
+            MenuButton::Settings => write!(f, "Settings"),
             MenuButton::ThemeToggle => write!(f, "Theme"),
+            MenuButton::Title => write!(f, "Title"),
             MenuButton::Quit => write!(f, "Quit"),
         }
     }
 }
 
+/// Submenu beside the menu, for puzzle files
+#[derive(Component)]
+struct FileMenu;
+
+#[derive(EnumIter, Debug, Copy, Clone, Component)]
+enum FileButton {
+    Import,
+    Export,
+}
+
+impl Display for FileButton {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileButton::Import => write!(f, "Import"),
+            FileButton::Export => write!(f, "Export"),
+        }
+    }
+}
+
+fn spawn_file_menu(commands: &mut Commands, theme: &Theme, font_assets: &FontAssets) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                // just left of the menu
+                position: UiRect {
+                    top: Val::Px(20.0),
+                    right: Val::Px(176.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(2.0)),
+                ..default()
+            },
+            background_color: theme.line_thick.into(),
+            z_index: ZIndex::Global(5),
+            ..default()
+        })
+        .insert(ThickLine)
+        .insert(FileMenu)
+        .insert(Name::new("File Menu"))
+        .with_children(|parent| {
+            for button in FileButton::iter() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(150.0), Val::Px(50.0)),
+                                margin: UiRect::all(Val::Px(2.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: theme.btn_normal.into(),
+                            ..default()
+                        },
+                        Name::new(format!("{button}")),
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: font_assets.status(format!("{button}"), theme),
+                            ..default()
+                        });
+                    });
+            }
+        });
+}
+
 pub fn create_menu(parent: &mut ChildBuilder, theme: &Theme, font_assets: &FontAssets) {
     // Menu buttons
     parent
@@ -105,9 +209,22 @@ pub fn create_menu(parent: &mut ChildBuilder, theme: &Theme, font_assets: &FontA
 }
 
 fn menu_button_system(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    file_menu_query: Query<Entity, With<FileMenu>>,
     mut interaction_query: Query<(&Interaction, &MenuButton), (Changed<Interaction>, With<Button>)>,
     mut reset_event: EventWriter<Reset>,
+    mut undo_event: EventWriter<Undo>,
+    mut redo_event: EventWriter<Redo>,
     mut solve_event: EventWriter<Solve>,
+    mut watch_solve_event: EventWriter<WatchSolveStart>,
+    mut check_event: EventWriter<Check>,
+    mut find_wrong_move_event: EventWriter<FindWrongMove>,
+    mut heatmap_toggle_event: EventWriter<HeatmapToggle>,
+    mut logic_toggle_event: EventWriter<LogicToggle>,
+    mut reveal_event: EventWriter<Reveal>,
+    mut settings_toggle_event: EventWriter<SettingsToggle>,
     mut theme_toggle_event: EventWriter<ThemeToggle>,
     mut exit_event: EventWriter<AppExit>,
     mut app_state: ResMut<State<AppState>>,
@@ -121,12 +238,56 @@ fn menu_button_system(
                 MenuButton::Reset => {
                     reset_event.send(Reset);
                 }
+                MenuButton::Undo => {
+                    undo_event.send(Undo);
+                }
+                MenuButton::Redo => {
+                    redo_event.send(Redo);
+                }
+                MenuButton::Check => {
+                    check_event.send(Check);
+                }
+                MenuButton::FindMistake => {
+                    find_wrong_move_event.send(FindWrongMove);
+                }
+                MenuButton::Heatmap => {
+                    heatmap_toggle_event.send(HeatmapToggle);
+                }
+                MenuButton::Logic => {
+                    logic_toggle_event.send(LogicToggle);
+                }
+                MenuButton::RevealCell => {
+                    reveal_event.send(Reveal(RevealScope::Cell));
+                }
+                MenuButton::RevealBox => {
+                    reveal_event.send(Reveal(RevealScope::Box));
+                }
+                MenuButton::RevealRow => {
+                    reveal_event.send(Reveal(RevealScope::Row));
+                }
                 MenuButton::Solve => {
                     solve_event.send(Solve);
                 }
+                MenuButton::WatchSolve => {
+                    watch_solve_event.send(WatchSolveStart);
+                }
+                MenuButton::Files => {
+                    if file_menu_query.is_empty() {
+                        spawn_file_menu(&mut commands, &theme, &font_assets);
+                    }
+                    for menu in file_menu_query.iter() {
+                        commands.entity(menu).despawn_recursive();
+                    }
+                }
+                MenuButton::Settings => {
+                    settings_toggle_event.send(SettingsToggle);
+                }
                 MenuButton::ThemeToggle => {
                     theme_toggle_event.send(ThemeToggle);
                 }
+                MenuButton::Title => {
+                    app_state.set(AppState::Menu).unwrap();
+                }
                 MenuButton::Quit => {
                     exit_event.send(AppExit);
                 }
@@ -136,3 +297,23 @@ fn menu_button_system(
     }
 }
 
+fn file_menu_button_system(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &FileButton), (Changed<Interaction>, With<Button>)>,
+    file_menu_query: Query<Entity, With<FileMenu>>,
+    mut import_event: EventWriter<ImportPuzzle>,
+    mut export_event: EventWriter<ExportPuzzle>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            FileButton::Import => import_event.send(ImportPuzzle),
+            FileButton::Export => export_event.send(ExportPuzzle),
+        }
+        for menu in file_menu_query.iter() {
+            commands.entity(menu).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,753 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    window::WindowResized,
+};
+use futures_lite::future;
+use rand::{seq::SliceRandom, RngCore};
+
+use crate::{
+    board::{CellMenuKeys, Value},
+    cleanup,
+    settings::{MistakeMode, Preferences},
+    ui::{FontAssets, Theme, ThickLine},
+    AppState,
+};
+
+/// Width and height of the samurai board in cells
+pub const SIZE: usize = 21;
+/// Top left cell of each grid, the center one shares a corner box with each of the others
+const GRID_OFFSETS: [(usize, usize); 5] = [(0, 0), (12, 0), (6, 6), (0, 12), (12, 12)];
+/// Search steps allowed when proving a clue can go, keeping it if the proof runs long
+const REDUCE_BUDGET: usize = 2_000;
+/// Search steps allowed when filling the empty board, starting over if it runs long
+const FILL_BUDGET: usize = 100_000;
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 4.0;
+const ZOOM_STEP: f32 = 1.25;
+const PAN_STEP: f32 = 40.0;
+const THIN_LINE: f32 = 2.0;
+const THICK_LINE: f32 = 4.0;
+
+pub struct SamuraiPlugin;
+
+impl Plugin for SamuraiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SamuraiGame>()
+            .init_resource::<SamuraiView>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Samurai)
+                    .with_system(setup_samurai)
+                    .with_system(start_samurai_game),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Samurai)
+                    .with_system(handle_samurai_task)
+                    .with_system(samurai_cell_button_system)
+                    .with_system(samurai_button_system)
+                    .with_system(samurai_keyboard_input)
+                    .with_system(samurai_mouse_input)
+                    .with_system(layout_samurai)
+                    .with_system(update_samurai_cells),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Samurai).with_system(cleanup));
+    }
+}
+
+/// Values on the samurai board, indexed by row then column, cells outside the grids stay empty
+pub type SamuraiValues = [[Option<Value>; SIZE]; SIZE];
+
+/// Whether a cell belongs to any of the five grids
+pub fn is_active(x: usize, y: usize) -> bool {
+    GRID_OFFSETS
+        .iter()
+        .any(|(ox, oy)| (*ox..ox + 9).contains(&x) && (*oy..oy + 9).contains(&y))
+}
+
+/// The cells of all five grids, numbered once each, and which of them see each other
+struct SamuraiLayout {
+    cells: Vec<(usize, usize)>,
+    peers: Vec<Vec<usize>>,
+}
+
+impl SamuraiLayout {
+    fn new() -> Self {
+        let mut index = [[None; SIZE]; SIZE];
+        let mut cells = Vec::new();
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                if is_active(x, y) {
+                    index[y][x] = Some(cells.len());
+                    cells.push((x, y));
+                }
+            }
+        }
+
+        // a shared cell sees the rows, columns and boxes of both its grids
+        let mut peers = vec![Vec::new(); cells.len()];
+        for (ox, oy) in GRID_OFFSETS {
+            for (x, y) in (0..81).map(|i| (i % 9, i / 9)) {
+                let a = index[oy + y][ox + x].unwrap();
+                for (px, py) in (0..81).map(|i| (i % 9, i / 9)) {
+                    let same_box = x / 3 == px / 3 && y / 3 == py / 3;
+                    if (x, y) != (px, py) && (x == px || y == py || same_box) {
+                        peers[a].push(index[oy + py][ox + px].unwrap());
+                    }
+                }
+            }
+        }
+        for list in peers.iter_mut() {
+            list.sort_unstable();
+            list.dedup();
+        }
+        Self { cells, peers }
+    }
+
+    /// Digits still open to a cell, as bits 1 to 9
+    fn candidates(&self, values: &[u8], i: usize) -> u16 {
+        self.peers[i]
+            .iter()
+            .fold(0b11_1111_1110, |mask, peer| mask & !(1 << values[*peer]))
+    }
+
+    /// Count solutions up to `limit`, cells with the fewest candidates first, keeping the last found
+    fn search(
+        &self,
+        values: &mut [u8],
+        limit: usize,
+        budget: &mut usize,
+        mut rng: Option<&mut dyn RngCore>,
+        found: &mut Option<Vec<u8>>,
+    ) -> usize {
+        if *budget == 0 {
+            return limit;
+        }
+        *budget -= 1;
+
+        let mut best: Option<(usize, u16)> = None;
+        for i in 0..values.len() {
+            if values[i] != 0 {
+                continue;
+            }
+            let mask = self.candidates(values, i);
+            match best {
+                _ if mask == 0 => return 0,
+                Some((_, fewest)) if fewest.count_ones() <= mask.count_ones() => {}
+                _ => best = Some((i, mask)),
+            }
+        }
+        let (i, mask) = match best {
+            Some(best) => best,
+            None => {
+                *found = Some(values.to_vec());
+                return 1;
+            }
+        };
+
+        let mut digits = (1..=9u8).filter(|d| mask & (1 << d) != 0).collect::<Vec<_>>();
+        if let Some(rng) = rng.as_deref_mut() {
+            digits.shuffle(rng);
+        }
+        let mut count = 0;
+        for digit in digits {
+            values[i] = digit;
+            count += self.search(values, limit - count, budget, rng.as_deref_mut(), found);
+            if count >= limit {
+                break;
+            }
+        }
+        values[i] = 0;
+        count
+    }
+
+    fn to_values(&self, digits: &[u8]) -> SamuraiValues {
+        let mut values = [[None; SIZE]; SIZE];
+        for (i, (x, y)) in self.cells.iter().enumerate() {
+            if digits[i] != 0 {
+                values[*y][*x] = Some(Value::from(digits[i] as usize));
+            }
+        }
+        values
+    }
+}
+
+/// A random full board, then clues removed in random order while the solution stays unique,
+/// `progress` holds the percentage of clues tried so far
+pub fn generate_samurai(
+    rng: &mut dyn RngCore,
+    progress: &AtomicUsize,
+) -> (SamuraiValues, SamuraiValues) {
+    let layout = SamuraiLayout::new();
+    let solution = loop {
+        let mut digits = vec![0; layout.cells.len()];
+        let mut found = None;
+        let mut budget = FILL_BUDGET;
+        layout.search(&mut digits, 1, &mut budget, Some(&mut *rng), &mut found);
+        if let (Some(found), true) = (found, budget > 0) {
+            break found;
+        }
+    };
+
+    let mut givens = solution.clone();
+    let mut order = (0..givens.len()).collect::<Vec<_>>();
+    order.shuffle(rng);
+    let cells = order.len();
+    for (tried, i) in order.into_iter().enumerate() {
+        progress.store(tried * 100 / cells, Ordering::Relaxed);
+        let kept = givens[i];
+        givens[i] = 0;
+        let mut budget = REDUCE_BUDGET;
+        let count = layout.search(&mut givens.clone(), 2, &mut budget, None, &mut None);
+        // a proof that ran out of budget counts as ambiguous
+        if count != 1 || budget == 0 {
+            givens[i] = kept;
+        }
+    }
+    (layout.to_values(&givens), layout.to_values(&solution))
+}
+
+/// Resource for the samurai puzzle being played
+#[derive(Resource, Default)]
+pub struct SamuraiGame {
+    givens: Option<Box<SamuraiValues>>,
+    solution: Option<Box<SamuraiValues>>,
+    entries: Box<SamuraiValues>,
+    selected: Option<(usize, usize)>,
+    won: bool,
+}
+
+impl SamuraiGame {
+    fn is_given(&self, x: usize, y: usize) -> bool {
+        self.givens.as_ref().map_or(false, |givens| givens[y][x].is_some())
+    }
+
+    fn value(&self, x: usize, y: usize) -> Option<Value> {
+        self.givens
+            .as_ref()
+            .and_then(|givens| givens[y][x])
+            .or(self.entries[y][x])
+    }
+
+    fn is_mistake(&self, x: usize, y: usize) -> bool {
+        match (&self.solution, self.entries[y][x]) {
+            (Some(solution), Some(value)) => solution[y][x] != Some(value),
+            _ => false,
+        }
+    }
+
+    fn is_solved(&self) -> bool {
+        match &self.solution {
+            Some(solution) => (0..SIZE * SIZE)
+                .map(|i| (i % SIZE, i / SIZE))
+                .filter(|(x, y)| is_active(*x, *y))
+                .all(|(x, y)| self.value(x, y) == solution[y][x]),
+            None => false,
+        }
+    }
+
+    fn enter(&mut self, value: Option<Value>) {
+        let (x, y) = match self.selected {
+            Some(selected) => selected,
+            None => return,
+        };
+        if self.won || self.givens.is_none() || self.is_given(x, y) {
+            return;
+        }
+        self.entries[y][x] = value;
+        self.won = self.is_solved();
+    }
+}
+
+/// Resource for how far the board is zoomed in and dragged from the center
+#[derive(Resource)]
+pub struct SamuraiView {
+    zoom: f32,
+    pan: Vec2,
+}
+
+impl Default for SamuraiView {
+    fn default() -> Self {
+        Self {
+            zoom: MIN_ZOOM,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
+impl SamuraiView {
+    fn zoom_by(&mut self, factor: f32) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        // keep the same spot in the middle of the view
+        self.pan *= zoom / self.zoom;
+        self.zoom = zoom;
+    }
+}
+
+/// Generation off the main thread, with how far it has got in percent
+#[derive(Resource)]
+struct SamuraiTask {
+    task: Task<(SamuraiValues, SamuraiValues)>,
+    progress: Arc<AtomicUsize>,
+}
+
+#[derive(Component)]
+struct SamuraiBoard;
+
+#[derive(Component)]
+struct SamuraiBox(usize, usize);
+
+#[derive(Component)]
+pub struct SamuraiCell(usize, usize);
+
+#[derive(Component)]
+struct SamuraiStatus;
+
+#[derive(Component, Debug, Copy, Clone)]
+enum SamuraiButton {
+    New,
+    ZoomIn,
+    ZoomOut,
+    Fit,
+    Title,
+    Digit(Option<Value>),
+}
+
+fn start_samurai_game(mut commands: Commands, mut game: ResMut<SamuraiGame>) {
+    spawn_samurai_task(&mut commands, &mut game);
+}
+
+fn spawn_samurai_task(commands: &mut Commands, game: &mut SamuraiGame) {
+    *game = SamuraiGame::default();
+    let progress = Arc::new(AtomicUsize::new(0));
+    let tried = progress.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        let mut rng = rand::thread_rng();
+        generate_samurai(&mut rng, &tried)
+    });
+    commands.insert_resource(SamuraiTask { task, progress });
+}
+
+fn handle_samurai_task(
+    mut commands: Commands,
+    task: Option<ResMut<SamuraiTask>>,
+    mut game: ResMut<SamuraiGame>,
+    mut status_query: Query<&mut Text, With<SamuraiStatus>>,
+) {
+    let mut task = match task {
+        Some(task) => task,
+        None => return,
+    };
+    match future::block_on(future::poll_once(&mut task.task)) {
+        Some((givens, solution)) => {
+            game.givens = Some(Box::new(givens));
+            game.solution = Some(Box::new(solution));
+            commands.remove_resource::<SamuraiTask>();
+        }
+        None => {
+            let percent = task.progress.load(Ordering::Relaxed);
+            for mut text in status_query.iter_mut() {
+                text.sections[0].value = format!("Generating... {percent}%");
+            }
+        }
+    }
+}
+
+fn setup_samurai(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    theme: Res<Theme>,
+    mut view: ResMut<SamuraiView>,
+) {
+    *view = SamuraiView::default();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Samurai"))
+        .with_children(|parent| {
+            spawn_button_row(
+                parent,
+                &theme,
+                &font_assets,
+                &[
+                    (SamuraiButton::New, "New"),
+                    (SamuraiButton::ZoomIn, "+"),
+                    (SamuraiButton::ZoomOut, "-"),
+                    (SamuraiButton::Fit, "Fit"),
+                    (SamuraiButton::Title, "Title"),
+                ],
+            );
+
+            // the board is dragged and zoomed inside this, anything outside is clipped
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Auto),
+                        flex_grow: 1.0,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        overflow: Overflow::Hidden,
+                        ..default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..default()
+                })
+                .insert(Name::new("Samurai View"))
+                .with_children(|parent| {
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                background_color: Color::NONE.into(),
+                                ..default()
+                            },
+                            Name::new("Samurai Board"),
+                            SamuraiBoard,
+                        ))
+                        .with_children(|parent| {
+                            spawn_samurai_cells(parent, &theme, &font_assets);
+                        });
+                });
+
+            let digits = std::iter::once((SamuraiButton::Digit(None), "X".to_string()))
+                .chain((1..=9).map(|d| (SamuraiButton::Digit(Some(Value::from(d))), d.to_string())))
+                .collect::<Vec<_>>();
+            let digits = digits
+                .iter()
+                .map(|(button, label)| (*button, label.as_str()))
+                .collect::<Vec<_>>();
+            spawn_button_row(parent, &theme, &font_assets, &digits);
+
+            parent.spawn((
+                TextBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Px(5.0)),
+                        ..default()
+                    },
+                    text: font_assets.status("Generating...", &theme),
+                    ..default()
+                },
+                SamuraiStatus,
+            ));
+        });
+}
+
+fn spawn_button_row(
+    parent: &mut ChildBuilder,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    buttons: &[(SamuraiButton, &str)],
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                margin: UiRect::all(Val::Px(5.0)),
+                padding: UiRect::all(Val::Px(2.0)),
+                ..default()
+            },
+            background_color: theme.line_thick.into(),
+            ..default()
+        })
+        .insert(ThickLine)
+        .with_children(|parent| {
+            for (button, label) in buttons.iter() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(80.0), Val::Px(50.0)),
+                                margin: UiRect::all(Val::Px(2.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: theme.btn_normal.into(),
+                            ..default()
+                        },
+                        Name::new(label.to_string()),
+                        *button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: font_assets.status(*label, theme),
+                            ..default()
+                        });
+                    });
+            }
+        });
+}
+
+/// Boxes are drawn behind their cells so the gaps between cells show as grid lines
+fn spawn_samurai_cells(parent: &mut ChildBuilder, theme: &Theme, font_assets: &FontAssets) {
+    for by in 0..SIZE / 3 {
+        for bx in 0..SIZE / 3 {
+            if !is_active(bx * 3, by * 3) {
+                continue;
+            }
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    background_color: theme.line_thick.into(),
+                    ..default()
+                },
+                ThickLine,
+                SamuraiBox(bx, by),
+            ));
+        }
+    }
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            if !is_active(x, y) {
+                continue;
+            }
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: theme.btn_normal.into(),
+                        ..default()
+                    },
+                    Name::new(format!("Samurai Cell {x}x{y}")),
+                    SamuraiCell(x, y),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle {
+                        text: font_assets.cell(" ", "", theme),
+                        ..default()
+                    });
+                });
+        }
+    }
+}
+
+fn samurai_cell_button_system(
+    interaction_query: Query<(&Interaction, &SamuraiCell), Changed<Interaction>>,
+    mut game: ResMut<SamuraiGame>,
+) {
+    for (interaction, SamuraiCell(x, y)) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            game.selected = Some((*x, *y));
+        }
+    }
+}
+
+fn samurai_button_system(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &SamuraiButton), (Changed<Interaction>, With<Button>)>,
+    mut game: ResMut<SamuraiGame>,
+    mut view: ResMut<SamuraiView>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match *button {
+            SamuraiButton::New => spawn_samurai_task(&mut commands, &mut game),
+            SamuraiButton::ZoomIn => view.zoom_by(ZOOM_STEP),
+            SamuraiButton::ZoomOut => view.zoom_by(1.0 / ZOOM_STEP),
+            SamuraiButton::Fit => *view = SamuraiView::default(),
+            SamuraiButton::Title => app_state.set(AppState::Menu).unwrap(),
+            SamuraiButton::Digit(value) => game.enter(value),
+        }
+    }
+}
+
+fn samurai_keyboard_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    cell_menu_keys: Res<CellMenuKeys>,
+    mut game: ResMut<SamuraiGame>,
+    mut view: ResMut<SamuraiView>,
+) {
+    for key_value in cell_menu_keys.0.iter() {
+        if keyboard_input.any_just_pressed(key_value.key.iter().copied()) {
+            game.enter(key_value.value);
+        }
+    }
+
+    for (key, step) in [
+        (KeyCode::Left, Vec2::new(1.0, 0.0)),
+        (KeyCode::Right, Vec2::new(-1.0, 0.0)),
+        (KeyCode::Up, Vec2::new(0.0, 1.0)),
+        (KeyCode::Down, Vec2::new(0.0, -1.0)),
+    ] {
+        if keyboard_input.just_pressed(key) {
+            view.pan += step * PAN_STEP;
+        }
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) {
+        view.zoom_by(ZOOM_STEP);
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        view.zoom_by(1.0 / ZOOM_STEP);
+    }
+}
+
+/// Wheel zooms, dragging with the right or middle button pans
+fn samurai_mouse_input(
+    mut wheel_events: EventReader<MouseWheel>,
+    mut motion_events: EventReader<MouseMotion>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut view: ResMut<SamuraiView>,
+) {
+    for wheel in wheel_events.iter() {
+        if wheel.y > 0.0 {
+            view.zoom_by(ZOOM_STEP);
+        } else if wheel.y < 0.0 {
+            view.zoom_by(1.0 / ZOOM_STEP);
+        }
+    }
+    let dragging = mouse_input.any_pressed([MouseButton::Right, MouseButton::Middle]);
+    for motion in motion_events.iter() {
+        if dragging {
+            view.pan += motion.delta;
+        }
+    }
+}
+
+/// Place every box and cell for the current zoom, the board itself is offset by the pan
+fn layout_samurai(
+    view: Res<SamuraiView>,
+    windows: Res<Windows>,
+    mut window_resize: EventReader<WindowResized>,
+    added_query: Query<(), Added<SamuraiBoard>>,
+    mut board_query: Query<&mut Style, With<SamuraiBoard>>,
+    mut box_query: Query<(&SamuraiBox, &mut Style), Without<SamuraiBoard>>,
+    mut cell_query: Query<
+        (&SamuraiCell, &mut Style, &Children),
+        (Without<SamuraiBoard>, Without<SamuraiBox>),
+    >,
+    mut text_query: Query<&mut Text>,
+) {
+    let resized = window_resize.iter().count() > 0;
+    if !view.is_changed() && !resized && added_query.is_empty() {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+
+    // at the lowest zoom the whole board fits, with room for the button rows
+    let fit = window.width().min(window.height() - 150.0).max(100.0) / (SIZE as f32 + 3.0);
+    let cell_size = fit * view.zoom;
+    let box_size = 3.0 * cell_size + 2.0 * THIN_LINE;
+    let box_pitch = box_size + THICK_LINE;
+    let offset = |i: usize| THICK_LINE + (i / 3) as f32 * box_pitch + (i % 3) as f32 * (cell_size + THIN_LINE);
+    let board_size = (SIZE / 3) as f32 * box_pitch + THICK_LINE;
+
+    for mut style in board_query.iter_mut() {
+        style.size = Size::new(Val::Px(board_size), Val::Px(board_size));
+        style.position = UiRect {
+            left: Val::Px(view.pan.x),
+            top: Val::Px(view.pan.y),
+            ..default()
+        };
+    }
+    for (SamuraiBox(bx, by), mut style) in box_query.iter_mut() {
+        style.position = UiRect {
+            left: Val::Px(*bx as f32 * box_pitch),
+            top: Val::Px(*by as f32 * box_pitch),
+            ..default()
+        };
+        style.size = Size::new(
+            Val::Px(box_size + 2.0 * THICK_LINE),
+            Val::Px(box_size + 2.0 * THICK_LINE),
+        );
+    }
+    for (SamuraiCell(x, y), mut style, children) in cell_query.iter_mut() {
+        style.position = UiRect {
+            left: Val::Px(offset(*x)),
+            top: Val::Px(offset(*y)),
+            ..default()
+        };
+        style.size = Size::new(Val::Px(cell_size), Val::Px(cell_size));
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].style.font_size = cell_size * 0.7;
+            }
+        }
+    }
+}
+
+fn update_samurai_cells(
+    game: Res<SamuraiGame>,
+    theme: Res<Theme>,
+    preferences: Res<Preferences>,
+    added_query: Query<(), Added<SamuraiCell>>,
+    hovered_query: Query<(), (With<SamuraiCell>, Changed<Interaction>)>,
+    mut cell_query: Query<(&SamuraiCell, &Interaction, &mut BackgroundColor, &Children)>,
+    mut text_query: Query<&mut Text, Without<SamuraiStatus>>,
+    mut status_query: Query<&mut Text, With<SamuraiStatus>>,
+) {
+    if !game.is_changed()
+        && !theme.is_changed()
+        && added_query.is_empty()
+        && hovered_query.is_empty()
+    {
+        return;
+    }
+
+    let show_mistakes = preferences.mistake_mode != MistakeMode::Off;
+    for (SamuraiCell(x, y), interaction, mut color, children) in cell_query.iter_mut() {
+        *color = if game.selected == Some((*x, *y)) {
+            theme.btn_selected.into()
+        } else if *interaction == Interaction::Hovered {
+            theme.btn_hovered.into()
+        } else {
+            theme.btn_normal.into()
+        };
+
+        let style = if game.is_given(*x, *y) {
+            theme.value_given
+        } else {
+            theme.value_player
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                let section = &mut text.sections[0];
+                section.value = game
+                    .value(*x, *y)
+                    .map(|value| value.to_string())
+                    .unwrap_or_else(|| " ".to_string());
+                section.style.color = if show_mistakes && game.is_mistake(*x, *y) {
+                    theme.text_error
+                } else {
+                    style.color
+                };
+            }
+        }
+    }
+
+    let status = if game.givens.is_none() {
+        "Generating..."
+    } else if game.won {
+        "Solved!"
+    } else {
+        ""
+    };
+    for mut text in status_query.iter_mut() {
+        text.sections[0].value = status.to_string();
+    }
+}
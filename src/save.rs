@@ -0,0 +1,186 @@
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use sudoku_variants::{constraint::DefaultConstraint, Sudoku, SudokuGrid};
+
+use crate::{
+    annotation::{Annotation, Annotations},
+    assist::{grid_from, Grid},
+    board::{Cell, CellPosition},
+    checkpoint::Checkpoints,
+    game::GameStatus,
+    history::BoardSnapshot,
+    storage,
+    trainer::Training,
+    variant::VariantRules,
+    AppState, SudokuContainer, SudokuSolution,
+};
+
+const SAVE_FILE: &str = "game.json";
+/// Seconds without changes before the game is written, so a burst of moves saves once
+const SAVE_DELAY: f32 = 1.0;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingSave>()
+            .add_system(restore_saved_game)
+            .add_system(save_game.after(restore_saved_game))
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(flush_save))
+            .add_system_to_stage(CoreStage::Last, save_on_app_exit);
+    }
+}
+
+/// Resource for the delay before the latest changes are written, None when nothing is waiting
+#[derive(Resource, Default)]
+struct PendingSave(Option<Timer>);
+
+/// Everything a save is made from
+#[derive(SystemParam)]
+struct CurrentGame<'w, 's> {
+    annotations: Res<'w, Annotations>,
+    checkpoints: Res<'w, Checkpoints>,
+    sudoku: Res<'w, SudokuContainer>,
+    solution: Res<'w, SudokuSolution>,
+    rules: Res<'w, VariantRules>,
+    query: Query<'w, 's, (&'static Cell, &'static CellPosition)>,
+}
+
+impl<'w, 's> CurrentGame<'w, 's> {
+    fn save(&self) {
+        let saved = SavedGame {
+            givens: grid_from(self.sudoku.0.grid()),
+            solution: self.solution.0.as_ref().map(grid_from),
+            cells: BoardSnapshot::new(self.query.iter()),
+            annotations: self.annotations.0.clone(),
+            checkpoints: self.checkpoints.clone(),
+            rules: self.rules.clone(),
+        };
+        storage::save(SAVE_FILE, &saved);
+    }
+}
+
+/// The game in progress, saved as it is played so it can be resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub givens: Grid,
+    pub solution: Option<Grid>,
+    pub cells: BoardSnapshot,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[serde(default)]
+    pub checkpoints: Checkpoints,
+    #[serde(default)]
+    pub rules: VariantRules,
+}
+
+impl SavedGame {
+    pub fn load() -> Option<Self> {
+        storage::load(SAVE_FILE)
+    }
+
+    /// Insert the saved puzzle, the cells are restored once the board is spawned
+    pub fn resume(self, commands: &mut Commands) {
+        let mut sudoku = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+        fill_grid(sudoku.grid_mut(), &self.givens);
+        let solution = self.solution.as_ref().map(|values| {
+            let mut grid = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap().grid().clone();
+            fill_grid(&mut grid, values);
+            grid
+        });
+
+        commands.insert_resource(SudokuContainer(sudoku));
+        commands.insert_resource(SudokuSolution(solution));
+        commands.insert_resource(self.rules.clone());
+        commands.insert_resource(PendingRestore(self));
+    }
+}
+
+/// Resource holding a resumed game until its board exists
+#[derive(Resource)]
+struct PendingRestore(SavedGame);
+
+pub fn fill_grid(grid: &mut SudokuGrid, values: &Grid) {
+    for (y, row) in values.iter().enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            if let Some(value) = value {
+                grid.set_cell(x, y, (*value).into()).unwrap();
+            }
+        }
+    }
+}
+
+pub(crate) fn restore_saved_game(
+    mut commands: Commands,
+    pending: Option<Res<PendingRestore>>,
+    added_query: Query<(), Added<CellPosition>>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+    mut annotations: ResMut<Annotations>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    let pending = match pending {
+        Some(pending) if !added_query.is_empty() => pending,
+        _ => return,
+    };
+    for (mut cell, pos) in query.iter_mut() {
+        cell.restore(pending.0.cells.0[pos.y][pos.x]);
+    }
+    annotations.0 = pending.0.annotations.clone();
+    *checkpoints = pending.0.checkpoints.clone();
+    commands.remove_resource::<PendingRestore>();
+}
+
+/// Save once changes settle, a finished game is removed so it isn't resumed
+fn save_game(
+    time: Res<Time>,
+    app_state: Res<State<AppState>>,
+    training: Res<Training>,
+    status: Res<GameStatus>,
+    mut pending: ResMut<PendingSave>,
+    changed_query: Query<(), Changed<Cell>>,
+    game: CurrentGame,
+) {
+    // tutorial boards and training puzzles aren't games to resume
+    if app_state.current() != &AppState::Playing || training.target.is_some() {
+        return;
+    }
+    if *status != GameStatus::Playing {
+        if status.is_changed() {
+            pending.0 = None;
+            storage::remove(SAVE_FILE);
+        }
+        return;
+    }
+    if game.query.is_empty() {
+        return;
+    }
+    if !changed_query.is_empty() || game.annotations.is_changed() || game.checkpoints.is_changed()
+    {
+        pending.0 = Some(Timer::from_seconds(SAVE_DELAY, TimerMode::Once));
+    }
+    let settled = match pending.0.as_mut() {
+        Some(timer) => timer.tick(time.delta()).finished(),
+        None => return,
+    };
+    if settled {
+        pending.0 = None;
+        game.save();
+    }
+}
+
+/// Write changes still waiting on the delay before the board goes away
+fn flush_save(mut pending: ResMut<PendingSave>, game: CurrentGame) {
+    if pending.0.take().is_some() {
+        game.save();
+    }
+}
+
+fn save_on_app_exit(
+    exit_events: EventReader<AppExit>,
+    pending: ResMut<PendingSave>,
+    game: CurrentGame,
+) {
+    if !exit_events.is_empty() {
+        flush_save(pending, game);
+    }
+}
@@ -0,0 +1,783 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use sudoku_variants::{constraint::DefaultConstraint, Sudoku, SudokuGrid};
+
+use crate::{
+    assist::Grid,
+    board::{
+        create_board, create_cell_menu, BoardSelect, BoardSelected, Cell, CellPosition, EntryMode,
+        ValueOrigin,
+    },
+    cleanup,
+    fpuzzles::{self, ImportedPuzzle},
+    game::GameStatus,
+    history::History,
+    logic::{solve_path, Technique},
+    save::fill_grid,
+    storage,
+    trainer::Training,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::{
+        find_solutions, BorderClue, BorderKind, Inequality, Line, LineKind, ParityClue,
+        RegionSetting, VariantRules, SEARCH_BUDGET,
+    },
+    AppState, SudokuContainer, SudokuSolution,
+};
+
+/// The puzzle being set, kept between visits once saved
+const DRAFT_FILE: &str = "setter.json";
+const EXPORT_FILE: &str = "setter-export.json";
+/// Seconds without edits before the solutions are counted again
+const CHECK_DELAY: f32 = 0.3;
+
+pub struct SetterPlugin;
+
+impl Plugin for SetterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Setter>()
+            .add_system_set(SystemSet::on_enter(AppState::Setter).with_system(setup_setter))
+            .add_system_set(
+                SystemSet::on_update(AppState::Setter)
+                    .with_system(restore_draft)
+                    .with_system(place_clues)
+                    .with_system(setter_button_system)
+                    .with_system(update_setter_labels)
+                    .with_system(start_solution_count)
+                    .with_system(handle_solution_count_task)
+                    .with_system(update_setter_status),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Setter)
+                    .with_system(exit_setter)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+/// What selecting a cell does, besides letting digits be typed as givens
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SetterTool {
+    Givens,
+    /// Each selected cell extends the line, selecting its end again finishes it
+    Line(LineKind),
+    /// Placed between the two orthogonal neighbors selected one after the other
+    Border(BorderKind),
+    /// The first of the two neighbors selected is the greater
+    Greater,
+    Even,
+    Odd,
+    /// Remove every clue on the selected cell
+    Erase,
+}
+
+impl Default for SetterTool {
+    fn default() -> Self {
+        Self::Givens
+    }
+}
+
+impl Display for SetterTool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetterTool::Givens => write!(f, "Givens"),
+            SetterTool::Line(kind) => write!(f, "{kind}"),
+            SetterTool::Border(BorderKind::White) => write!(f, "White Dot"),
+            SetterTool::Border(BorderKind::Black) => write!(f, "Black Dot"),
+            SetterTool::Border(BorderKind::X) => write!(f, "X"),
+            SetterTool::Border(BorderKind::V) => write!(f, "V"),
+            SetterTool::Greater => write!(f, "Greater Than"),
+            SetterTool::Even => write!(f, "Even"),
+            SetterTool::Odd => write!(f, "Odd"),
+            SetterTool::Erase => write!(f, "Erase"),
+        }
+    }
+}
+
+impl SetterTool {
+    fn all() -> Vec<SetterTool> {
+        let mut tools = vec![SetterTool::Givens];
+        tools.extend(LineKind::iter().map(SetterTool::Line));
+        tools.extend(
+            [
+                BorderKind::White,
+                BorderKind::Black,
+                BorderKind::X,
+                BorderKind::V,
+            ]
+            .map(SetterTool::Border),
+        );
+        tools.extend([
+            SetterTool::Greater,
+            SetterTool::Even,
+            SetterTool::Odd,
+            SetterTool::Erase,
+        ]);
+        tools
+    }
+
+    fn next(self) -> Self {
+        let tools = Self::all();
+        let index = tools.iter().position(|tool| *tool == self).unwrap_or(0);
+        tools[(index + 1) % tools.len()]
+    }
+}
+
+/// Rough difficulty from the hardest technique a logical solve of the givens needs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+    /// The techniques run out before the solution
+    BeyondLogic,
+    /// Classic techniques run out, the variant clues may still be enough
+    NeedsVariantLogic,
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Medium => write!(f, "Medium"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Expert => write!(f, "Expert"),
+            Difficulty::BeyondLogic => write!(f, "Beyond the logic solver"),
+            Difficulty::NeedsVariantLogic => write!(f, "Needs variant logic"),
+        }
+    }
+}
+
+pub fn rate_difficulty(givens: &Grid, rules: &VariantRules) -> Difficulty {
+    let path = solve_path(givens, rules);
+    if !path.solved {
+        return if rules.is_classic() {
+            Difficulty::BeyondLogic
+        } else {
+            Difficulty::NeedsVariantLogic
+        };
+    }
+    match path.hardest() {
+        None | Some(Technique::NakedSingle | Technique::HiddenSingle) => Difficulty::Easy,
+        Some(Technique::Pointing | Technique::Claiming) => Difficulty::Medium,
+        Some(Technique::NakedPair | Technique::HiddenPair | Technique::NakedTriple) => {
+            Difficulty::Hard
+        }
+        Some(Technique::XWing) => Difficulty::Expert,
+    }
+}
+
+/// How many solutions the puzzle being set has
+#[derive(Debug, Clone)]
+enum SolutionCount {
+    Checking,
+    /// The givens already break a rule
+    Broken,
+    NoSolution,
+    Unique(SudokuGrid, Difficulty),
+    Multiple,
+    /// The search gave up before telling
+    Unknown,
+}
+
+impl Default for SolutionCount {
+    fn default() -> Self {
+        Self::Checking
+    }
+}
+
+fn count_solutions(givens: &Grid, rules: &VariantRules) -> SolutionCount {
+    let mut puzzle = Sudoku::new_empty(3, 3, rules.constraint()).unwrap();
+    fill_grid(puzzle.grid_mut(), givens);
+    if !puzzle.is_valid() {
+        return SolutionCount::Broken;
+    }
+    let solutions = match find_solutions(givens, rules, 2, SEARCH_BUDGET) {
+        Some(solutions) => solutions,
+        None => return SolutionCount::Unknown,
+    };
+    match solutions.as_slice() {
+        [] => SolutionCount::NoSolution,
+        [solution] => {
+            fill_grid(puzzle.grid_mut(), solution);
+            SolutionCount::Unique(puzzle.grid().clone(), rate_difficulty(givens, rules))
+        }
+        _ => SolutionCount::Multiple,
+    }
+}
+
+/// Values the setter typed, anything else on the board isn't part of the puzzle
+fn setter_givens<'a>(cells: impl Iterator<Item = (&'a Cell, &'a CellPosition)>) -> Grid {
+    let mut grid = Grid::default();
+    for (cell, pos) in cells {
+        if cell.origin() == ValueOrigin::Player {
+            grid[pos.y][pos.x] = cell.value();
+        }
+    }
+    grid
+}
+
+/// Resource for the setter's tool, the clue being placed and the latest count
+#[derive(Resource, Default)]
+struct Setter {
+    tool: SetterTool,
+    /// Cells of the line being drawn, it joins the rules once it has two
+    drawing: Vec<CellPosition>,
+    /// Index in the rules of the line being drawn
+    drawn: Option<usize>,
+    /// First cell of a border clue or inequality
+    anchor: Option<CellPosition>,
+    count: SolutionCount,
+    /// Counts down after each edit, the solutions are counted once it finishes
+    settle: Option<Timer>,
+    /// Result of the last save or export
+    message: String,
+}
+
+impl Setter {
+    fn stop_placing(&mut self) {
+        self.drawing.clear();
+        self.drawn = None;
+        self.anchor = None;
+    }
+}
+
+/// Count solutions off the main thread, replacing the task drops the stale count
+#[derive(Resource)]
+struct SolutionCountTask(Task<SolutionCount>);
+
+/// A saved puzzle in progress
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SetterDraft {
+    givens: Grid,
+    #[serde(default)]
+    rules: VariantRules,
+}
+
+/// Resource holding the saved givens until the board exists
+#[derive(Resource)]
+struct PendingDraft(Grid);
+
+#[derive(Component)]
+struct SetterStatus;
+
+#[derive(EnumIter, Debug, Copy, Clone, Component)]
+enum SetterButton {
+    Tool,
+    Diagonal,
+    AntiKnight,
+    AntiKing,
+    NonConsecutive,
+    Regions,
+    ClearClues,
+    ClearGivens,
+    Save,
+    Export,
+    Play,
+    Back,
+}
+
+impl Display for SetterButton {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetterButton::Tool => write!(f, "Tool"),
+            SetterButton::Diagonal => write!(f, "Diagonal"),
+            SetterButton::AntiKnight => write!(f, "Anti-Knight"),
+            SetterButton::AntiKing => write!(f, "Anti-King"),
+            SetterButton::NonConsecutive => write!(f, "Non-Consecutive"),
+            SetterButton::Regions => write!(f, "Regions"),
+            SetterButton::ClearClues => write!(f, "Clear Clues"),
+            SetterButton::ClearGivens => write!(f, "Clear Givens"),
+            SetterButton::Save => write!(f, "Save"),
+            SetterButton::Export => write!(f, "Export"),
+            SetterButton::Play => write!(f, "Play"),
+            SetterButton::Back => write!(f, "Back"),
+        }
+    }
+}
+
+impl SetterButton {
+    fn label(&self, setter: &Setter, rules: &VariantRules) -> String {
+        let on_off = |enabled: bool| if enabled { "On" } else { "Off" };
+        let global = rules.global;
+        match self {
+            SetterButton::Tool => format!("{self}: {}", setter.tool),
+            SetterButton::Diagonal => format!("{self}: {}", on_off(global.diagonal)),
+            SetterButton::AntiKnight => format!("{self}: {}", on_off(global.anti_knight)),
+            SetterButton::AntiKing => format!("{self}: {}", on_off(global.anti_king)),
+            SetterButton::NonConsecutive => format!("{self}: {}", on_off(global.non_consecutive)),
+            SetterButton::Regions => {
+                format!("{self}: {}", RegionSetting::from_regions(rules.regions))
+            }
+            _ => format!("{self}"),
+        }
+    }
+}
+
+fn setup_setter(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    theme: Res<Theme>,
+    windows: Res<Windows>,
+    mut setter: ResMut<Setter>,
+    mut status: ResMut<GameStatus>,
+    mut history: ResMut<History>,
+    mut board_selected: ResMut<BoardSelected>,
+    mut entry_mode: ResMut<EntryMode>,
+) {
+    let draft = storage::load::<SetterDraft>(DRAFT_FILE).unwrap_or_default();
+    *setter = Setter::default();
+    // the values typed in are givens, nothing is known to check them against
+    commands.insert_resource(SudokuSolution(None));
+    commands.insert_resource(draft.rules);
+    commands.insert_resource(PendingDraft(draft.givens));
+    *status = GameStatus::Playing;
+    history.clear();
+    *entry_mode = EntryMode::Value;
+    board_selected.entity = None;
+
+    let window = windows.get_primary().unwrap();
+    let empty = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Setter"))
+        .with_children(|parent| {
+            create_board(
+                parent,
+                &theme,
+                &font_assets,
+                empty.grid(),
+                window.width(),
+                window.height(),
+            );
+            create_cell_menu(
+                parent,
+                &theme,
+                &font_assets,
+                window.width(),
+                window.height(),
+            );
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                top: Val::Px(20.0),
+                                right: Val::Px(20.0),
+                                ..default()
+                            },
+                            size: Size::new(Val::Px(320.0), Val::Auto),
+                            flex_direction: FlexDirection::Column,
+                            padding: UiRect::all(Val::Px(2.0)),
+                            ..default()
+                        },
+                        background_color: theme.line_thick.into(),
+                        ..default()
+                    },
+                    ThickLine,
+                    Name::new("Setter Panel"),
+                ))
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(10.0)),
+                                margin: UiRect::all(Val::Px(2.0)),
+                                ..default()
+                            },
+                            background_color: theme.btn_normal.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle {
+                                    style: Style {
+                                        max_size: Size::new(Val::Px(296.0), Val::Undefined),
+                                        ..default()
+                                    },
+                                    text: font_assets.status("", &theme),
+                                    ..default()
+                                },
+                                SetterStatus,
+                            ));
+                        });
+                    for button in SetterButton::iter() {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(312.0), Val::Px(40.0)),
+                                        margin: UiRect::all(Val::Px(2.0)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: theme.btn_normal.into(),
+                                    ..default()
+                                },
+                                Name::new(format!("{button}")),
+                                button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle {
+                                    text: font_assets.status(format!("{button}"), &theme),
+                                    ..default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+/// Type the saved givens back in once the board's cells exist
+fn restore_draft(
+    mut commands: Commands,
+    draft: Option<Res<PendingDraft>>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    let draft = match draft {
+        Some(draft) => draft,
+        None => return,
+    };
+    if query.is_empty() {
+        return;
+    }
+    for (mut cell, pos) in query.iter_mut() {
+        if let Some(value) = draft.0[pos.y][pos.x] {
+            cell.set_value(value, ValueOrigin::Player);
+        }
+    }
+    commands.remove_resource::<PendingDraft>();
+}
+
+fn king_adjacent(a: CellPosition, b: CellPosition) -> bool {
+    a != b && a.x.abs_diff(b.x) <= 1 && a.y.abs_diff(b.y) <= 1
+}
+
+fn orthogonal(a: CellPosition, b: CellPosition) -> bool {
+    a.x.abs_diff(b.x) + a.y.abs_diff(b.y) == 1
+}
+
+fn extend_line(setter: &mut Setter, rules: &mut VariantRules, kind: LineKind, pos: CellPosition) {
+    let extends = setter
+        .drawing
+        .last()
+        .map_or(false, |end| king_adjacent(*end, pos))
+        && !setter.drawing.contains(&pos);
+    if !extends {
+        // selecting the end again finishes the line, anywhere else starts a new one
+        let finished = setter.drawing.last() == Some(&pos);
+        setter.stop_placing();
+        if !finished {
+            setter.drawing.push(pos);
+        }
+        return;
+    }
+    setter.drawing.push(pos);
+    let cells = setter.drawing.clone();
+    match setter.drawn {
+        Some(index) => rules.lines[index].cells = cells,
+        None => {
+            rules.lines.push(Line { kind, cells });
+            setter.drawn = Some(rules.lines.len() - 1);
+        }
+    }
+}
+
+/// Clues between two neighbors, a second clue on the same border replaces the first
+fn place_between(setter: &mut Setter, rules: &mut VariantRules, pos: CellPosition) {
+    let anchor = match setter.anchor.take() {
+        Some(anchor) if orthogonal(anchor, pos) => anchor,
+        _ => {
+            setter.anchor = Some(pos);
+            return;
+        }
+    };
+    let joins =
+        |a: CellPosition, b: CellPosition| (a == anchor && b == pos) || (a == pos && b == anchor);
+    match setter.tool {
+        SetterTool::Border(kind) => {
+            let clue = BorderClue::new(kind, anchor, pos);
+            let existed = rules.borders.contains(&clue);
+            rules
+                .borders
+                .retain(|other| !joins(other.cells[0], other.cells[1]));
+            if !existed {
+                rules.borders.push(clue);
+            }
+        }
+        SetterTool::Greater => {
+            let sign = Inequality {
+                greater: anchor,
+                lesser: pos,
+            };
+            let existed = rules.signs.contains(&sign);
+            rules
+                .signs
+                .retain(|other| !joins(other.greater, other.lesser));
+            if !existed {
+                rules.signs.push(sign);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn toggle_parity(rules: &mut VariantRules, pos: CellPosition, even: bool) {
+    let clue = ParityClue { cell: pos, even };
+    let existed = rules.parity.contains(&clue);
+    rules.parity.retain(|other| other.cell != pos);
+    if !existed {
+        rules.parity.push(clue);
+    }
+}
+
+fn erase_clues(rules: &mut VariantRules, pos: CellPosition) {
+    rules.lines.retain(|line| !line.cells.contains(&pos));
+    rules.borders.retain(|clue| !clue.cells.contains(&pos));
+    rules
+        .signs
+        .retain(|sign| sign.greater != pos && sign.lesser != pos);
+    rules.parity.retain(|clue| clue.cell != pos);
+}
+
+fn place_clues(
+    mut select_events: EventReader<BoardSelect>,
+    cell_query: Query<&CellPosition, With<Cell>>,
+    mut setter: ResMut<Setter>,
+    mut rules: ResMut<VariantRules>,
+) {
+    for BoardSelect(entity) in select_events.iter() {
+        let pos = match cell_query.get(*entity) {
+            Ok(pos) => *pos,
+            Err(_) => continue,
+        };
+        match setter.tool {
+            SetterTool::Givens => {}
+            SetterTool::Line(kind) => extend_line(&mut setter, &mut rules, kind, pos),
+            SetterTool::Border(_) | SetterTool::Greater => {
+                place_between(&mut setter, &mut rules, pos)
+            }
+            SetterTool::Even => toggle_parity(&mut rules, pos, true),
+            SetterTool::Odd => toggle_parity(&mut rules, pos, false),
+            SetterTool::Erase => {
+                erase_clues(&mut rules, pos);
+                setter.stop_placing();
+            }
+        }
+    }
+}
+
+fn setter_button_system(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &SetterButton), (Changed<Interaction>, With<Button>)>,
+    mut cell_query: Query<(&mut Cell, &CellPosition)>,
+    mut setter: ResMut<Setter>,
+    mut rules: ResMut<VariantRules>,
+    mut training: ResMut<Training>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            SetterButton::Tool => {
+                setter.tool = setter.tool.next();
+                setter.stop_placing();
+            }
+            SetterButton::Diagonal => rules.global.diagonal = !rules.global.diagonal,
+            SetterButton::AntiKnight => rules.global.anti_knight = !rules.global.anti_knight,
+            SetterButton::AntiKing => rules.global.anti_king = !rules.global.anti_king,
+            SetterButton::NonConsecutive => {
+                rules.global.non_consecutive = !rules.global.non_consecutive
+            }
+            SetterButton::Regions => {
+                rules.regions = RegionSetting::from_regions(rules.regions).next().regions();
+            }
+            SetterButton::ClearClues => {
+                *rules = VariantRules::default();
+                setter.stop_placing();
+            }
+            SetterButton::ClearGivens => {
+                for (mut cell, _) in cell_query.iter_mut() {
+                    cell.reset();
+                }
+            }
+            SetterButton::Save => {
+                let draft = SetterDraft {
+                    givens: setter_givens(cell_query.iter()),
+                    rules: rules.clone(),
+                };
+                storage::save(DRAFT_FILE, &draft);
+                setter.message = format!("Saved to {}", storage::data_path(DRAFT_FILE).display());
+            }
+            SetterButton::Export => {
+                let givens = setter_givens(cell_query.iter());
+                let (text, left_out) = fpuzzles::export(&givens, &rules);
+                setter.message = match storage::save_text(EXPORT_FILE, &text) {
+                    Some(path) => format!("Exported to {}", path.display()),
+                    None => "Couldn't export the puzzle".to_string(),
+                };
+                if !left_out.is_empty() {
+                    setter.message += &format!("\nLeft out: {}", left_out.join(", "));
+                }
+            }
+            SetterButton::Play => {
+                let solution = match &setter.count {
+                    SolutionCount::Unique(solution, _) => Some(solution.clone()),
+                    _ => None,
+                };
+                let solution = match solution {
+                    Some(solution) => solution,
+                    None => {
+                        setter.message =
+                            "Only a puzzle with one solution can be played".to_string();
+                        continue;
+                    }
+                };
+                let mut sudoku = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+                fill_grid(sudoku.grid_mut(), &setter_givens(cell_query.iter()));
+                // the rules are already in place, loading starts the puzzle like an imported one
+                commands.insert_resource(SudokuContainer(sudoku));
+                commands.insert_resource(SudokuSolution(Some(solution)));
+                commands.insert_resource(ImportedPuzzle);
+                training.target = None;
+                app_state.set(AppState::Loading).unwrap();
+            }
+            SetterButton::Back => app_state.set(AppState::Menu).unwrap(),
+        }
+    }
+}
+
+fn update_setter_labels(
+    setter: Res<Setter>,
+    rules: Res<VariantRules>,
+    button_query: Query<(&SetterButton, &Children)>,
+    added_query: Query<(), Added<SetterButton>>,
+    mut text_query: Query<&mut Text, Without<SetterStatus>>,
+) {
+    if !setter.is_changed() && !rules.is_changed() && added_query.is_empty() {
+        return;
+    }
+    for (button, children) in button_query.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value = button.label(&setter, &rules);
+            }
+        }
+    }
+}
+
+/// Wait for the edits to settle, then count the solutions of what's on the board
+fn start_solution_count(
+    mut commands: Commands,
+    time: Res<Time>,
+    rules: Res<VariantRules>,
+    changed_query: Query<(), Changed<Cell>>,
+    cell_query: Query<(&Cell, &CellPosition)>,
+    mut setter: ResMut<Setter>,
+) {
+    if rules.is_changed() || !changed_query.is_empty() {
+        setter.settle = Some(Timer::from_seconds(CHECK_DELAY, TimerMode::Once));
+        setter.count = SolutionCount::Checking;
+        setter.message.clear();
+    }
+    // only touch the timer while it runs, so the status isn't rebuilt every frame
+    if setter.settle.is_none() || cell_query.is_empty() {
+        return;
+    }
+    let settled = match setter.settle.as_mut() {
+        Some(timer) => timer.tick(time.delta()).finished(),
+        None => false,
+    };
+    if !settled {
+        return;
+    }
+    setter.settle = None;
+    let givens = setter_givens(cell_query.iter());
+    let rules = rules.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move { count_solutions(&givens, &rules) });
+    commands.insert_resource(SolutionCountTask(task));
+}
+
+fn handle_solution_count_task(
+    mut commands: Commands,
+    task: Option<ResMut<SolutionCountTask>>,
+    mut setter: ResMut<Setter>,
+) {
+    let mut task = match task {
+        Some(task) => task,
+        None => return,
+    };
+    let count = match future::block_on(future::poll_once(&mut task.0)) {
+        Some(count) => count,
+        None => return,
+    };
+    commands.remove_resource::<SolutionCountTask>();
+    // a count started before the latest edit is already out of date
+    if setter.settle.is_none() {
+        setter.count = count;
+    }
+}
+
+fn update_setter_status(
+    setter: Res<Setter>,
+    added_query: Query<(), Added<SetterStatus>>,
+    mut query: Query<&mut Text, With<SetterStatus>>,
+) {
+    if !setter.is_changed() && added_query.is_empty() {
+        return;
+    }
+    let mut status = match &setter.count {
+        SolutionCount::Checking => "Counting solutions...".to_string(),
+        SolutionCount::Broken => "The givens break a rule".to_string(),
+        SolutionCount::NoSolution => "No solution".to_string(),
+        SolutionCount::Unique(_, difficulty) => {
+            format!("Unique solution\nDifficulty: {difficulty}")
+        }
+        SolutionCount::Multiple => "More than one solution".to_string(),
+        SolutionCount::Unknown => "Too many possibilities to count".to_string(),
+    };
+    match setter.tool {
+        SetterTool::Line(_) if !setter.drawing.is_empty() => {
+            status += "\nSelect the line's end again to finish it";
+        }
+        SetterTool::Border(_) | SetterTool::Greater if setter.anchor.is_some() => {
+            status += "\nSelect a neighbor to place the clue";
+        }
+        _ => {}
+    }
+    if !setter.message.is_empty() {
+        status += &format!("\n{}", setter.message);
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = status.clone();
+    }
+}
+
+fn exit_setter(mut commands: Commands, mut setter: ResMut<Setter>) {
+    commands.remove_resource::<SolutionCountTask>();
+    commands.remove_resource::<PendingDraft>();
+    setter.stop_placing();
+    setter.settle = None;
+}
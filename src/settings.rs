@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    assist::AssistSettings,
+    board::LockedDigit,
+    storage,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::{BorderSetting, CompareSetting, GlobalRules, LineSetting, OutsideSetting, RegionSetting},
+};
+
+const PREFERENCES_FILE: &str = "preferences.json";
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SettingsToggle>()
+            .insert_resource(Preferences::load())
+            .add_system(save_preferences)
+            .add_system(settings_toggle_events)
+            .add_system(setting_button_system)
+            .add_system(update_setting_labels);
+    }
+}
+
+/// Event to open or close the settings panel
+pub struct SettingsToggle;
+
+/// How the cell menu and board interact
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputMode {
+    /// Select a cell, then pick a digit
+    CellFirst,
+    /// Lock a digit in the cell menu, then tap cells to place it
+    DigitFirst,
+}
+
+impl Default for InputMode {
+    fn default() -> Self {
+        Self::CellFirst
+    }
+}
+
+/// How entries are checked against the solution
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MistakeMode {
+    /// Mistakes are counted but not shown
+    Off,
+    /// Wrong entries are flagged as soon as they are placed
+    Immediate,
+    /// Wrong entries are flagged, and the game is lost after [`MAX_MISTAKES`]
+    ThreeStrikes,
+}
+
+impl Default for MistakeMode {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+/// Mistakes allowed in [`MistakeMode::ThreeStrikes`]
+pub const MAX_MISTAKES: usize = 3;
+
+/// Player preferences, persisted between runs
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub input_mode: InputMode,
+    pub mistake_mode: MistakeMode,
+    pub assists: AssistSettings,
+    /// Variant lines for new games
+    pub lines: LineSetting,
+    /// Kropki dots or XV markers for new games
+    pub borders: BorderSetting,
+    /// Clues around the grid for new games
+    pub outside: OutsideSetting,
+    /// Windoku windows or disjoint groups for new games
+    pub regions: RegionSetting,
+    /// Even/odd shapes or greater-than signs for new games
+    pub compare: CompareSetting,
+    /// Diagonal, anti-knight, anti-king and non-consecutive rules for new games
+    pub global: GlobalRules,
+}
+
+impl Preferences {
+    pub fn load() -> Self {
+        storage::load(PREFERENCES_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(PREFERENCES_FILE, self);
+    }
+}
+
+fn save_preferences(preferences: Res<Preferences>) {
+    if preferences.is_changed() && !preferences.is_added() {
+        preferences.save();
+    }
+}
+
+#[derive(Component)]
+struct SettingsPanel;
+
+#[derive(EnumIter, Debug, Copy, Clone, Component)]
+enum SettingButton {
+    InputMode,
+    MistakeMode,
+    AutoCandidates,
+    AutoCleanup,
+    AutoFillSingles,
+    LegalDigitsOnly,
+    Close,
+}
+
+impl Display for SettingButton {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingButton::InputMode => write!(f, "Input"),
+            SettingButton::MistakeMode => write!(f, "Mistakes"),
+            SettingButton::AutoCandidates => write!(f, "Auto Notes"),
+            SettingButton::AutoCleanup => write!(f, "Auto Cleanup"),
+            SettingButton::AutoFillSingles => write!(f, "Fill Singles"),
+            SettingButton::LegalDigitsOnly => write!(f, "Legal Digits"),
+            SettingButton::Close => write!(f, "Close"),
+        }
+    }
+}
+
+impl SettingButton {
+    fn label(&self, preferences: &Preferences) -> String {
+        let on_off = |enabled: bool| if enabled { "On" } else { "Off" };
+        let value = match self {
+            SettingButton::InputMode => match preferences.input_mode {
+                InputMode::CellFirst => "Cell First",
+                InputMode::DigitFirst => "Digit First",
+            },
+            SettingButton::MistakeMode => match preferences.mistake_mode {
+                MistakeMode::Off => "Off",
+                MistakeMode::Immediate => "Immediate",
+                MistakeMode::ThreeStrikes => "3 Strikes",
+            },
+            SettingButton::AutoCandidates => on_off(preferences.assists.auto_candidates),
+            SettingButton::AutoCleanup => on_off(preferences.assists.auto_cleanup),
+            SettingButton::AutoFillSingles => on_off(preferences.assists.auto_fill_singles),
+            SettingButton::LegalDigitsOnly => on_off(preferences.assists.legal_digits_only),
+            SettingButton::Close => return format!("{self}"),
+        };
+        format!("{self}: {value}")
+    }
+
+    fn apply(&self, preferences: &mut Preferences) {
+        match self {
+            SettingButton::InputMode => {
+                preferences.input_mode = match preferences.input_mode {
+                    InputMode::CellFirst => InputMode::DigitFirst,
+                    InputMode::DigitFirst => InputMode::CellFirst,
+                };
+            }
+            SettingButton::MistakeMode => {
+                preferences.mistake_mode = match preferences.mistake_mode {
+                    MistakeMode::Off => MistakeMode::Immediate,
+                    MistakeMode::Immediate => MistakeMode::ThreeStrikes,
+                    MistakeMode::ThreeStrikes => MistakeMode::Off,
+                };
+            }
+            SettingButton::AutoCandidates => {
+                preferences.assists.auto_candidates = !preferences.assists.auto_candidates;
+            }
+            SettingButton::AutoCleanup => {
+                preferences.assists.auto_cleanup = !preferences.assists.auto_cleanup;
+            }
+            SettingButton::AutoFillSingles => {
+                preferences.assists.auto_fill_singles = !preferences.assists.auto_fill_singles;
+            }
+            SettingButton::LegalDigitsOnly => {
+                preferences.assists.legal_digits_only = !preferences.assists.legal_digits_only;
+            }
+            SettingButton::Close => {}
+        }
+    }
+}
+
+fn settings_toggle_events(
+    mut commands: Commands,
+    mut settings_toggle: EventReader<SettingsToggle>,
+    panel_query: Query<Entity, With<SettingsPanel>>,
+    preferences: Res<Preferences>,
+    font_assets: Res<FontAssets>,
+    theme: Res<Theme>,
+) {
+    for _ in settings_toggle.iter() {
+        if let Ok(panel) = panel_query.get_single() {
+            commands.entity(panel).despawn_recursive();
+            continue;
+        }
+
+        commands
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            })
+            .insert(SettingsPanel)
+            .insert(Name::new("Settings"))
+            .with_children(|parent| {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::all(Val::Px(2.0)),
+                            ..default()
+                        },
+                        background_color: theme.line_thick.into(),
+                        ..default()
+                    })
+                    .insert(ThickLine)
+                    .with_children(|parent| {
+                        for button in SettingButton::iter() {
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                                            margin: UiRect::all(Val::Px(2.0)),
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        background_color: theme.btn_normal.into(),
+                                        ..default()
+                                    },
+                                    Name::new(format!("{button}")),
+                                    button,
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle {
+                                        text: font_assets.status(button.label(&preferences), &theme),
+                                        ..default()
+                                    });
+                                });
+                        }
+                    });
+            });
+    }
+}
+
+fn setting_button_system(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &SettingButton), (Changed<Interaction>, With<Button>)>,
+    panel_query: Query<Entity, With<SettingsPanel>>,
+    mut preferences: ResMut<Preferences>,
+    mut locked_digit: ResMut<LockedDigit>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            SettingButton::Close => {
+                for panel in panel_query.iter() {
+                    commands.entity(panel).despawn_recursive();
+                }
+            }
+            SettingButton::InputMode => {
+                button.apply(&mut preferences);
+                locked_digit.0 = None;
+            }
+            _ => button.apply(&mut preferences),
+        }
+    }
+}
+
+fn update_setting_labels(
+    preferences: Res<Preferences>,
+    button_query: Query<(&SettingButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    if !preferences.is_changed() {
+        return;
+    }
+    for (button, children) in button_query.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value = button.label(&preferences);
+            }
+        }
+    }
+}
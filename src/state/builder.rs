@@ -0,0 +1,295 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{
+    assist::Grid,
+    cleanup,
+    settings::Preferences,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::{find_solution, Feasibility, VariantRules, SEARCH_BUDGET},
+    AppState,
+};
+
+pub struct StateBuilderPlugin;
+
+impl Plugin for StateBuilderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BuilderRequest>()
+            .add_system_set(SystemSet::on_enter(AppState::Builder).with_system(setup_builder))
+            .add_system_set(
+                SystemSet::on_update(AppState::Builder)
+                    .with_system(builder_button_system)
+                    .with_system(update_builder_labels)
+                    .with_system(handle_feasibility_task),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Builder)
+                    .with_system(cancel_feasibility_task)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+/// Resource set when the builder starts a game, so loading generates one instead of resuming
+#[derive(Resource, Default)]
+pub struct BuilderRequest {
+    pub fresh: bool,
+    /// The grid the feasibility check found, the puzzle is built on it
+    pub grid: Option<Grid>,
+    /// Why generating under the chosen rules failed, shown when the builder opens again
+    pub problem: Option<String>,
+}
+
+/// Check the chosen rules have a solution before generating under them
+#[derive(Resource)]
+struct FeasibilityTask(Task<Feasibility>);
+
+#[derive(Component)]
+struct BuilderStatus;
+
+#[derive(EnumIter, Debug, Copy, Clone, Component)]
+enum BuilderButton {
+    Diagonal,
+    AntiKnight,
+    AntiKing,
+    NonConsecutive,
+    Regions,
+    Compare,
+    Lines,
+    Borders,
+    Outside,
+    Generate,
+    Back,
+}
+
+impl Display for BuilderButton {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuilderButton::Diagonal => write!(f, "Diagonal"),
+            BuilderButton::AntiKnight => write!(f, "Anti-Knight"),
+            BuilderButton::AntiKing => write!(f, "Anti-King"),
+            BuilderButton::NonConsecutive => write!(f, "Non-Consecutive"),
+            BuilderButton::Regions => write!(f, "Regions"),
+            BuilderButton::Compare => write!(f, "Shapes"),
+            BuilderButton::Lines => write!(f, "Lines"),
+            BuilderButton::Borders => write!(f, "Borders"),
+            BuilderButton::Outside => write!(f, "Outside"),
+            BuilderButton::Generate => write!(f, "Generate"),
+            BuilderButton::Back => write!(f, "Back"),
+        }
+    }
+}
+
+impl BuilderButton {
+    fn label(&self, preferences: &Preferences) -> String {
+        let on_off = |enabled: bool| if enabled { "On" } else { "Off" };
+        let global = preferences.global;
+        match self {
+            BuilderButton::Diagonal => format!("{self}: {}", on_off(global.diagonal)),
+            BuilderButton::AntiKnight => format!("{self}: {}", on_off(global.anti_knight)),
+            BuilderButton::AntiKing => format!("{self}: {}", on_off(global.anti_king)),
+            BuilderButton::NonConsecutive => format!("{self}: {}", on_off(global.non_consecutive)),
+            BuilderButton::Regions => format!("{self}: {}", preferences.regions),
+            BuilderButton::Compare => format!("{self}: {}", preferences.compare),
+            BuilderButton::Lines => format!("{self}: {}", preferences.lines),
+            BuilderButton::Borders => format!("{self}: {}", preferences.borders),
+            BuilderButton::Outside => format!("{self}: {}", preferences.outside),
+            BuilderButton::Generate | BuilderButton::Back => format!("{self}"),
+        }
+    }
+
+    fn apply(&self, preferences: &mut Preferences) {
+        let global = &mut preferences.global;
+        match self {
+            BuilderButton::Diagonal => global.diagonal = !global.diagonal,
+            BuilderButton::AntiKnight => global.anti_knight = !global.anti_knight,
+            BuilderButton::AntiKing => global.anti_king = !global.anti_king,
+            BuilderButton::NonConsecutive => global.non_consecutive = !global.non_consecutive,
+            BuilderButton::Regions => preferences.regions = preferences.regions.next(),
+            BuilderButton::Compare => preferences.compare = preferences.compare.next(),
+            BuilderButton::Lines => preferences.lines = preferences.lines.next(),
+            BuilderButton::Borders => preferences.borders = preferences.borders.next(),
+            BuilderButton::Outside => preferences.outside = preferences.outside.next(),
+            BuilderButton::Generate | BuilderButton::Back => {}
+        }
+    }
+}
+
+fn setup_builder(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    theme: Res<Theme>,
+    preferences: Res<Preferences>,
+    mut request: ResMut<BuilderRequest>,
+) {
+    let problem = request.problem.take().unwrap_or_default();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Builder"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: theme.line_thick.into(),
+                    ..default()
+                })
+                .insert(ThickLine)
+                .with_children(|parent| {
+                    for button in BuilderButton::iter() {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                                        margin: UiRect::all(Val::Px(2.0)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: theme.btn_normal.into(),
+                                    ..default()
+                                },
+                                Name::new(format!("{button}")),
+                                button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle {
+                                    text: font_assets.status(button.label(&preferences), &theme),
+                                    ..default()
+                                });
+                            });
+                    }
+                });
+
+            parent.spawn((
+                TextBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    text: font_assets.status(problem, &theme),
+                    ..default()
+                },
+                BuilderStatus,
+            ));
+        });
+}
+
+fn builder_button_system(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &BuilderButton), (Changed<Interaction>, With<Button>)>,
+    task: Option<Res<FeasibilityTask>>,
+    mut preferences: ResMut<Preferences>,
+    mut request: ResMut<BuilderRequest>,
+    mut app_state: ResMut<State<AppState>>,
+    mut status_query: Query<&mut Text, With<BuilderStatus>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        // the rules stay put while they're being checked
+        if task.is_some() && !matches!(button, BuilderButton::Back) {
+            continue;
+        }
+        match button {
+            BuilderButton::Generate => {
+                // clues are read off the solution, only rules with no clues can rule every grid out
+                let base = VariantRules {
+                    regions: preferences.regions.regions(),
+                    global: preferences.global,
+                    ..default()
+                };
+                if base.is_classic() {
+                    request.fresh = true;
+                    app_state.set(AppState::Loading).unwrap();
+                    continue;
+                }
+                let task = AsyncComputeTaskPool::get().spawn(async move {
+                    find_solution(&base, &mut rand::thread_rng(), SEARCH_BUDGET)
+                });
+                commands.insert_resource(FeasibilityTask(task));
+                set_status(&mut status_query, "Checking the rules...");
+            }
+            BuilderButton::Back => app_state.set(AppState::Menu).unwrap(),
+            _ => {
+                button.apply(&mut preferences);
+                set_status(&mut status_query, "");
+            }
+        }
+    }
+}
+
+fn handle_feasibility_task(
+    mut commands: Commands,
+    task: Option<ResMut<FeasibilityTask>>,
+    mut request: ResMut<BuilderRequest>,
+    mut app_state: ResMut<State<AppState>>,
+    mut status_query: Query<&mut Text, With<BuilderStatus>>,
+) {
+    let mut task = match task {
+        Some(task) => task,
+        None => return,
+    };
+    let feasibility = match future::block_on(future::poll_once(&mut task.0)) {
+        Some(feasibility) => feasibility,
+        None => return,
+    };
+    commands.remove_resource::<FeasibilityTask>();
+    match feasibility {
+        Feasibility::Solvable(grid) => {
+            request.fresh = true;
+            request.grid = Some(grid);
+            app_state.set(AppState::Loading).unwrap();
+        }
+        _ => set_status(&mut status_query, feasibility.problem().unwrap_or_default()),
+    }
+}
+
+fn cancel_feasibility_task(mut commands: Commands) {
+    commands.remove_resource::<FeasibilityTask>();
+}
+
+fn set_status(status_query: &mut Query<&mut Text, With<BuilderStatus>>, status: &str) {
+    for mut text in status_query.iter_mut() {
+        text.sections[0].value = status.to_string();
+    }
+}
+
+fn update_builder_labels(
+    preferences: Res<Preferences>,
+    button_query: Query<(&BuilderButton, &Children)>,
+    mut text_query: Query<&mut Text, Without<BuilderStatus>>,
+) {
+    if !preferences.is_changed() {
+        return;
+    }
+    for (button, children) in button_query.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value = button.label(&preferences);
+            }
+        }
+    }
+}
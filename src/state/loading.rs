@@ -3,10 +3,15 @@ use bevy::{ecs::schedule::ShouldRun, prelude::*, time::FixedTimestep};
 use crate::{
     cleanup,
     events::*,
+    fpuzzles::ImportedPuzzle,
+    save::SavedGame,
+    trainer::Training,
     ui::{FontAssets, Theme},
     AppState,
 };
 
+use super::builder::BuilderRequest;
+
 pub struct StateLoadingPlugin;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
@@ -76,7 +81,32 @@ fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>, theme: Res<The
     ));
 }
 
-fn setup_new_game(mut new_game_event: EventWriter<NewGame>) {
+fn setup_new_game(
+    mut commands: Commands,
+    mut new_game_event: EventWriter<NewGame>,
+    mut new_game_ready_event: EventWriter<NewGameReady>,
+    training: Res<Training>,
+    mut request: ResMut<BuilderRequest>,
+    imported: Option<Res<ImportedPuzzle>>,
+    mut started: Local<bool>,
+) {
+    // an imported puzzle is already in place, it only has to be started
+    if imported.is_some() {
+        commands.remove_resource::<ImportedPuzzle>();
+        *started = true;
+        new_game_ready_event.send(NewGameReady);
+        return;
+    }
+    // pick up the game left unfinished last time, training and the builder ask for a fresh puzzle
+    let fresh = training.target.is_some() || std::mem::take(&mut request.fresh);
+    if !*started && !fresh {
+        *started = true;
+        if let Some(saved) = SavedGame::load() {
+            saved.resume(&mut commands);
+            new_game_ready_event.send(NewGameReady);
+            return;
+        }
+    }
     new_game_event.send(NewGame);
 }
 
@@ -1,15 +1,22 @@
+mod builder;
 mod loading;
 mod playing;
+mod title;
 
 use bevy::prelude::*;
+pub use builder::BuilderRequest;
+use builder::StateBuilderPlugin;
 use loading::StateLoadingPlugin;
 use playing::StatePlayingPlugin;
+use title::StateTitlePlugin;
 
 pub struct StatePlugin;
 
 impl Plugin for StatePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(StateLoadingPlugin)
-        .add_plugin(StatePlayingPlugin);
+        .add_plugin(StatePlayingPlugin)
+        .add_plugin(StateTitlePlugin)
+        .add_plugin(StateBuilderPlugin);
     }
 }
\ No newline at end of file
@@ -2,6 +2,9 @@ use bevy::prelude::*;
 
 use crate::{
     board::*,
+    checkpoint::create_checkpoint_list,
+    heatmap::create_heatmap_panel,
+    game::create_status,
     ui::{FontAssets, Theme},
     AppState, 
     menu::create_menu,
@@ -49,7 +52,10 @@ fn setup_layout(
         .with_children(|parent| {
             create_board(parent, &theme, &font_assets, grid, width, height);
             create_cell_menu(parent, &theme, &font_assets, width, height);
+            create_status(parent, &theme, &font_assets);
             create_menu(parent, &theme, &font_assets);
+            create_checkpoint_list(parent);
+            create_heatmap_panel(parent, &theme, &font_assets);
         });
 
         
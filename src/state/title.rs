@@ -0,0 +1,441 @@
+use bevy::{app::AppExit, prelude::*};
+use strum::IntoEnumIterator;
+
+use crate::{
+    assist::Grid,
+    board::{CellPosition, Value},
+    cleanup,
+    fpuzzles::ImportPuzzle,
+    logic::Technique,
+    trainer::{Training, TrainingStats},
+    ui::{FontAssets, Theme, ThickLine},
+    variant::VariantRules,
+    watch::{flash, solve_frames, Playback, SolveFrame},
+    AppState,
+};
+
+/// Puzzles the title screen solves while waiting, 0 for an empty cell
+const DEMO_PUZZLES: [&str; 3] = [
+    "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+    "000000907000420180000705026100904000050000040000507009920108000034059000507000000",
+    "200080300060070084030500209000105408000000000402706000301007040720040060004010003",
+];
+/// Pause on the solved grid before moving to the next puzzle
+const DEMO_HOLD: f32 = 3.0;
+const DEMO_CELL_SIZE: f32 = 40.0;
+
+pub struct StateTitlePlugin;
+
+impl Plugin for StateTitlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Menu).with_system(setup_title))
+            .add_system_set(
+                SystemSet::on_update(AppState::Menu)
+                    .with_system(title_button_system)
+                    .with_system(play_demo),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Menu).with_system(cleanup));
+    }
+}
+
+/// Digit shown on the demo grid, the solver fills it in
+#[derive(Component)]
+struct DemoCell(CellPosition);
+
+#[derive(Component, Debug, Copy, Clone)]
+enum TitleButton {
+    Play,
+    /// Pick the variant rules for a new game
+    Builder,
+    /// Place givens and clues for a puzzle of your own
+    Setter,
+    /// Puzzle packs that ship with the game
+    Library,
+    Tutorial,
+    /// Open or close the list of techniques to train
+    Trainer,
+    Train(Technique),
+    Samurai,
+    /// Start a game from an f-puzzles file
+    Import,
+    Quit,
+}
+
+/// Techniques to practice, with the accuracy so far
+#[derive(Component)]
+struct TrainerList;
+
+/// The demo puzzle being solved, and how long the solved grid has been shown
+struct DemoSolve {
+    puzzle: usize,
+    playback: Playback,
+    hold: Timer,
+}
+
+fn parse_puzzle(puzzle: &str) -> Grid {
+    let digits: Vec<usize> = puzzle.bytes().map(|b| (b - b'0') as usize).collect();
+    std::array::from_fn(|y| {
+        std::array::from_fn(|x| match digits[y * 9 + x] {
+            0 => None,
+            digit => Some(Value::from(digit)),
+        })
+    })
+}
+
+fn setup_title(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    theme: Res<Theme>,
+    stats: Res<TrainingStats>,
+    mut training: ResMut<Training>,
+) {
+    // a technique with no puzzle goes back to the list so another can be picked
+    if let Some(problem) = training.problem.take() {
+        spawn_trainer_list(&mut commands, &theme, &font_assets, &stats, Some(problem));
+    }
+    let grid = parse_puzzle(DEMO_PUZZLES[0]);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Title"))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                style: Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                text: Text::from_section("Sudoku", font_assets.loading_text("", &theme).style),
+                ..default()
+            });
+            create_demo_grid(parent, &theme, &font_assets, &grid);
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        margin: UiRect::all(Val::Px(20.0)),
+                        padding: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: theme.line_thick.into(),
+                    ..default()
+                })
+                .insert(ThickLine)
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (TitleButton::Play, "Play"),
+                        (TitleButton::Builder, "Variant Builder"),
+                        (TitleButton::Setter, "Puzzle Setter"),
+                        (TitleButton::Library, "Library"),
+                        (TitleButton::Tutorial, "Tutorial"),
+                        (TitleButton::Trainer, "Trainer"),
+                        (TitleButton::Samurai, "Samurai"),
+                        (TitleButton::Import, "Import"),
+                        (TitleButton::Quit, "Quit"),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                                        margin: UiRect::all(Val::Px(2.0)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: theme.btn_normal.into(),
+                                    ..default()
+                                },
+                                Name::new(label),
+                                button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle {
+                                    text: font_assets.status(label, &theme),
+                                    ..default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+/// A small board of plain text cells, separate from the game's so nothing gets saved or scored
+fn create_demo_grid(parent: &mut ChildBuilder, theme: &Theme, font_assets: &FontAssets, grid: &Grid) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(2.0)),
+                ..default()
+            },
+            background_color: theme.line_thick.into(),
+            ..default()
+        })
+        .insert(ThickLine)
+        .insert(Name::new("Demo Grid"))
+        .with_children(|parent| {
+            for y in 0..9 {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for x in 0..9 {
+                            // thicker gaps between boxes
+                            let right = if x % 3 == 2 && x < 8 { 3.0 } else { 1.0 };
+                            let bottom = if y % 3 == 2 && y < 8 { 3.0 } else { 1.0 };
+                            parent
+                                .spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            size: Size::new(
+                                                Val::Px(DEMO_CELL_SIZE),
+                                                Val::Px(DEMO_CELL_SIZE),
+                                            ),
+                                            margin: UiRect {
+                                                right: Val::Px(right),
+                                                bottom: Val::Px(bottom),
+                                                ..default()
+                                            },
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        background_color: theme.btn_normal.into(),
+                                        ..default()
+                                    },
+                                    DemoCell(CellPosition::new(x, y)),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle {
+                                        text: demo_text(grid[y][x], theme.value_given.color, font_assets),
+                                        ..default()
+                                    });
+                                });
+                        }
+                    });
+            }
+        });
+}
+
+fn demo_text(value: Option<Value>, color: Color, font_assets: &FontAssets) -> Text {
+    Text::from_section(
+        value.map(|value| value.to_string()).unwrap_or_default(),
+        TextStyle {
+            font: font_assets.ui_font.clone(),
+            font_size: 30.0,
+            color,
+        },
+    )
+}
+
+fn title_button_system(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &TitleButton), (Changed<Interaction>, With<Button>)>,
+    list_query: Query<Entity, With<TrainerList>>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    stats: Res<TrainingStats>,
+    mut training: ResMut<Training>,
+    mut app_state: ResMut<State<AppState>>,
+    mut import_event: EventWriter<ImportPuzzle>,
+    mut exit_event: EventWriter<AppExit>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match *button {
+            TitleButton::Play => {
+                training.target = None;
+                app_state.set(AppState::Loading).unwrap();
+            }
+            TitleButton::Builder => {
+                training.target = None;
+                app_state.set(AppState::Builder).unwrap();
+            }
+            TitleButton::Setter => app_state.set(AppState::Setter).unwrap(),
+            TitleButton::Library => app_state.set(AppState::Library).unwrap(),
+            TitleButton::Tutorial => app_state.set(AppState::Tutorial).unwrap(),
+            TitleButton::Trainer => {
+                if list_query.is_empty() {
+                    spawn_trainer_list(&mut commands, &theme, &font_assets, &stats, None);
+                }
+                for list in list_query.iter() {
+                    commands.entity(list).despawn_recursive();
+                }
+            }
+            TitleButton::Train(technique) => {
+                training.target = Some(technique);
+                app_state.set(AppState::Loading).unwrap();
+            }
+            TitleButton::Samurai => app_state.set(AppState::Samurai).unwrap(),
+            TitleButton::Import => import_event.send(ImportPuzzle),
+            TitleButton::Quit => exit_event.send(AppExit),
+        }
+    }
+}
+
+fn spawn_trainer_list(
+    commands: &mut Commands,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    stats: &TrainingStats,
+    message: Option<String>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(TrainerList)
+        .insert(Name::new("Trainer"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: theme.line_thick.into(),
+                    ..default()
+                })
+                .insert(ThickLine)
+                .with_children(|parent| {
+                    if let Some(message) = message {
+                        parent.spawn(TextBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Px(10.0)),
+                                max_size: Size::new(Val::Px(300.0), Val::Undefined),
+                                ..default()
+                            },
+                            text: font_assets.status(message, theme),
+                            ..default()
+                        });
+                    }
+                    let choices = Technique::iter().map(|technique| {
+                        let accuracy = stats.get(technique);
+                        let label = format!("{technique} {}/{}", accuracy.correct, accuracy.attempts);
+                        (TitleButton::Train(technique), label)
+                    });
+                    for (button, label) in choices.chain([(TitleButton::Trainer, "Back".to_string())]) {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                                        margin: UiRect::all(Val::Px(2.0)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: theme.btn_normal.into(),
+                                    ..default()
+                                },
+                                Name::new(label.clone()),
+                                button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle {
+                                    text: font_assets.status(label, theme),
+                                    ..default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+/// Attract mode, solving demo puzzles one after another while the title is up
+fn play_demo(
+    mut commands: Commands,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    mut demo: Local<Option<DemoSolve>>,
+    added_query: Query<(), Added<DemoCell>>,
+    cell_query: Query<(Entity, &DemoCell, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    // coming back to the title starts over from the first puzzle
+    if !added_query.is_empty() {
+        *demo = None;
+    }
+    let demo = demo.get_or_insert_with(|| DemoSolve {
+        puzzle: 0,
+        playback: demo_playback(0),
+        hold: Timer::from_seconds(DEMO_HOLD, TimerMode::Once),
+    });
+
+    if demo.playback.is_done() {
+        demo.hold.tick(time.delta());
+        if !demo.hold.finished() {
+            return;
+        }
+        demo.hold.reset();
+        demo.puzzle = (demo.puzzle + 1) % DEMO_PUZZLES.len();
+        demo.playback = demo_playback(demo.puzzle);
+        let grid = parse_puzzle(DEMO_PUZZLES[demo.puzzle]);
+        for (_, cell, children) in cell_query.iter() {
+            if let Ok(mut text) = text_query.get_mut(children[0]) {
+                *text = demo_text(grid[cell.0.y][cell.0.x], theme.value_given.color, &font_assets);
+            }
+        }
+        return;
+    }
+
+    for frame in demo.playback.due(time.delta()) {
+        let (at, value, color) = match frame {
+            SolveFrame::Place(at, value) => (at, Some(value), theme.logic_pattern),
+            SolveFrame::Backtrack(at) => (at, None, theme.text_error),
+            // the demo grid has no notes, so eliminations only flash
+            SolveFrame::Eliminate(at, _) => {
+                if let Some((entity, _, _)) = cell_query.iter().find(|(_, cell, _)| cell.0 == at) {
+                    flash(&mut commands, entity, theme.logic_elimination, &theme);
+                }
+                continue;
+            }
+        };
+        for (entity, cell, children) in cell_query.iter() {
+            if cell.0 != at {
+                continue;
+            }
+            if let Ok(mut text) = text_query.get_mut(children[0]) {
+                *text = demo_text(value, theme.value_solver.color, &font_assets);
+            }
+            flash(&mut commands, entity, color, &theme);
+        }
+    }
+}
+
+fn demo_playback(puzzle: usize) -> Playback {
+    let frames = solve_frames(&parse_puzzle(DEMO_PUZZLES[puzzle]), &VariantRules::default())
+        .unwrap_or_default();
+    let mut playback = Playback::new(frames);
+    playback.change_speed(true);
+    playback
+}
@@ -0,0 +1,86 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Directory used to persist preferences and saves
+fn data_dir() -> PathBuf {
+    #[cfg(target_os = "android")]
+    {
+        PathBuf::from("/data/data/com.sly.sudoku/files")
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".sly_sudoku"))
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+}
+
+/// Load a json file from the data directory, returns None if missing or invalid
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = data_dir().join(name);
+    let data = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&data) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Failed to parse {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Save a value as json in the data directory, failures are only logged
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let dir = data_dir();
+    if let Err(e) = fs::create_dir_all(&dir) {
+        warn!("Failed to create {}: {e}", dir.display());
+        return;
+    }
+    let path = dir.join(name);
+    match serde_json::to_string_pretty(value) {
+        Ok(data) => {
+            if let Err(e) = fs::write(&path, data) {
+                warn!("Failed to write {}: {e}", path.display());
+            }
+        }
+        Err(e) => warn!("Failed to serialize {}: {e}", path.display()),
+    }
+}
+
+/// Remove a file from the data directory if it exists
+pub fn remove(name: &str) {
+    let path = data_dir().join(name);
+    if path.exists() {
+        if let Err(e) = fs::remove_file(&path) {
+            warn!("Failed to remove {}: {e}", path.display());
+        }
+    }
+}
+
+/// Write plain text to the data directory, returns where it went
+pub fn save_text(name: &str, text: &str) -> Option<PathBuf> {
+    let dir = data_dir();
+    if let Err(e) = fs::create_dir_all(&dir) {
+        warn!("Failed to create {}: {e}", dir.display());
+        return None;
+    }
+    let path = dir.join(name);
+    match fs::write(&path, text) {
+        Ok(()) => Some(path),
+        Err(e) => {
+            warn!("Failed to write {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Read plain text from the data directory, None if it's missing
+pub fn load_text(name: &str) -> Option<String> {
+    fs::read_to_string(data_dir().join(name)).ok()
+}
+
+/// Where a file in the data directory lives, for telling the player
+pub fn data_path(name: &str) -> PathBuf {
+    data_dir().join(name)
+}
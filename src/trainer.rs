@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::{HighlightKind, Highlights},
+    assist::{grid_from, grid_values, Grid},
+    board::{Cell, CellPosition},
+    events::NewGameReady,
+    history::{BoardSnapshot, History},
+    logic::{key_position, solve_path, KeyPosition, Technique},
+    storage,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::VariantRules,
+    SudokuContainer,
+};
+
+const STATS_FILE: &str = "training.json";
+/// Puzzles to try for one that needs the technique, before asking for another technique
+pub const MAX_ATTEMPTS: usize = 500;
+
+pub struct TrainerPlugin;
+
+impl Plugin for TrainerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Training>()
+            .insert_resource(TrainingStats::load())
+            .add_system(find_key_position)
+            .add_system(check_key_position.after(find_key_position))
+            .add_system(judge_key_move.after(check_key_position))
+            .add_system(trainer_button_system);
+    }
+}
+
+/// Whether a puzzle's hardest step is the technique being trained, any puzzle does without one
+pub fn needs_technique(givens: &Grid, rules: &VariantRules, target: Option<Technique>) -> bool {
+    match target {
+        Some(target) => {
+            let path = solve_path(givens, rules);
+            path.solved && path.hardest() == Some(target)
+        }
+        None => true,
+    }
+}
+
+/// Resource for the technique being practiced, None for ordinary games
+#[derive(Resource, Default)]
+pub struct Training {
+    pub target: Option<Technique>,
+    /// Why no puzzle could be made for the last target
+    pub problem: Option<String>,
+    key: Option<KeyPosition>,
+    stage: TrainingStage,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum TrainingStage {
+    /// Playing towards the key position
+    Solving,
+    /// Stopped at the key position, waiting for the player's next move
+    AtKey,
+    Answered,
+}
+
+impl Default for TrainingStage {
+    fn default() -> Self {
+        Self::Solving
+    }
+}
+
+/// How often a technique was spotted at its key position
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Accuracy {
+    pub attempts: usize,
+    pub correct: usize,
+}
+
+/// Resource for accuracy per technique, kept across games
+#[derive(Resource, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TrainingStats(pub BTreeMap<Technique, Accuracy>);
+
+impl TrainingStats {
+    pub fn load() -> Self {
+        storage::load(STATS_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(STATS_FILE, self);
+    }
+
+    pub fn get(&self, technique: Technique) -> Accuracy {
+        self.0.get(&technique).copied().unwrap_or_default()
+    }
+
+    fn record(&mut self, technique: Technique, correct: bool) {
+        let accuracy = self.0.entry(technique).or_default();
+        accuracy.attempts += 1;
+        if correct {
+            accuracy.correct += 1;
+        }
+    }
+}
+
+#[derive(Component)]
+struct TrainerPanel;
+
+#[derive(Component)]
+struct TrainerCloseButton;
+
+fn find_key_position(
+    mut new_game_ready: EventReader<NewGameReady>,
+    sudoku: Res<SudokuContainer>,
+    rules: Res<VariantRules>,
+    mut training: ResMut<Training>,
+) {
+    for _ in new_game_ready.iter() {
+        training.stage = TrainingStage::Solving;
+        training.key = training
+            .target
+            .and_then(|target| key_position(&grid_from(sudoku.0.grid()), &rules, target));
+    }
+}
+
+/// Stop the player once the easier steps leave nothing to place, whatever order they were solved in
+fn check_key_position(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    rules: Res<VariantRules>,
+    mut training: ResMut<Training>,
+    mut history: ResMut<History>,
+    changed_query: Query<(), Changed<Cell>>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    if training.stage != TrainingStage::Solving || changed_query.is_empty() {
+        return;
+    }
+    let target = match (&training.key, training.target) {
+        (Some(_), Some(target)) => target,
+        _ => return,
+    };
+    // the candidates left on this board decide, a key the player got to by another route counts too
+    let key = match key_position(&grid_values(query.iter()), &rules, target) {
+        Some(key) if key.placements.is_empty() => key,
+        _ => return,
+    };
+
+    // show the candidates the easier steps leave, so the pattern is there to find
+    let before = BoardSnapshot::new(query.iter());
+    for (mut cell, pos) in query.iter_mut() {
+        let notes = key.candidates[pos.y][pos.x];
+        if cell.value().is_none() && cell.notes() != notes {
+            cell.set_notes(notes);
+        }
+    }
+    history.record(&before, &BoardSnapshot::new(query.iter()));
+    let message = format!(
+        "Key position: find the {} and make its move",
+        key.step.technique
+    );
+    spawn_trainer_panel(&mut commands, &theme, &font_assets, message, false);
+    training.key = Some(key);
+    training.stage = TrainingStage::AtKey;
+}
+
+/// The first move after the key position counts if it makes the step's placement or one of its eliminations
+fn judge_key_move(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    mut training: ResMut<Training>,
+    mut stats: ResMut<TrainingStats>,
+    mut highlights: ResMut<Highlights>,
+    mut armed: Local<bool>,
+    panel_query: Query<Entity, With<TrainerPanel>>,
+    query: Query<(&Cell, &CellPosition), Changed<Cell>>,
+) {
+    if training.stage != TrainingStage::AtKey {
+        *armed = false;
+        return;
+    }
+    // skip the notes filled in when stopping at the key position
+    if !*armed {
+        *armed = true;
+        return;
+    }
+    if query.is_empty() {
+        return;
+    }
+    let key = match &training.key {
+        Some(key) => key,
+        None => return,
+    };
+    let step = &key.step;
+    let correct = query.iter().any(|(cell, pos)| {
+        let placed = step.placement == cell.value().map(|value| (*pos, value));
+        let eliminated = step.eliminations.iter().any(|(at, value)| {
+            at == pos && cell.value().is_none() && !cell.notes().contains(*value)
+        });
+        placed || eliminated
+    });
+
+    stats.record(step.technique, correct);
+    stats.save();
+    highlights.set(HighlightKind::LogicPattern, step.cells.iter().copied());
+    highlights.set(
+        HighlightKind::LogicElimination,
+        step.eliminations.iter().map(|(pos, _)| *pos),
+    );
+
+    let accuracy = stats.get(step.technique);
+    let verdict = if correct { "Correct" } else { "Not this time" };
+    let message = format!(
+        "{verdict}. {step}\n{} accuracy {}/{}",
+        step.technique, accuracy.correct, accuracy.attempts
+    );
+    for panel in panel_query.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+    spawn_trainer_panel(&mut commands, &theme, &font_assets, message, true);
+    training.stage = TrainingStage::Answered;
+}
+
+fn spawn_trainer_panel(
+    commands: &mut Commands,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    message: String,
+    closable: bool,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(20.0),
+                        left: Val::Px(20.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Px(420.0), Val::Auto),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                background_color: theme.line_thick.into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            ThickLine,
+            Name::new("Trainer"),
+            TrainerPanel,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(10.0)),
+                        margin: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: theme.btn_normal.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle {
+                        style: Style {
+                            max_size: Size::new(Val::Px(396.0), Val::Undefined),
+                            ..default()
+                        },
+                        text: Text::from_section(
+                            message,
+                            TextStyle {
+                                font: font_assets.ui_font.clone(),
+                                font_size: 24.0,
+                                color: theme.text,
+                            },
+                        ),
+                        ..default()
+                    });
+                });
+            if closable {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Auto, Val::Px(50.0)),
+                                margin: UiRect::all(Val::Px(2.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: theme.btn_normal.into(),
+                            ..default()
+                        },
+                        Name::new("Close"),
+                        TrainerCloseButton,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: font_assets.status("Close", theme),
+                            ..default()
+                        });
+                    });
+            }
+        });
+}
+
+fn trainer_button_system(
+    mut commands: Commands,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<TrainerCloseButton>)>,
+    panel_query: Query<Entity, With<TrainerPanel>>,
+    mut highlights: ResMut<Highlights>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        highlights.clear(HighlightKind::LogicPattern);
+        highlights.clear(HighlightKind::LogicElimination);
+        for panel in panel_query.iter() {
+            commands.entity(panel).despawn_recursive();
+        }
+    }
+}
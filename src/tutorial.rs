@@ -0,0 +1,576 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use sudoku_variants::{
+    constraint::DefaultConstraint,
+    solver::{BacktrackingSolver, Solution, Solver},
+    Sudoku,
+};
+
+use crate::{
+    annotation::{HighlightKind, Highlights},
+    assist::fill_candidates,
+    board::{
+        create_board, create_cell_menu, BoardSelected, Cell, CellLock, CellPosition, EntryMode,
+        Value,
+    },
+    cleanup,
+    game::{GameStatus, Mistakes},
+    history::History,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::VariantRules,
+    AppState, SudokuSolution,
+};
+
+const LESSONS_PATH: &str = "lessons/tutorial.lessons";
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Lessons>()
+            .init_asset_loader::<LessonsLoader>()
+            .init_resource::<TutorialAssets>()
+            .init_resource::<Tutorial>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Tutorial)
+                    .with_system(spawn_lesson)
+                    .with_system(fill_lesson_candidates)
+                    .with_system(check_lesson_action)
+                    .with_system(tutorial_button_system)
+                    .with_system(update_lesson_step.before(spawn_lesson)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Tutorial)
+                    .with_system(exit_tutorial)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+/// Every lesson in a `.lessons` file, in the order they are taught
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "6c1b8f0e-3d5a-4c52-9a7e-2f4d1b8e9c31"]
+pub struct Lessons(pub Vec<Lesson>);
+
+/// A hand-crafted position and the steps that walk through one technique on it
+#[derive(Debug, Deserialize)]
+pub struct Lesson {
+    pub title: String,
+    /// 81 digits row by row, 0 for an empty cell
+    pub puzzle: String,
+    /// Start with every candidate noted
+    #[serde(default)]
+    pub candidates: bool,
+    pub steps: Vec<LessonStep>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LessonStep {
+    pub text: String,
+    /// Cells as (x, y) from the top left
+    #[serde(default)]
+    pub highlight: Vec<(usize, usize)>,
+    /// What the player has to do to move on, steps without one wait for Continue
+    #[serde(default)]
+    pub action: Option<LessonAction>,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LessonAction {
+    Place { cell: (usize, usize), value: usize },
+    Remove { cell: (usize, usize), value: usize },
+}
+
+impl LessonAction {
+    fn value(&self) -> usize {
+        match *self {
+            LessonAction::Place { value, .. } | LessonAction::Remove { value, .. } => value,
+        }
+    }
+
+    fn cell(&self) -> CellPosition {
+        match *self {
+            LessonAction::Place { cell, .. } | LessonAction::Remove { cell, .. } => {
+                CellPosition::new(cell.0, cell.1)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LessonsLoader;
+
+impl AssetLoader for LessonsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let lessons: Lessons = serde_json::from_slice(bytes)?;
+            for lesson in &lessons.0 {
+                validate_lesson(lesson)
+                    .map_err(|err| bevy::asset::Error::msg(format!("{}: {}", lesson.title, err)))?;
+            }
+            load_context.set_default_asset(LoadedAsset::new(lessons));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lessons"]
+    }
+}
+
+/// Rejects lessons that point outside the board or at digits that don't exist
+fn validate_lesson(lesson: &Lesson) -> Result<(), String> {
+    let in_range = |(x, y): (usize, usize)| x < 9 && y < 9;
+    if lesson.puzzle.len() != 81 || !lesson.puzzle.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("puzzle must be 81 digits".to_string());
+    }
+    for step in &lesson.steps {
+        if let Some(&cell) = step.highlight.iter().find(|&&cell| !in_range(cell)) {
+            return Err(format!("highlighted cell {:?} is off the board", cell));
+        }
+        if let Some(action) = step.action {
+            let CellPosition { x, y } = action.cell();
+            if !in_range((x, y)) {
+                return Err(format!("action cell {:?} is off the board", (x, y)));
+            }
+            if !(1..=9).contains(&action.value()) {
+                return Err(format!("action value {} is not 1-9", action.value()));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Resource)]
+pub struct TutorialAssets {
+    pub lessons: Handle<Lessons>,
+}
+
+impl FromWorld for TutorialAssets {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+        Self {
+            lessons: asset_server.load(LESSONS_PATH),
+        }
+    }
+}
+
+/// Resource for where the player is in the tutorial
+#[derive(Resource, Default)]
+pub struct Tutorial {
+    pub lesson: usize,
+    pub step: usize,
+    /// Shown under the step text after a wrong move
+    pub feedback: Option<String>,
+    /// Lesson whose board is on screen
+    spawned: Option<usize>,
+}
+
+impl Tutorial {
+    fn go_to_lesson(&mut self, lesson: usize) {
+        self.lesson = lesson;
+        self.step = 0;
+        self.feedback = None;
+    }
+}
+
+#[derive(Component)]
+struct LessonLayout;
+
+#[derive(Component)]
+struct LessonPanel;
+
+#[derive(Component, Debug, Copy, Clone)]
+enum TutorialButton {
+    Continue,
+    Previous,
+    Next,
+    Exit,
+}
+
+fn lesson_grid(puzzle: &str) -> Sudoku<DefaultConstraint> {
+    let mut sudoku = Sudoku::new_empty(3, 3, DefaultConstraint).unwrap();
+    let grid = sudoku.grid_mut();
+    for (i, digit) in puzzle.bytes().enumerate().take(81) {
+        if (b'1'..=b'9').contains(&digit) {
+            grid.set_cell(i % 9, i / 9, (digit - b'0') as usize)
+                .unwrap();
+        }
+    }
+    sudoku
+}
+
+/// Build the board for the current lesson, once its file has loaded
+fn spawn_lesson(
+    mut commands: Commands,
+    assets: Res<TutorialAssets>,
+    lessons: Res<Assets<Lessons>>,
+    font_assets: Res<FontAssets>,
+    theme: Res<Theme>,
+    windows: Res<Windows>,
+    mut tutorial: ResMut<Tutorial>,
+    mut status: ResMut<GameStatus>,
+    mut board_selected: ResMut<BoardSelected>,
+    mut entry_mode: ResMut<EntryMode>,
+    mut history: ResMut<History>,
+    mut mistakes: ResMut<Mistakes>,
+    layout_query: Query<Entity, With<LessonLayout>>,
+) {
+    if tutorial.spawned == Some(tutorial.lesson) {
+        return;
+    }
+    let lesson = match lessons
+        .get(&assets.lessons)
+        .and_then(|l| l.0.get(tutorial.lesson))
+    {
+        Some(lesson) => lesson,
+        None => return,
+    };
+    for layout in layout_query.iter() {
+        commands.entity(layout).despawn_recursive();
+    }
+
+    let sudoku = lesson_grid(&lesson.puzzle);
+    // lets mistakes be flagged like in a game
+    let solution = match BacktrackingSolver.solve(&sudoku) {
+        Solution::Unique(solution) => Some(solution),
+        _ => {
+            warn!("Lesson {} doesn't have a unique solution", lesson.title);
+            None
+        }
+    };
+    commands.insert_resource(SudokuSolution(solution));
+    commands.insert_resource(VariantRules::default());
+    *status = GameStatus::Playing;
+    *entry_mode = EntryMode::Value;
+    board_selected.entity = None;
+    // undo and the mistake count start over with each lesson
+    history.clear();
+    mistakes.0 = 0;
+
+    let window = windows.get_primary().unwrap();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Lesson"))
+        .insert(LessonLayout)
+        .with_children(|parent| {
+            create_board(
+                parent,
+                &theme,
+                &font_assets,
+                sudoku.grid(),
+                window.width(),
+                window.height(),
+            );
+            create_cell_menu(
+                parent,
+                &theme,
+                &font_assets,
+                window.width(),
+                window.height(),
+            );
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            top: Val::Px(20.0),
+                            right: Val::Px(20.0),
+                            ..default()
+                        },
+                        size: Size::new(Val::Px(320.0), Val::Auto),
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: theme.line_thick.into(),
+                    ..default()
+                },
+                ThickLine,
+                Name::new("Lesson Panel"),
+                LessonPanel,
+            ));
+        });
+    tutorial.spawned = Some(tutorial.lesson);
+}
+
+fn fill_lesson_candidates(
+    assets: Res<TutorialAssets>,
+    lessons: Res<Assets<Lessons>>,
+    tutorial: Res<Tutorial>,
+    added_query: Query<(), Added<CellPosition>>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    if added_query.is_empty() {
+        return;
+    }
+    let lesson = lessons
+        .get(&assets.lessons)
+        .and_then(|l| l.0.get(tutorial.lesson));
+    if lesson.map_or(false, |lesson| lesson.candidates) {
+        fill_candidates(&mut query);
+    }
+}
+
+/// Move on once the locked cell holds what the step asks for, undoing anything else
+fn check_lesson_action(
+    assets: Res<TutorialAssets>,
+    lessons: Res<Assets<Lessons>>,
+    mut tutorial: ResMut<Tutorial>,
+    mut query: Query<(&mut Cell, &CellPosition), Changed<Cell>>,
+) {
+    let step = lessons
+        .get(&assets.lessons)
+        .and_then(|l| l.0.get(tutorial.lesson))
+        .and_then(|lesson| lesson.steps.get(tutorial.step));
+    let action = match step.and_then(|step| step.action) {
+        Some(action) => action,
+        None => return,
+    };
+    for (mut cell, pos) in query.iter_mut() {
+        if *pos != action.cell() {
+            continue;
+        }
+        let done = match action {
+            LessonAction::Place { value, .. } => cell.value() == Some(Value::from(value)),
+            LessonAction::Remove { value, .. } => {
+                cell.value().is_none() && !cell.notes().contains(Value::from(value))
+            }
+        };
+        if done {
+            tutorial.step += 1;
+            tutorial.feedback = None;
+        } else if cell.value().is_some() {
+            cell.input(None, EntryMode::Value);
+            tutorial.feedback = Some("Not quite, have another look".to_string());
+        }
+    }
+}
+
+fn tutorial_button_system(
+    interaction_query: Query<(&Interaction, &TutorialButton), (Changed<Interaction>, With<Button>)>,
+    assets: Res<TutorialAssets>,
+    lessons: Res<Assets<Lessons>>,
+    mut tutorial: ResMut<Tutorial>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let count = lessons.get(&assets.lessons).map_or(0, |l| l.0.len());
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            TutorialButton::Continue => {
+                tutorial.step += 1;
+                tutorial.feedback = None;
+            }
+            TutorialButton::Previous => {
+                let lesson = tutorial.lesson.saturating_sub(1);
+                tutorial.go_to_lesson(lesson);
+            }
+            TutorialButton::Next => {
+                let lesson = (tutorial.lesson + 1).min(count.saturating_sub(1));
+                tutorial.go_to_lesson(lesson);
+            }
+            TutorialButton::Exit => {
+                app_state.set(AppState::Menu).unwrap();
+            }
+        }
+    }
+}
+
+fn spawn_tutorial_button(
+    parent: &mut ChildBuilder,
+    theme: &Theme,
+    font_assets: &FontAssets,
+    button: TutorialButton,
+    label: &str,
+    width: f32,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(width), Val::Px(50.0)),
+                    margin: UiRect::all(Val::Px(2.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: theme.btn_normal.into(),
+                ..default()
+            },
+            Name::new(label.to_string()),
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: font_assets.status(label, theme),
+                ..default()
+            });
+        });
+}
+
+/// Show the step's text and highlights, and lock input to the cell it asks about
+fn update_lesson_step(
+    mut commands: Commands,
+    assets: Res<TutorialAssets>,
+    lessons: Res<Assets<Lessons>>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    mut tutorial: ResMut<Tutorial>,
+    mut cell_lock: ResMut<CellLock>,
+    mut highlights: ResMut<Highlights>,
+    mut app_state: ResMut<State<AppState>>,
+    panel_query: Query<Entity, With<LessonPanel>>,
+    added_query: Query<(), Added<LessonPanel>>,
+) {
+    if !tutorial.is_changed() && added_query.is_empty() {
+        return;
+    }
+    let all = match lessons.get(&assets.lessons) {
+        Some(lessons) => &lessons.0,
+        None => return,
+    };
+    let lesson = match all.get(tutorial.lesson) {
+        Some(lesson) => lesson,
+        None => return,
+    };
+    // finishing a lesson moves on to the next, and the last one back to the title
+    if tutorial.step >= lesson.steps.len() {
+        if tutorial.lesson + 1 < all.len() {
+            let next = tutorial.lesson + 1;
+            tutorial.go_to_lesson(next);
+        } else {
+            app_state.set(AppState::Menu).unwrap();
+        }
+        return;
+    }
+    // a new lesson's panel is filled in once its board is spawned
+    if tutorial.spawned != Some(tutorial.lesson) {
+        return;
+    }
+    let step = &lesson.steps[tutorial.step];
+
+    cell_lock.0 = step.action.map(|action| action.cell());
+    highlights.set(
+        HighlightKind::Lesson,
+        step.highlight
+            .iter()
+            .map(|(x, y)| CellPosition::new(*x, *y)),
+    );
+
+    let text_style = TextStyle {
+        font: font_assets.ui_font.clone(),
+        font_size: 24.0,
+        color: theme.text,
+    };
+    for panel in panel_query.iter() {
+        commands.entity(panel).despawn_descendants();
+        commands.entity(panel).with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        margin: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: theme.btn_normal.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    let title = format!(
+                        "{} ({}/{})",
+                        lesson.title,
+                        tutorial.step + 1,
+                        lesson.steps.len()
+                    );
+                    parent.spawn(TextBundle {
+                        text: font_assets.status(title, &theme),
+                        ..default()
+                    });
+                    parent.spawn(TextBundle {
+                        style: Style {
+                            max_size: Size::new(Val::Px(296.0), Val::Undefined),
+                            margin: UiRect::vertical(Val::Px(8.0)),
+                            ..default()
+                        },
+                        text: Text::from_section(step.text.clone(), text_style.clone()),
+                        ..default()
+                    });
+                    if let Some(feedback) = &tutorial.feedback {
+                        parent.spawn(TextBundle {
+                            text: Text::from_section(
+                                feedback.clone(),
+                                TextStyle {
+                                    color: theme.text_error,
+                                    ..text_style.clone()
+                                },
+                            ),
+                            ..default()
+                        });
+                    }
+                });
+            if step.action.is_none() {
+                spawn_tutorial_button(
+                    parent,
+                    &theme,
+                    &font_assets,
+                    TutorialButton::Continue,
+                    "Continue",
+                    312.0,
+                );
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (TutorialButton::Previous, "<"),
+                        (TutorialButton::Exit, "Exit"),
+                        (TutorialButton::Next, ">"),
+                    ] {
+                        spawn_tutorial_button(parent, &theme, &font_assets, button, label, 100.0);
+                    }
+                });
+        });
+    }
+}
+
+/// Leave the board as the game expects it, and start from the first lesson next time
+fn exit_tutorial(
+    mut tutorial: ResMut<Tutorial>,
+    mut cell_lock: ResMut<CellLock>,
+    mut highlights: ResMut<Highlights>,
+) {
+    *tutorial = Tutorial::default();
+    cell_lock.0 = None;
+    highlights.clear(HighlightKind::Lesson);
+}
@@ -3,7 +3,7 @@ use std::time::Duration;
 use bevy::prelude::*;
 use bevy_tweening::{lens::*, *};
 
-use crate::{board::Cell, lens::{Camera2dClearColorLens, BackgroundColorLens}};
+use crate::{board::{Cell, CellMenuButton, CellText, EntryModeButton, ValueOrigin}, lens::{Camera2dClearColorLens, BackgroundColorLens}, samurai::SamuraiCell};
 
 pub struct UIPlugin;
 
@@ -29,7 +29,7 @@ pub fn theme_toggle_events(
     mut theme_mode: ResMut<ThemeMode>,
     mut theme: ResMut<Theme>,
     button_query: Query<Entity, (With<Button>, With<BackgroundColor>)>,
-    text_query: Query<Entity, With<Text>>,
+    text_query: Query<Entity, (With<Text>, Without<CellText>)>,
     thick_line_query: Query<Entity, (With<BackgroundColor>, With<ThickLine>)>,
     thin_line_query: Query<Entity, (With<BackgroundColor>, With<ThinLine>)>,
     camera_query: Query<Entity, With<Camera2d>>,
@@ -133,15 +133,62 @@ impl ThemeMode {
 #[derive(Resource,Clone)]
 pub struct Theme {
     pub text: Color,
+    pub text_error: Color,
     pub btn_normal: Color,
     pub btn_hovered: Color,
     pub btn_pressed: Color,
     pub btn_selected: Color,
+    pub btn_disabled: Color,
     pub line_thin: Color,
     pub line_thick: Color,
     pub background: Color,
+    /// Palette the player can color cells with
+    pub user_colors: [Color; USER_COLOR_COUNT],
+    /// Lines, arrows and circles drawn over the board
+    pub annotation: Color,
+    /// Outline for cells that differ from a checkpoint
+    pub compare: Color,
+    /// Shading for cells with few candidates, alpha scales with the count
+    pub heatmap: Color,
+    pub bi_value: Color,
+    pub hidden_single: Color,
+    /// Cells forming the logic step being previewed
+    pub logic_pattern: Color,
+    /// Cells the previewed logic step removes candidates from
+    pub logic_elimination: Color,
+    /// Variant lines, drawn beneath the digits
+    pub thermo: Color,
+    pub arrow: Color,
+    pub palindrome: Color,
+    pub renban: Color,
+    pub whisper: Color,
+    /// Border clues, drawn over the grid lines
+    pub kropki_white: Color,
+    pub kropki_black: Color,
+    pub xv: Color,
+    /// Extra regions, the Windoku shading sits under the cell text
+    pub windoku: Color,
+    pub disjoint: Color,
+    /// Even/odd shapes under the digits, and greater-than signs
+    pub parity: Color,
+    pub inequality: Color,
+    pub diagonal: Color,
+    pub value_given: ValueStyle,
+    pub value_player: ValueStyle,
+    pub value_hint: ValueStyle,
+    pub value_solver: ValueStyle,
 }
 
+/// How a cell value is drawn, sizes stand in for weights since there is one font
+#[derive(Debug, Copy, Clone)]
+pub struct ValueStyle {
+    pub color: Color,
+    pub font_size: f32,
+}
+
+/// Number of colors in [`Theme::user_colors`]
+pub const USER_COLOR_COUNT: usize = 6;
+
 impl Default for Theme {
     fn default() -> Self {
         ThemeMode::default().theme().clone()
@@ -152,25 +199,126 @@ impl Theme {
 
     const LIGHT: Theme = Theme {
         text: Color::BLACK,
+        text_error: Color::RED,
         btn_normal: Color::WHITE,
         btn_hovered: Color::GRAY,
         btn_pressed: Color::DARK_GRAY,
         btn_selected: Color::GREEN,        
+        btn_disabled: Color::rgb(0.8, 0.8, 0.8),
         line_thin: Color::GRAY,
         line_thick: Color::BLACK,        
         background: Color::WHITE,
+        user_colors: [
+            Color::rgba(1.0, 0.45, 0.45, 0.6),
+            Color::rgba(1.0, 0.7, 0.3, 0.6),
+            Color::rgba(1.0, 0.95, 0.35, 0.6),
+            Color::rgba(0.45, 0.85, 0.45, 0.6),
+            Color::rgba(0.45, 0.65, 1.0, 0.6),
+            Color::rgba(0.75, 0.5, 1.0, 0.6),
+        ],
+        annotation: Color::rgba(0.1, 0.35, 0.9, 0.8),
+        compare: Color::rgb(0.95, 0.55, 0.0),
+        heatmap: Color::rgba(1.0, 0.45, 0.0, 0.6),
+        bi_value: Color::rgb(0.6, 0.2, 0.8),
+        hidden_single: Color::rgb(0.0, 0.6, 0.3),
+        logic_pattern: Color::rgb(0.1, 0.45, 0.85),
+        logic_elimination: Color::rgb(0.85, 0.15, 0.15),
+        thermo: Color::rgb(0.78, 0.78, 0.78),
+        arrow: Color::rgb(0.55, 0.55, 0.55),
+        palindrome: Color::rgb(0.65, 0.65, 0.7),
+        renban: Color::rgb(0.85, 0.7, 0.95),
+        whisper: Color::rgb(0.55, 0.85, 0.5),
+        kropki_white: Color::WHITE,
+        kropki_black: Color::BLACK,
+        xv: Color::BLACK,
+        windoku: Color::rgba(0.5, 0.6, 0.9, 0.3),
+        disjoint: Color::rgb(0.4, 0.5, 0.8),
+        parity: Color::rgba(0.5, 0.5, 0.5, 0.35),
+        inequality: Color::BLACK,
+        diagonal: Color::rgba(0.2, 0.4, 0.8, 0.6),
+        value_given: ValueStyle {
+            color: Color::BLACK,
+            font_size: 72.0,
+        },
+        value_player: ValueStyle {
+            color: Color::rgb(0.1, 0.3, 0.75),
+            font_size: 64.0,
+        },
+        value_hint: ValueStyle {
+            color: Color::rgb(0.1, 0.5, 0.25),
+            font_size: 64.0,
+        },
+        value_solver: ValueStyle {
+            color: Color::rgb(0.5, 0.5, 0.5),
+            font_size: 56.0,
+        },
     };
 
     const DARK: Theme = Theme {
         text: Color::WHITE,
+        text_error: Color::rgb(1.0, 0.35, 0.35),
         btn_normal: Color::BLACK,
         btn_hovered: Color::GRAY,
         btn_pressed: Color::DARK_GRAY,
         btn_selected: Color::DARK_GRAY,
+        btn_disabled: Color::rgb(0.25, 0.25, 0.25),
         line_thin: Color::GRAY,
         line_thick: Color::WHITE,
         background: Color::BLACK,
+        user_colors: [
+            Color::rgba(0.7, 0.2, 0.2, 0.6),
+            Color::rgba(0.7, 0.45, 0.1, 0.6),
+            Color::rgba(0.65, 0.6, 0.1, 0.6),
+            Color::rgba(0.2, 0.55, 0.2, 0.6),
+            Color::rgba(0.2, 0.35, 0.7, 0.6),
+            Color::rgba(0.45, 0.25, 0.7, 0.6),
+        ],
+        annotation: Color::rgba(0.45, 0.7, 1.0, 0.8),
+        compare: Color::rgb(1.0, 0.7, 0.2),
+        heatmap: Color::rgba(1.0, 0.5, 0.1, 0.5),
+        bi_value: Color::rgb(0.8, 0.5, 1.0),
+        hidden_single: Color::rgb(0.3, 0.9, 0.5),
+        logic_pattern: Color::rgb(0.4, 0.7, 1.0),
+        logic_elimination: Color::rgb(1.0, 0.4, 0.4),
+        thermo: Color::rgb(0.35, 0.35, 0.35),
+        arrow: Color::rgb(0.6, 0.6, 0.6),
+        palindrome: Color::rgb(0.4, 0.4, 0.5),
+        renban: Color::rgb(0.45, 0.3, 0.55),
+        whisper: Color::rgb(0.2, 0.5, 0.2),
+        kropki_white: Color::rgb(0.9, 0.9, 0.9),
+        kropki_black: Color::rgb(0.15, 0.15, 0.15),
+        xv: Color::WHITE,
+        windoku: Color::rgba(0.3, 0.4, 0.7, 0.35),
+        disjoint: Color::rgb(0.5, 0.6, 0.9),
+        parity: Color::rgba(0.6, 0.6, 0.6, 0.3),
+        inequality: Color::WHITE,
+        diagonal: Color::rgba(0.5, 0.6, 0.9, 0.6),
+        value_given: ValueStyle {
+            color: Color::WHITE,
+            font_size: 72.0,
+        },
+        value_player: ValueStyle {
+            color: Color::rgb(0.55, 0.75, 1.0),
+            font_size: 64.0,
+        },
+        value_hint: ValueStyle {
+            color: Color::rgb(0.5, 0.85, 0.6),
+            font_size: 64.0,
+        },
+        value_solver: ValueStyle {
+            color: Color::rgb(0.55, 0.55, 0.55),
+            font_size: 56.0,
+        },
     };
+
+    pub fn value_style(&self, origin: ValueOrigin) -> ValueStyle {
+        match origin {
+            ValueOrigin::Given => self.value_given,
+            ValueOrigin::Player => self.value_player,
+            ValueOrigin::Hint => self.value_hint,
+            ValueOrigin::Solver => self.value_solver,
+        }
+    }
 }
 
 #[derive(Resource)]
@@ -208,6 +356,56 @@ impl FontAssets {
         }
     }
 
+    /// Text for a board cell, the value followed by its notes
+    pub fn cell(&self, value: impl Into<String>, notes: impl Into<String>, theme: &Theme) -> Text {
+        let mut text = self.btn(value, theme);
+        text.sections.push(TextSection {
+            value: notes.into(),
+            style: TextStyle {
+                font: self.ui_font.clone(),
+                font_size: 20.0,
+                color: theme.text,
+            },
+        });
+        text
+    }
+
+    /// Small text used for count badges
+    pub fn badge(&self, text: impl Into<String>, theme: &Theme) -> Text {
+        Text {
+            sections: vec![TextSection {
+                value: text.into(),
+                style: TextStyle {
+                    font: self.ui_font.clone(),
+                    font_size: 24.0,
+                    color: theme.text,
+                },
+            }],
+            alignment: TextAlignment {
+                vertical: VerticalAlign::Top,
+                horizontal: HorizontalAlign::Right,
+            }
+        }
+    }
+
+    /// Text for status messages under the board
+    pub fn status(&self, text: impl Into<String>, theme: &Theme) -> Text {
+        Text {
+            sections: vec![TextSection {
+                value: text.into(),
+                style: TextStyle {
+                    font: self.ui_font.clone(),
+                    font_size: 30.0,
+                    color: theme.text,
+                },
+            }],
+            alignment: TextAlignment {
+                vertical: VerticalAlign::Center,
+                horizontal: HorizontalAlign::Center,
+            }
+        }
+    }
+
     pub fn loading_text(&self, text: impl Into<String>, theme: &Theme) -> TextSection {
         
             TextSection {
@@ -226,7 +424,14 @@ impl FontAssets {
 fn button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>, Without<Cell>),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            Without<Cell>,
+            Without<CellMenuButton>,
+            Without<EntryModeButton>,
+            Without<SamuraiCell>,
+        ),
     >,
 
     theme: Res<Theme>,
@@ -0,0 +1,366 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use sudoku_variants::{constraint::Constraint, SudokuGrid};
+
+use crate::{
+    annotation::spawn_segment,
+    assist::Grid,
+    board::{border_center, CellPosition},
+    ui::{FontAssets, Theme},
+};
+
+use super::line::value;
+
+/// Chance a border that fits is given a clue, when not every clue is given
+const CLUE_CHANCE: f64 = 0.5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BorderKind {
+    /// Kropki white dot, the values are consecutive
+    White,
+    /// Kropki black dot, one value is double the other
+    Black,
+    /// The values sum to ten
+    X,
+    /// The values sum to five
+    V,
+}
+
+impl BorderKind {
+    fn holds(self, a: usize, b: usize) -> bool {
+        match self {
+            BorderKind::White => a.abs_diff(b) == 1,
+            BorderKind::Black => a == b * 2 || b == a * 2,
+            BorderKind::X => a + b == 10,
+            BorderKind::V => a + b == 5,
+        }
+    }
+
+    fn is_kropki(self) -> bool {
+        matches!(self, BorderKind::White | BorderKind::Black)
+    }
+}
+
+/// A clue on the border between two orthogonally adjacent cells, the top or left one first
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BorderClue {
+    pub kind: BorderKind,
+    pub cells: [CellPosition; 2],
+}
+
+impl BorderClue {
+    pub fn new(kind: BorderKind, a: CellPosition, b: CellPosition) -> Self {
+        let cells = if (a.y, a.x) <= (b.y, b.x) { [a, b] } else { [b, a] };
+        Self { kind, cells }
+    }
+
+    fn joins(&self, a: CellPosition, b: CellPosition) -> bool {
+        self.cells == [a, b] || self.cells == [b, a]
+    }
+}
+
+/// Which border clues are given in full, so a border without one rules its relation out
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NegativeBorders {
+    /// Every white and black dot is shown
+    pub kropki: bool,
+    /// Every white dot is shown, consecutive neighbors without one are ruled out
+    pub white: bool,
+    /// Every X and V is shown
+    pub xv: bool,
+}
+
+impl NegativeBorders {
+    pub fn any(&self) -> bool {
+        self.kropki || self.white || self.xv
+    }
+}
+
+/// Cells sharing an edge with `pos`
+fn orthogonal_neighbors(pos: CellPosition) -> impl Iterator<Item = CellPosition> {
+    let (x, y) = (pos.x as i32, pos.y as i32);
+    [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+        .into_iter()
+        .filter(|(x, y)| (0..9).contains(x) && (0..9).contains(y))
+        .map(|(x, y)| CellPosition::new(x as usize, y as usize))
+}
+
+pub(super) fn borders_allow(
+    borders: &[BorderClue],
+    negative: NegativeBorders,
+    pos: CellPosition,
+    number: usize,
+    value_at: &impl Fn(CellPosition) -> Option<usize>,
+) -> bool {
+    orthogonal_neighbors(pos).all(|other| {
+        let value = match value_at(other) {
+            Some(value) => value,
+            None => return true,
+        };
+        let clues = borders.iter().filter(|clue| clue.joins(pos, other));
+        if !clues.clone().all(|clue| clue.kind.holds(number, value)) {
+            return false;
+        }
+        let related = |kinds: [BorderKind; 2]| kinds.iter().any(|kind| kind.holds(number, value));
+        let has_kropki = clues.clone().any(|clue| clue.kind.is_kropki());
+        let has_xv = clues.clone().any(|clue| !clue.kind.is_kropki());
+        !(negative.kropki && !has_kropki && related([BorderKind::White, BorderKind::Black]))
+            && !(negative.white && !has_kropki && BorderKind::White.holds(number, value))
+            && !(negative.xv && !has_xv && related([BorderKind::X, BorderKind::V]))
+    })
+}
+
+/// Kropki dots and XV markers
+#[derive(Debug, Clone)]
+pub struct BorderConstraint {
+    pub(super) borders: Vec<BorderClue>,
+    pub(super) negative: NegativeBorders,
+}
+
+impl Constraint for BorderConstraint {
+    fn check_number(&self, grid: &SudokuGrid, column: usize, row: usize, number: usize) -> bool {
+        let value_at = |at: CellPosition| grid.get_cell(at.x, at.y).ok().flatten();
+        borders_allow(
+            &self.borders,
+            self.negative,
+            CellPosition::new(column, row),
+            number,
+            &value_at,
+        )
+    }
+}
+
+/// Which border clues new games are generated with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BorderSetting {
+    Off,
+    Kropki,
+    /// Every dot given, the negative constraint
+    KropkiAll,
+    XV,
+    /// Every X and V given, the negative constraint
+    XVAll,
+}
+
+impl Default for BorderSetting {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+impl Display for BorderSetting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BorderSetting::Off => write!(f, "Off"),
+            BorderSetting::Kropki => write!(f, "Kropki"),
+            BorderSetting::KropkiAll => write!(f, "All Kropki"),
+            BorderSetting::XV => write!(f, "XV"),
+            BorderSetting::XVAll => write!(f, "All XV"),
+        }
+    }
+}
+
+impl BorderSetting {
+    pub fn next(self) -> Self {
+        match self {
+            BorderSetting::Off => BorderSetting::Kropki,
+            BorderSetting::Kropki => BorderSetting::KropkiAll,
+            BorderSetting::KropkiAll => BorderSetting::XV,
+            BorderSetting::XV => BorderSetting::XVAll,
+            BorderSetting::XVAll => BorderSetting::Off,
+        }
+    }
+
+    fn kinds(self) -> [BorderKind; 2] {
+        match self {
+            BorderSetting::XV | BorderSetting::XVAll => [BorderKind::X, BorderKind::V],
+            _ => [BorderKind::White, BorderKind::Black],
+        }
+    }
+
+    fn negative(self) -> NegativeBorders {
+        NegativeBorders {
+            kropki: self == BorderSetting::KropkiAll,
+            white: false,
+            xv: self == BorderSetting::XVAll,
+        }
+    }
+}
+
+/// Clues on borders the solved grid already satisfies, every one of them for a negative constraint
+pub fn random_borders(
+    solution: &Grid,
+    setting: BorderSetting,
+    rng: &mut impl Rng,
+) -> (Vec<BorderClue>, NegativeBorders) {
+    let negative = setting.negative();
+    let mut borders = Vec::new();
+    if setting == BorderSetting::Off {
+        return (borders, negative);
+    }
+    for i in 0..81 {
+        let a = CellPosition::new(i % 9, i / 9);
+        for b in [CellPosition::new(a.x + 1, a.y), CellPosition::new(a.x, a.y + 1)] {
+            if b.x > 8 || b.y > 8 {
+                continue;
+            }
+            let fits = setting
+                .kinds()
+                .into_iter()
+                .filter(|kind| kind.holds(value(solution, a), value(solution, b)))
+                .collect::<Vec<_>>();
+            // 1 and 2 fit either dot, only one is shown
+            let kind = match fits.choose(rng) {
+                Some(kind) => *kind,
+                None => continue,
+            };
+            if negative.any() || rng.gen_bool(CLUE_CHANCE) {
+                borders.push(BorderClue::new(kind, a, b));
+            }
+        }
+    }
+    (borders, negative)
+}
+
+/// Markers on the borders above and to the left of the cell, later cells render over earlier ones
+pub(super) fn spawn_border_marks(
+    parent: &mut ChildBuilder,
+    borders: &[BorderClue],
+    pos: CellPosition,
+    cell_size: f32,
+    theme: &Theme,
+    font_assets: &FontAssets,
+) {
+    for clue in borders.iter().filter(|clue| clue.cells[1] == pos) {
+        let center = border_center(pos, clue.cells[0], cell_size);
+        let radius = cell_size * 0.12;
+        match clue.kind {
+            BorderKind::White => spawn_dot(parent, center, radius, theme.kropki_white, theme.line_thick),
+            BorderKind::Black => spawn_dot(parent, center, radius, theme.kropki_black, theme.line_thick),
+            BorderKind::X | BorderKind::V => {
+                let label = if clue.kind == BorderKind::X { "X" } else { "V" };
+                let size = cell_size * 0.3;
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: Val::Px(center.x - size * 0.5),
+                                top: Val::Px(center.y - size * 0.5),
+                                ..default()
+                            },
+                            size: Size::new(Val::Px(size), Val::Px(size)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: theme.btn_normal.into(),
+                        ..default()
+                    })
+                    .insert(FocusPolicy::Pass)
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: Text::from_section(
+                                label,
+                                TextStyle {
+                                    font: font_assets.ui_font.clone(),
+                                    font_size: size,
+                                    color: theme.xv,
+                                },
+                            ),
+                            ..default()
+                        });
+                    });
+            }
+        }
+    }
+}
+
+/// Two squares at 45 degrees make an octagon, close enough to a dot at this size
+fn spawn_dot(parent: &mut ChildBuilder, center: Vec2, radius: f32, fill: Color, outline: Color) {
+    for (r, color) in [(radius, outline), (radius - 2.0, fill)] {
+        for direction in [Vec2::X, Vec2::ONE.normalize()] {
+            spawn_segment(parent, center - direction * r, center + direction * r, r * 2.0, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::test_grid;
+
+    fn constraint(borders: Vec<BorderClue>, negative: NegativeBorders) -> BorderConstraint {
+        BorderConstraint { borders, negative }
+    }
+
+    fn clue(kind: BorderKind) -> BorderClue {
+        BorderClue::new(kind, CellPosition::new(1, 0), CellPosition::new(0, 0))
+    }
+
+    #[test]
+    fn white_dot_needs_consecutive_values() {
+        let white = constraint(vec![clue(BorderKind::White)], NegativeBorders::default());
+        assert!(white.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 5));
+        assert!(!white.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 6));
+    }
+
+    #[test]
+    fn black_dot_needs_a_double() {
+        let black = constraint(vec![clue(BorderKind::Black)], NegativeBorders::default());
+        assert!(black.check_number(&test_grid(&[(0, 0, 3)]), 1, 0, 6));
+        assert!(!black.check_number(&test_grid(&[(0, 0, 3)]), 1, 0, 4));
+    }
+
+    #[test]
+    fn x_and_v_need_their_sums() {
+        let x = constraint(vec![clue(BorderKind::X)], NegativeBorders::default());
+        assert!(x.check_number(&test_grid(&[(0, 0, 3)]), 1, 0, 7));
+        assert!(!x.check_number(&test_grid(&[(0, 0, 3)]), 1, 0, 2));
+        let v = constraint(vec![clue(BorderKind::V)], NegativeBorders::default());
+        assert!(v.check_number(&test_grid(&[(0, 0, 3)]), 1, 0, 2));
+        assert!(!v.check_number(&test_grid(&[(0, 0, 3)]), 1, 0, 7));
+    }
+
+    #[test]
+    fn negative_kropki_rules_out_undotted_relations() {
+        let negative = NegativeBorders {
+            kropki: true,
+            ..default()
+        };
+        let empty = constraint(vec![], negative);
+        assert!(empty.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 7));
+        assert!(!empty.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 5));
+        assert!(!empty.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 8));
+    }
+
+    #[test]
+    fn negative_white_exempts_dotted_pairs() {
+        let negative = NegativeBorders {
+            white: true,
+            ..default()
+        };
+        let dotted = constraint(vec![clue(BorderKind::White)], negative);
+        assert!(dotted.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 5));
+        // the negative constraint still holds below the given cell
+        assert!(!dotted.check_number(&test_grid(&[(0, 0, 4)]), 0, 1, 3));
+        // doubles are fine without a black dot
+        assert!(dotted.check_number(&test_grid(&[(0, 0, 4)]), 0, 1, 8));
+    }
+
+    #[test]
+    fn negative_xv_rules_out_unmarked_sums() {
+        let negative = NegativeBorders {
+            xv: true,
+            ..default()
+        };
+        let empty = constraint(vec![], negative);
+        assert!(empty.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 5));
+        assert!(!empty.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 6));
+        assert!(!empty.check_number(&test_grid(&[(0, 0, 4)]), 1, 0, 1));
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use sudoku_variants::{constraint::Constraint, SudokuGrid};
+
+use crate::{
+    annotation::{spawn_circle, spawn_segment},
+    assist::Grid,
+    board::{border_center, CellPosition},
+    ui::Theme,
+};
+
+use super::line::value;
+
+/// Cells given a parity shape in a generated puzzle
+const PARITY_COUNT: usize = 16;
+/// Chance a border between two cells is given a sign
+const SIGN_CHANCE: f64 = 0.5;
+
+/// A circle for an odd value or a square for an even one, shaded under the cell
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParityClue {
+    pub cell: CellPosition,
+    pub even: bool,
+}
+
+impl ParityClue {
+    fn holds(&self, number: usize) -> bool {
+        (number % 2 == 0) == self.even
+    }
+}
+
+/// A sign between orthogonally adjacent cells, pointing at the smaller value
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inequality {
+    pub greater: CellPosition,
+    pub lesser: CellPosition,
+}
+
+pub(super) fn compare_allow(
+    parity: &[ParityClue],
+    signs: &[Inequality],
+    pos: CellPosition,
+    number: usize,
+    value_at: &impl Fn(CellPosition) -> Option<usize>,
+) -> bool {
+    parity
+        .iter()
+        .filter(|clue| clue.cell == pos)
+        .all(|clue| clue.holds(number))
+        && signs.iter().all(|sign| {
+            // an empty neighbor still rules out the extreme the sign can't reach
+            if sign.greater == pos {
+                value_at(sign.lesser).map_or(number > 1, |other| number > other)
+            } else if sign.lesser == pos {
+                value_at(sign.greater).map_or(number < 9, |other| number < other)
+            } else {
+                true
+            }
+        })
+}
+
+/// Parity shapes and greater-than signs
+#[derive(Debug, Clone)]
+pub struct CompareConstraint {
+    pub(super) parity: Vec<ParityClue>,
+    pub(super) signs: Vec<Inequality>,
+}
+
+impl Constraint for CompareConstraint {
+    fn check_number(&self, grid: &SudokuGrid, column: usize, row: usize, number: usize) -> bool {
+        let value_at = |at: CellPosition| grid.get_cell(at.x, at.y).ok().flatten();
+        compare_allow(
+            &self.parity,
+            &self.signs,
+            CellPosition::new(column, row),
+            number,
+            &value_at,
+        )
+    }
+}
+
+/// Which parity shapes and signs new games are generated with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompareSetting {
+    Off,
+    Parity,
+    GreaterThan,
+    Both,
+}
+
+impl Default for CompareSetting {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+impl Display for CompareSetting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompareSetting::Off => write!(f, "Off"),
+            CompareSetting::Parity => write!(f, "Even/Odd"),
+            CompareSetting::GreaterThan => write!(f, "Greater Than"),
+            CompareSetting::Both => write!(f, "Both"),
+        }
+    }
+}
+
+impl CompareSetting {
+    pub fn next(self) -> Self {
+        match self {
+            CompareSetting::Off => CompareSetting::Parity,
+            CompareSetting::Parity => CompareSetting::GreaterThan,
+            CompareSetting::GreaterThan => CompareSetting::Both,
+            CompareSetting::Both => CompareSetting::Off,
+        }
+    }
+}
+
+/// Parity shapes and signs read off the solved grid
+pub fn random_compare_clues(
+    solution: &Grid,
+    setting: CompareSetting,
+    rng: &mut impl Rng,
+) -> (Vec<ParityClue>, Vec<Inequality>) {
+    let mut parity = Vec::new();
+    let mut signs = Vec::new();
+    if matches!(setting, CompareSetting::Parity | CompareSetting::Both) {
+        let mut cells = (0..81)
+            .map(|i| CellPosition::new(i % 9, i / 9))
+            .collect::<Vec<_>>();
+        cells.shuffle(rng);
+        parity = cells
+            .into_iter()
+            .take(PARITY_COUNT)
+            .map(|cell| ParityClue {
+                cell,
+                even: value(solution, cell) % 2 == 0,
+            })
+            .collect();
+    }
+    if matches!(setting, CompareSetting::GreaterThan | CompareSetting::Both) {
+        for i in 0..81 {
+            let a = CellPosition::new(i % 9, i / 9);
+            for b in [
+                CellPosition::new(a.x + 1, a.y),
+                CellPosition::new(a.x, a.y + 1),
+            ] {
+                if b.x > 8 || b.y > 8 || !rng.gen_bool(SIGN_CHANCE) {
+                    continue;
+                }
+                let (greater, lesser) = if value(solution, a) > value(solution, b) {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                signs.push(Inequality { greater, lesser });
+            }
+        }
+    }
+    (parity, signs)
+}
+
+/// Parity shape under the cell, and signs on the borders above and to the left of it
+pub(super) fn spawn_compare_marks(
+    parent: &mut ChildBuilder,
+    parity: &[ParityClue],
+    signs: &[Inequality],
+    pos: CellPosition,
+    cell_size: f32,
+    theme: &Theme,
+) {
+    let center = Vec2::splat(cell_size * 0.5);
+    for clue in parity.iter().filter(|clue| clue.cell == pos) {
+        let radius = cell_size * 0.4;
+        if clue.even {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Px(center.x - radius),
+                            top: Val::Px(center.y - radius),
+                            ..default()
+                        },
+                        size: Size::new(Val::Px(radius * 2.0), Val::Px(radius * 2.0)),
+                        ..default()
+                    },
+                    background_color: theme.parity.into(),
+                    ..default()
+                })
+                .insert(FocusPolicy::Pass);
+        } else {
+            // a ring as thick as its radius fills in
+            spawn_circle(parent, center, radius * 0.5, radius, theme.parity);
+        }
+    }
+
+    let thickness = (cell_size * 0.04).max(2.0);
+    for sign in signs.iter() {
+        // drawn by the bottom or right cell, like border clues
+        let (earlier, later) = if (sign.greater.y, sign.greater.x) < (sign.lesser.y, sign.lesser.x)
+        {
+            (sign.greater, sign.lesser)
+        } else {
+            (sign.lesser, sign.greater)
+        };
+        if later != pos {
+            continue;
+        }
+        let middle = border_center(pos, earlier, cell_size);
+        // the tip points at the smaller value
+        let toward = Vec2::new(
+            sign.lesser.x as f32 - sign.greater.x as f32,
+            sign.lesser.y as f32 - sign.greater.y as f32,
+        );
+        let size = cell_size * 0.1;
+        let tip = middle + toward * size;
+        let back = middle - toward * size;
+        let across = toward.perp() * size * 1.5;
+        spawn_segment(parent, back + across, tip, thickness, theme.inequality);
+        spawn_segment(parent, back - across, tip, thickness, theme.inequality);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::test_grid;
+
+    #[test]
+    fn parity_shapes_hold_their_parity() {
+        let constraint = CompareConstraint {
+            parity: vec![ParityClue {
+                cell: CellPosition::new(2, 2),
+                even: true,
+            }],
+            signs: vec![],
+        };
+        assert!(constraint.check_number(&test_grid(&[]), 2, 2, 4));
+        assert!(!constraint.check_number(&test_grid(&[]), 2, 2, 5));
+    }
+
+    #[test]
+    fn signs_point_at_the_smaller_value() {
+        let constraint = CompareConstraint {
+            parity: vec![],
+            signs: vec![Inequality {
+                greater: CellPosition::new(0, 0),
+                lesser: CellPosition::new(1, 0),
+            }],
+        };
+        let grid = test_grid(&[(1, 0, 5)]);
+        assert!(constraint.check_number(&grid, 0, 0, 6));
+        assert!(!constraint.check_number(&grid, 0, 0, 4));
+    }
+
+    #[test]
+    fn signs_rule_out_extremes_next_to_empty_cells() {
+        let constraint = CompareConstraint {
+            parity: vec![],
+            signs: vec![Inequality {
+                greater: CellPosition::new(0, 0),
+                lesser: CellPosition::new(1, 0),
+            }],
+        };
+        assert!(!constraint.check_number(&test_grid(&[]), 0, 0, 1));
+        assert!(!constraint.check_number(&test_grid(&[]), 1, 0, 9));
+        assert!(constraint.check_number(&test_grid(&[]), 1, 0, 8));
+    }
+}
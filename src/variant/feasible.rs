@@ -0,0 +1,130 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    assist::{candidates, Grid},
+    board::{CellPosition, Value},
+};
+
+use super::VariantRules;
+
+/// Guesses allowed before giving up on a set of rules
+pub const SEARCH_BUDGET: usize = 50_000;
+
+/// Whether any full grid keeps a set of rules, found by a bounded search
+#[derive(Debug, Clone)]
+pub enum Feasibility {
+    Solvable(Grid),
+    /// Every grid was ruled out
+    Infeasible,
+    /// The search ran out of budget before deciding
+    Unknown,
+}
+
+impl Feasibility {
+    /// Why no grid could be used, None when one was found
+    pub fn problem(&self) -> Option<&'static str> {
+        match self {
+            Feasibility::Solvable(_) => None,
+            Feasibility::Infeasible => Some("No grid fits these rules together"),
+            Feasibility::Unknown => Some("Couldn't find a grid for these rules, try fewer"),
+        }
+    }
+}
+
+/// A random full grid keeping the rules, cells with the fewest candidates first
+pub fn find_solution(rules: &VariantRules, rng: &mut impl Rng, budget: usize) -> Feasibility {
+    let mut grid = Grid::default();
+    let mut budget = budget;
+    match fill(&mut grid, rules, rng, &mut budget) {
+        Some(true) => Feasibility::Solvable(grid),
+        Some(false) => Feasibility::Infeasible,
+        None => Feasibility::Unknown,
+    }
+}
+
+/// None once the budget runs out
+fn fill(
+    grid: &mut Grid,
+    rules: &VariantRules,
+    rng: &mut impl Rng,
+    budget: &mut usize,
+) -> Option<bool> {
+    if *budget == 0 {
+        return None;
+    }
+    *budget -= 1;
+
+    let (pos, mut values) = match next_cell(grid, rules) {
+        Some(next) => next,
+        None => return Some(true),
+    };
+
+    values.shuffle(rng);
+    for value in values {
+        grid[pos.y][pos.x] = Some(value);
+        if fill(grid, rules, rng, budget)? {
+            return Some(true);
+        }
+    }
+    grid[pos.y][pos.x] = None;
+    Some(false)
+}
+
+/// Up to `limit` solutions of the givens, None once the budget runs out
+pub fn find_solutions(
+    givens: &Grid,
+    rules: &VariantRules,
+    limit: usize,
+    budget: usize,
+) -> Option<Vec<Grid>> {
+    let mut grid = *givens;
+    let mut budget = budget;
+    let mut found = Vec::new();
+    count(&mut grid, rules, limit, &mut budget, &mut found)?;
+    Some(found)
+}
+
+fn count(
+    grid: &mut Grid,
+    rules: &VariantRules,
+    limit: usize,
+    budget: &mut usize,
+    found: &mut Vec<Grid>,
+) -> Option<()> {
+    if *budget == 0 {
+        return None;
+    }
+    *budget -= 1;
+
+    let (pos, values) = match next_cell(grid, rules) {
+        Some(next) => next,
+        None => {
+            found.push(*grid);
+            return Some(());
+        }
+    };
+    for value in values {
+        grid[pos.y][pos.x] = Some(value);
+        count(grid, rules, limit, budget, found)?;
+        if found.len() >= limit {
+            break;
+        }
+    }
+    grid[pos.y][pos.x] = None;
+    Some(())
+}
+
+/// The empty cell with the fewest values the rules allow, None once the grid is full
+fn next_cell(grid: &Grid, rules: &VariantRules) -> Option<(CellPosition, Vec<Value>)> {
+    (0..81)
+        .map(|i| CellPosition::new(i % 9, i / 9))
+        .filter(|pos| grid[pos.y][pos.x].is_none())
+        .map(|pos| {
+            let values = candidates(grid, pos.x, pos.y)
+                .values()
+                .filter(|value| rules.allows(grid, pos, *value))
+                .collect::<Vec<_>>();
+            (pos, values)
+        })
+        .min_by_key(|(_, values)| values.len())
+}
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use sudoku_variants::{
+    constraint::{Constraint, Group},
+    SudokuGrid,
+};
+
+use crate::{annotation::spawn_segment, board::CellPosition, ui::Theme};
+
+const KNIGHT_MOVES: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_DIAGONALS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ORTHOGONALS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Rules over the whole grid, with no clues of their own
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlobalRules {
+    /// Both long diagonals hold distinct values
+    pub diagonal: bool,
+    /// Cells a knight's move apart differ
+    pub anti_knight: bool,
+    /// Cells a king's move apart differ, only the diagonal touches add anything
+    pub anti_king: bool,
+    /// Orthogonal neighbors aren't consecutive
+    pub non_consecutive: bool,
+}
+
+impl GlobalRules {
+    pub fn any(&self) -> bool {
+        self.diagonal || self.anti_knight || self.anti_king || self.non_consecutive
+    }
+}
+
+fn offsets(pos: CellPosition, moves: &[(i32, i32)]) -> impl Iterator<Item = CellPosition> + '_ {
+    moves
+        .iter()
+        .map(move |(dx, dy)| (pos.x as i32 + dx, pos.y as i32 + dy))
+        .filter(|(x, y)| (0..9).contains(x) && (0..9).contains(y))
+        .map(|(x, y)| CellPosition::new(x as usize, y as usize))
+}
+
+fn diagonals() -> [Vec<CellPosition>; 2] {
+    [
+        (0..9).map(|i| CellPosition::new(i, i)).collect(),
+        (0..9).map(|i| CellPosition::new(8 - i, i)).collect(),
+    ]
+}
+
+pub(super) fn global_allow(
+    rules: GlobalRules,
+    pos: CellPosition,
+    number: usize,
+    value_at: &impl Fn(CellPosition) -> Option<usize>,
+) -> bool {
+    let differs = |other: CellPosition| value_at(other) != Some(number);
+    (!rules.diagonal
+        || diagonals()
+            .iter()
+            .filter(|diagonal| diagonal.contains(&pos))
+            .all(|diagonal| {
+                diagonal
+                    .iter()
+                    .all(|other| *other == pos || differs(*other))
+            }))
+        && (!rules.anti_knight || offsets(pos, &KNIGHT_MOVES).all(differs))
+        && (!rules.anti_king || offsets(pos, &KING_DIAGONALS).all(differs))
+        && (!rules.non_consecutive
+            || offsets(pos, &ORTHOGONALS)
+                .all(|other| value_at(other).map_or(true, |value| value.abs_diff(number) != 1)))
+}
+
+/// Diagonal, anti-knight, anti-king and non-consecutive rules
+#[derive(Debug, Clone)]
+pub struct GlobalConstraint {
+    pub(super) rules: GlobalRules,
+}
+
+impl Constraint for GlobalConstraint {
+    fn check_number(&self, grid: &SudokuGrid, column: usize, row: usize, number: usize) -> bool {
+        let value_at = |at: CellPosition| grid.get_cell(at.x, at.y).ok().flatten();
+        global_allow(
+            self.rules,
+            CellPosition::new(column, row),
+            number,
+            &value_at,
+        )
+    }
+
+    fn get_groups(&self, _grid: &SudokuGrid) -> Vec<Group> {
+        if !self.rules.diagonal {
+            return Vec::new();
+        }
+        diagonals()
+            .iter()
+            .map(|diagonal| diagonal.iter().map(|pos| (pos.x, pos.y)).collect())
+            .collect()
+    }
+}
+
+/// The diagonals run corner to corner through each cell on them
+pub(super) fn spawn_diagonal_marks(
+    parent: &mut ChildBuilder,
+    rules: GlobalRules,
+    pos: CellPosition,
+    cell_size: f32,
+    theme: &Theme,
+) {
+    if !rules.diagonal {
+        return;
+    }
+    let thickness = (cell_size * 0.03).max(1.0);
+    if pos.x == pos.y {
+        spawn_segment(
+            parent,
+            Vec2::ZERO,
+            Vec2::splat(cell_size),
+            thickness,
+            theme.diagonal,
+        );
+    }
+    if pos.x + pos.y == 8 {
+        spawn_segment(
+            parent,
+            Vec2::new(cell_size, 0.0),
+            Vec2::new(0.0, cell_size),
+            thickness,
+            theme.diagonal,
+        );
+    }
+}
@@ -0,0 +1,508 @@
+use std::{
+    f32::consts::PI,
+    fmt::{Display, Formatter},
+};
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use sudoku_variants::{constraint::Constraint, SudokuGrid};
+
+use crate::{
+    annotation::{spawn_circle, spawn_segment},
+    assist::Grid,
+    board::CellPosition,
+    ui::Theme,
+};
+
+/// Lines added to a generated puzzle
+const LINE_COUNT: usize = 5;
+/// Random walks tried before settling for fewer lines
+const LINE_ATTEMPTS: usize = 200;
+const MIN_LENGTH: usize = 3;
+const MAX_LENGTH: usize = 6;
+/// Smallest difference between neighbors on a whisper
+const WHISPER_GAP: usize = 5;
+
+#[derive(EnumIter, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineKind {
+    /// Values increase from the bulb
+    Thermo,
+    /// Values along the shaft sum to the circle, the first cell
+    Arrow,
+    /// Values read the same from either end
+    Palindrome,
+    /// A set of consecutive values in any order
+    Renban,
+    /// Neighbors differ by at least five
+    Whisper,
+}
+
+impl Display for LineKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineKind::Thermo => write!(f, "Thermo"),
+            LineKind::Arrow => write!(f, "Arrow"),
+            LineKind::Palindrome => write!(f, "Palindrome"),
+            LineKind::Renban => write!(f, "Renban"),
+            LineKind::Whisper => write!(f, "Whisper"),
+        }
+    }
+}
+
+impl LineKind {
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            LineKind::Thermo => theme.thermo,
+            LineKind::Arrow => theme.arrow,
+            LineKind::Palindrome => theme.palindrome,
+            LineKind::Renban => theme.renban,
+            LineKind::Whisper => theme.whisper,
+        }
+    }
+
+    /// Line thickness as a fraction of the cell size
+    fn thickness(self) -> f32 {
+        match self {
+            LineKind::Thermo => 0.25,
+            LineKind::Arrow => 0.06,
+            LineKind::Palindrome | LineKind::Whisper => 0.12,
+            LineKind::Renban => 0.2,
+        }
+    }
+}
+
+/// A line through neighboring cells, in order from its start
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Line {
+    pub kind: LineKind,
+    pub cells: Vec<CellPosition>,
+}
+
+impl Line {
+    /// Whether `number` at `index` fits the values already on the line
+    fn allows(
+        &self,
+        index: usize,
+        number: usize,
+        value_at: &impl Fn(CellPosition) -> Option<usize>,
+    ) -> bool {
+        let len = self.cells.len();
+        let get = |i: usize| {
+            if i == index {
+                Some(number)
+            } else {
+                value_at(self.cells[i])
+            }
+        };
+        let known = (0..len)
+            .filter_map(|i| get(i).map(|value| (i, value)))
+            .collect::<Vec<_>>();
+
+        match self.kind {
+            LineKind::Thermo => {
+                // leave room for the cells before and after
+                if number < index + 1 || number + (len - 1 - index) > 9 {
+                    return false;
+                }
+                known.iter().all(|&(i, value)| {
+                    if i < index {
+                        value + (index - i) <= number
+                    } else {
+                        i == index || number + (i - index) <= value
+                    }
+                })
+            }
+            LineKind::Arrow => {
+                let (sum, unknown) = (1..len).fold((0, 0), |(sum, unknown), i| match get(i) {
+                    Some(value) => (sum + value, unknown),
+                    None => (sum, unknown + 1),
+                });
+                let (min, max) = (sum + unknown, sum + unknown * 9);
+                match get(0) {
+                    Some(circle) => min <= circle && circle <= max,
+                    None => min <= 9,
+                }
+            }
+            LineKind::Palindrome => {
+                let mirror = len - 1 - index;
+                get(mirror).map_or(true, |value| value == number)
+            }
+            LineKind::Renban => {
+                let values = known.iter().map(|(_, value)| *value);
+                let min = values.clone().min().unwrap_or(number);
+                let max = values.max().unwrap_or(number);
+                let repeated = known.iter().any(|&(i, value)| i != index && value == number);
+                !repeated && max - min < len
+            }
+            LineKind::Whisper => {
+                let neighbors = [index.checked_sub(1), Some(index + 1).filter(|i| *i < len)];
+                neighbors
+                    .into_iter()
+                    .flatten()
+                    .filter_map(get)
+                    .all(|value| value.abs_diff(number) >= WHISPER_GAP)
+                    && (len == 1 || number != 5)
+            }
+        }
+    }
+}
+
+pub(super) fn lines_allow(
+    lines: &[Line],
+    pos: CellPosition,
+    number: usize,
+    value_at: &impl Fn(CellPosition) -> Option<usize>,
+) -> bool {
+    lines.iter().all(|line| {
+        line.cells
+            .iter()
+            .position(|at| *at == pos)
+            .map_or(true, |index| line.allows(index, number, value_at))
+    })
+}
+
+/// Thermometers, arrows, palindromes, renban and whispers
+#[derive(Debug, Clone)]
+pub struct LineConstraint {
+    pub(super) lines: Vec<Line>,
+}
+
+impl Constraint for LineConstraint {
+    fn check_number(&self, grid: &SudokuGrid, column: usize, row: usize, number: usize) -> bool {
+        let value_at = |at: CellPosition| grid.get_cell(at.x, at.y).ok().flatten();
+        lines_allow(&self.lines, CellPosition::new(column, row), number, &value_at)
+    }
+}
+
+/// Which lines new games are generated with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineSetting {
+    Off,
+    Only(LineKind),
+    Mixed,
+}
+
+impl Default for LineSetting {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+impl Display for LineSetting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineSetting::Off => write!(f, "Off"),
+            LineSetting::Only(kind) => write!(f, "{kind}"),
+            LineSetting::Mixed => write!(f, "Mixed"),
+        }
+    }
+}
+
+impl LineSetting {
+    pub fn kinds(self) -> Vec<LineKind> {
+        match self {
+            LineSetting::Off => Vec::new(),
+            LineSetting::Only(kind) => vec![kind],
+            LineSetting::Mixed => LineKind::iter().collect(),
+        }
+    }
+
+    /// Off, each kind in turn, then all of them
+    pub fn next(self) -> Self {
+        let kinds = LineKind::iter().collect::<Vec<_>>();
+        match self {
+            LineSetting::Off => LineSetting::Only(kinds[0]),
+            LineSetting::Only(kind) => match kinds.iter().position(|k| *k == kind) {
+                Some(i) if i + 1 < kinds.len() => LineSetting::Only(kinds[i + 1]),
+                _ => LineSetting::Mixed,
+            },
+            LineSetting::Mixed => LineSetting::Off,
+        }
+    }
+}
+
+/// Lines the solved grid already satisfies, walked at random through unused cells
+pub fn random_lines(solution: &Grid, kinds: &[LineKind], rng: &mut impl Rng) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    if kinds.is_empty() {
+        return lines;
+    }
+    let mut used = [[false; 9]; 9];
+    for _ in 0..LINE_ATTEMPTS {
+        if lines.len() >= LINE_COUNT {
+            break;
+        }
+        let kind = *kinds.choose(rng).unwrap();
+        let start = CellPosition::new(rng.gen_range(0..9), rng.gen_range(0..9));
+        if used[start.y][start.x] {
+            continue;
+        }
+        let cells = match kind {
+            LineKind::Arrow => walk_arrow(solution, &used, start, rng),
+            LineKind::Palindrome => walk_palindrome(solution, &used, start, rng),
+            _ => walk_line(kind, solution, &used, start, rng),
+        };
+        if let Some(cells) = cells.filter(|cells| cells.len() >= MIN_LENGTH) {
+            for pos in cells.iter() {
+                used[pos.y][pos.x] = true;
+            }
+            lines.push(Line { kind, cells });
+        }
+    }
+    lines
+}
+
+pub(super) fn value(grid: &Grid, pos: CellPosition) -> usize {
+    grid[pos.y][pos.x].map(usize::from).unwrap_or_default()
+}
+
+/// Unused cells touching `pos`, diagonals included
+fn free_neighbors(
+    used: &[[bool; 9]; 9],
+    path: &[CellPosition],
+    pos: CellPosition,
+) -> Vec<CellPosition> {
+    let mut neighbors = Vec::with_capacity(8);
+    for dy in -1i32..=1 {
+        for dx in -1i32..=1 {
+            let (x, y) = (pos.x as i32 + dx, pos.y as i32 + dy);
+            if (dx, dy) == (0, 0) || !(0..9).contains(&x) || !(0..9).contains(&y) {
+                continue;
+            }
+            let next = CellPosition::new(x as usize, y as usize);
+            if !used[next.y][next.x] && !path.contains(&next) {
+                neighbors.push(next);
+            }
+        }
+    }
+    neighbors
+}
+
+/// Thermos, renban and whispers, each step checked against the cells walked so far
+fn walk_line(
+    kind: LineKind,
+    grid: &Grid,
+    used: &[[bool; 9]; 9],
+    start: CellPosition,
+    rng: &mut impl Rng,
+) -> Option<Vec<CellPosition>> {
+    let target = rng.gen_range(MIN_LENGTH..=MAX_LENGTH);
+    let mut path = vec![start];
+    while path.len() < target {
+        let last = *path.last().unwrap();
+        let fits = |next: &CellPosition| {
+            let (from, to) = (value(grid, last), value(grid, *next));
+            match kind {
+                LineKind::Thermo => to > from,
+                LineKind::Whisper => from.abs_diff(to) >= WHISPER_GAP,
+                _ => {
+                    // renban stays within a run as long as the target length
+                    let values = path.iter().map(|pos| value(grid, *pos));
+                    let min = values.clone().min().unwrap().min(to);
+                    let max = values.max().unwrap().max(to);
+                    !path.iter().any(|pos| value(grid, *pos) == to) && max - min < target
+                }
+            }
+        };
+        let options = free_neighbors(used, &path, last)
+            .into_iter()
+            .filter(fits)
+            .collect::<Vec<_>>();
+        match options.choose(rng) {
+            Some(next) => path.push(*next),
+            None => break,
+        }
+    }
+
+    if kind == LineKind::Renban {
+        // keep the longest start that forms a run
+        while path.len() >= MIN_LENGTH {
+            let values = path.iter().map(|pos| value(grid, *pos));
+            let spread = values.clone().max().unwrap() - values.min().unwrap();
+            if spread == path.len() - 1 {
+                break;
+            }
+            path.pop();
+        }
+    }
+    Some(path)
+}
+
+/// The circle is `start`, the shaft walks on until it adds up
+fn walk_arrow(
+    grid: &Grid,
+    used: &[[bool; 9]; 9],
+    start: CellPosition,
+    rng: &mut impl Rng,
+) -> Option<Vec<CellPosition>> {
+    let circle = value(grid, start);
+    let mut path = vec![start];
+    let mut sum = 0;
+    while sum < circle {
+        let last = *path.last().unwrap();
+        let options = free_neighbors(used, &path, last)
+            .into_iter()
+            .filter(|next| sum + value(grid, *next) <= circle)
+            // a single cell shaft would repeat the circle
+            .filter(|next| path.len() > 1 || value(grid, *next) < circle)
+            .collect::<Vec<_>>();
+        let next = *options.choose(rng)?;
+        sum += value(grid, next);
+        path.push(next);
+    }
+    Some(path)
+}
+
+/// Grows outwards from `start` in the middle, a pair of equal values at a time
+fn walk_palindrome(
+    grid: &Grid,
+    used: &[[bool; 9]; 9],
+    start: CellPosition,
+    rng: &mut impl Rng,
+) -> Option<Vec<CellPosition>> {
+    let target = rng.gen_range(MIN_LENGTH..=MAX_LENGTH + 1);
+    let mut path = vec![start];
+    while path.len() + 2 <= target {
+        let (first, last) = (path[0], *path.last().unwrap());
+        let mut options = Vec::new();
+        for before in free_neighbors(used, &path, first) {
+            for after in free_neighbors(used, &path, last) {
+                if before != after && value(grid, before) == value(grid, after) {
+                    options.push((before, after));
+                }
+            }
+        }
+        match options.choose(rng) {
+            Some((before, after)) => {
+                path.insert(0, *before);
+                path.push(*after);
+            }
+            None => break,
+        }
+    }
+    Some(path)
+}
+
+/// Draw the half of every line segment on this cell's side, overlapping the grid lines to meet its neighbor
+pub(super) fn spawn_line_marks(
+    parent: &mut ChildBuilder,
+    lines: &[Line],
+    pos: CellPosition,
+    cell_size: f32,
+    theme: &Theme,
+) {
+    let center = Vec2::splat(cell_size * 0.5);
+    // reach past the cell edge, over the widest grid line
+    let reach = cell_size * 0.5 + 4.0;
+    let circle_radius = cell_size * 0.4;
+    for line in lines.iter() {
+        let index = match line.cells.iter().position(|at| *at == pos) {
+            Some(index) => index,
+            None => continue,
+        };
+        let color = line.kind.color(theme);
+        let thickness = (cell_size * line.kind.thickness()).max(2.0);
+        let toward = |other: CellPosition| {
+            Vec2::new(
+                other.x as f32 - pos.x as f32,
+                other.y as f32 - pos.y as f32,
+            )
+        };
+
+        let neighbors = [index.checked_sub(1), Some(index + 1)];
+        for other in neighbors.into_iter().flatten().filter_map(|i| line.cells.get(i)) {
+            let direction = toward(*other);
+            // the arrow shaft starts at the edge of its circle
+            let from = if line.kind == LineKind::Arrow && index == 0 {
+                center + direction.normalize_or_zero() * circle_radius
+            } else {
+                center
+            };
+            spawn_segment(parent, from, center + direction * reach, thickness, color);
+        }
+
+        match line.kind {
+            LineKind::Thermo if index == 0 => {
+                // a thick ring closes up into the bulb
+                let radius = cell_size * 0.35;
+                spawn_circle(parent, center, radius * 0.5, radius, color);
+            }
+            LineKind::Arrow if index == 0 => {
+                spawn_circle(parent, center, circle_radius, thickness, color);
+            }
+            LineKind::Arrow if index + 1 == line.cells.len() => {
+                // two short strokes back from the tip make the head
+                let back = toward(line.cells[index - 1]).normalize_or_zero() * cell_size * 0.3;
+                for angle in [PI / 6.0, -PI / 6.0] {
+                    let side = Vec2::from_angle(angle).rotate(back);
+                    spawn_segment(parent, center, center + side, thickness, color);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::test_grid;
+
+    fn line(kind: LineKind, cells: &[(usize, usize)]) -> LineConstraint {
+        let cells = cells.iter().map(|&(x, y)| CellPosition::new(x, y)).collect();
+        LineConstraint {
+            lines: vec![Line { kind, cells }],
+        }
+    }
+
+    #[test]
+    fn thermo_increases_from_the_bulb() {
+        let thermo = line(LineKind::Thermo, &[(0, 0), (1, 0), (2, 0)]);
+        let grid = test_grid(&[(0, 0, 3)]);
+        assert!(thermo.check_number(&grid, 1, 0, 4));
+        assert!(!thermo.check_number(&grid, 1, 0, 2));
+    }
+
+    #[test]
+    fn arrow_shaft_sums_to_the_circle() {
+        let arrow = line(LineKind::Arrow, &[(0, 0), (1, 0), (2, 0)]);
+        let grid = test_grid(&[(0, 0, 7), (1, 0, 2)]);
+        assert!(arrow.check_number(&grid, 2, 0, 5));
+        assert!(!arrow.check_number(&grid, 2, 0, 6));
+    }
+
+    #[test]
+    fn palindrome_mirrors_its_ends() {
+        let palindrome = line(LineKind::Palindrome, &[(0, 0), (1, 1), (2, 2)]);
+        let grid = test_grid(&[(0, 0, 4)]);
+        assert!(palindrome.check_number(&grid, 2, 2, 4));
+        assert!(!palindrome.check_number(&grid, 2, 2, 5));
+    }
+
+    #[test]
+    fn renban_stays_consecutive() {
+        let renban = line(LineKind::Renban, &[(0, 0), (1, 0), (2, 0)]);
+        let grid = test_grid(&[(0, 0, 5), (1, 0, 6)]);
+        assert!(renban.check_number(&grid, 2, 0, 4));
+        assert!(!renban.check_number(&grid, 2, 0, 8));
+        assert!(!renban.check_number(&grid, 2, 0, 5));
+    }
+
+    #[test]
+    fn whisper_neighbors_differ_by_five() {
+        let whisper = line(LineKind::Whisper, &[(0, 0), (0, 1), (0, 2)]);
+        let grid = test_grid(&[(0, 1, 2)]);
+        assert!(whisper.check_number(&grid, 0, 0, 8));
+        assert!(!whisper.check_number(&grid, 0, 0, 6));
+    }
+
+    #[test]
+    fn cells_off_the_line_are_unconstrained() {
+        let thermo = line(LineKind::Thermo, &[(0, 0), (1, 0)]);
+        let grid = test_grid(&[(0, 0, 9)]);
+        assert!(thermo.check_number(&grid, 4, 4, 1));
+    }
+}
@@ -0,0 +1,214 @@
+mod border;
+mod compare;
+mod feasible;
+mod global;
+mod line;
+mod outside;
+mod region;
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use serde::{Deserialize, Serialize};
+use sudoku_variants::constraint::{CompositeConstraint, DefaultConstraint};
+
+use crate::{
+    assist::Grid,
+    board::{Board, Cell, CellPosition, Value},
+    ui::{FontAssets, Theme},
+};
+
+pub use border::*;
+pub use compare::*;
+pub use feasible::*;
+pub use global::*;
+pub use line::*;
+pub use outside::*;
+pub use region::*;
+
+pub struct VariantPlugin;
+
+impl Plugin for VariantPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VariantRules>()
+            .add_system(render_variant_layers);
+    }
+}
+
+/// The constraint puzzles are solved and generated with, classic rules plus every variant
+pub type PuzzleConstraint = CompositeConstraint<
+    CompositeConstraint<
+        CompositeConstraint<
+            CompositeConstraint<
+                CompositeConstraint<
+                    CompositeConstraint<DefaultConstraint, LineConstraint>,
+                    BorderConstraint,
+                >,
+                OutsideConstraint,
+            >,
+            RegionConstraint,
+        >,
+        CompareConstraint,
+    >,
+    GlobalConstraint,
+>;
+
+/// Resource for the variant rules of the current puzzle, empty for a classic sudoku
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VariantRules {
+    pub lines: Vec<Line>,
+    pub borders: Vec<BorderClue>,
+    pub negative: NegativeBorders,
+    pub outside: Vec<OutsideClue>,
+    pub regions: ExtraRegions,
+    pub parity: Vec<ParityClue>,
+    pub signs: Vec<Inequality>,
+    pub global: GlobalRules,
+}
+
+impl VariantRules {
+    pub fn is_classic(&self) -> bool {
+        self.lines.is_empty()
+            && self.borders.is_empty()
+            && !self.negative.any()
+            && self.outside.is_empty()
+            && !self.regions.any()
+            && self.parity.is_empty()
+            && self.signs.is_empty()
+            && !self.global.any()
+    }
+
+    pub fn constraint(&self) -> PuzzleConstraint {
+        let lines = LineConstraint {
+            lines: self.lines.clone(),
+        };
+        let borders = BorderConstraint {
+            borders: self.borders.clone(),
+            negative: self.negative,
+        };
+        let outside = OutsideConstraint {
+            clues: self.outside.clone(),
+        };
+        let regions = RegionConstraint {
+            regions: self.regions,
+        };
+        let compare = CompareConstraint {
+            parity: self.parity.clone(),
+            signs: self.signs.clone(),
+        };
+        let global = GlobalConstraint { rules: self.global };
+        let composite = CompositeConstraint::new(DefaultConstraint, lines);
+        let composite = CompositeConstraint::new(composite, borders);
+        let composite = CompositeConstraint::new(composite, outside);
+        let composite = CompositeConstraint::new(composite, regions);
+        let composite = CompositeConstraint::new(composite, compare);
+        CompositeConstraint::new(composite, global)
+    }
+
+    /// Whether `value` at `pos` fits every variant clue touching it, peers are left to the caller
+    pub fn allows(&self, grid: &Grid, pos: CellPosition, value: Value) -> bool {
+        let value_at = |at: CellPosition| grid[at.y][at.x].map(usize::from);
+        let number = value.into();
+        lines_allow(&self.lines, pos, number, &value_at)
+            && borders_allow(&self.borders, self.negative, pos, number, &value_at)
+            && outside_allow(&self.outside, pos, number, &value_at)
+            && regions_allow(self.regions, pos, number, &value_at)
+            && compare_allow(&self.parity, &self.signs, pos, number, &value_at)
+            && global_allow(self.global, pos, number, &value_at)
+    }
+
+    /// Whether every filled cell fits the variant clues
+    pub fn is_consistent(&self, grid: &Grid) -> bool {
+        (0..81).all(|i| {
+            let pos = CellPosition::new(i % 9, i / 9);
+            grid[pos.y][pos.x].map_or(true, |value| self.allows(grid, pos, value))
+        })
+    }
+}
+
+/// Per cell node drawing the variant clues on it, between the cell colors and the digits
+#[derive(Component)]
+pub struct VariantLayer(pub CellPosition);
+
+pub fn spawn_variant_layer(parent: &mut ChildBuilder, pos: CellPosition) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(0.0),
+                        top: Val::Px(0.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            VariantLayer(pos),
+        ))
+        .insert(FocusPolicy::Pass);
+}
+
+fn render_variant_layers(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    rules: Res<VariantRules>,
+    layer_query: Query<(Entity, &VariantLayer)>,
+    added_layer_query: Query<(), Added<VariantLayer>>,
+    ring_query: Query<Entity, With<OutsideClueLayer>>,
+    mut board_query: Query<&mut Style, (With<Board>, Without<Cell>)>,
+    resized_query: Query<(), (With<Cell>, Changed<Style>)>,
+    cell_query: Query<&Style, (With<Cell>, Without<Board>)>,
+) {
+    if !rules.is_changed()
+        && !theme.is_changed()
+        && added_layer_query.is_empty()
+        && resized_query.is_empty()
+    {
+        return;
+    }
+    let cell_size = match cell_query.iter().next().map(|style| style.size.width) {
+        Some(Val::Px(size)) => size,
+        _ => return,
+    };
+
+    for (layer, VariantLayer(pos)) in layer_query.iter() {
+        commands.entity(layer).despawn_descendants();
+        commands.entity(layer).with_children(|parent| {
+            spawn_region_shading(parent, rules.regions, *pos, cell_size, &theme);
+            spawn_diagonal_marks(parent, rules.global, *pos, cell_size, &theme);
+            spawn_compare_marks(parent, &rules.parity, &rules.signs, *pos, cell_size, &theme);
+            spawn_line_marks(parent, &rules.lines, *pos, cell_size, &theme);
+            spawn_border_marks(parent, &rules.borders, *pos, cell_size, &theme, &font_assets);
+        });
+    }
+
+    // outside clues get a ring of space around the board
+    let ring = if rules.outside.is_empty() {
+        0.0
+    } else {
+        cell_size * RING_SCALE
+    };
+    for mut style in board_query.iter_mut() {
+        style.margin = UiRect::all(Val::Px(ring));
+    }
+    for layer in ring_query.iter() {
+        commands.entity(layer).despawn_descendants();
+        commands.entity(layer).with_children(|parent| {
+            spawn_outside_clues(parent, &rules.outside, cell_size, &theme, &font_assets);
+        });
+    }
+}
+
+/// Empty grid with the (column, row, value) cells filled in, for the constraint tests
+#[cfg(test)]
+pub(super) fn test_grid(values: &[(usize, usize, usize)]) -> sudoku_variants::SudokuGrid {
+    let mut grid = sudoku_variants::SudokuGrid::new(3, 3).unwrap();
+    for &(x, y, value) in values {
+        grid.set_cell(x, y, value).unwrap();
+    }
+    grid
+}
@@ -0,0 +1,444 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sudoku_variants::{constraint::Constraint, SudokuGrid};
+
+use crate::{
+    annotation::spawn_segment,
+    assist::Grid,
+    board::{cell_center, CellPosition},
+    ui::{FontAssets, Theme},
+};
+
+use super::line::value;
+
+/// Chance a row, column or diagonal is given its clue
+const CLUE_CHANCE: f64 = 0.5;
+/// Clue ring width as a fraction of the cell size
+pub const RING_SCALE: f32 = 0.5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutsideKind {
+    /// Sum of the values between the 1 and the 9
+    Sandwich,
+    /// Values seen from the side, taller ones hiding shorter ones behind them
+    Skyscraper,
+    /// Sum of the first N values, N being the first value
+    XSum,
+    /// Sum along the diagonal, `turn` is its step along the entry edge, 1 for right or down
+    LittleKiller { turn: i32 },
+}
+
+/// A clue outside the grid, for the row or column at `index` read from `side`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutsideClue {
+    pub kind: OutsideKind,
+    pub side: Side,
+    pub index: usize,
+    pub value: usize,
+}
+
+impl OutsideClue {
+    /// Step from one cell to the next, walking away from the side
+    fn step(&self) -> (i32, i32) {
+        let (dx, dy) = match self.side {
+            Side::Top => (0, 1),
+            Side::Bottom => (0, -1),
+            Side::Left => (1, 0),
+            Side::Right => (-1, 0),
+        };
+        match self.kind {
+            // diagonals always move sideways along the edge they enter from
+            OutsideKind::LittleKiller { turn } => match self.side {
+                Side::Top | Side::Bottom => (turn, dy),
+                Side::Left | Side::Right => (dx, turn),
+            },
+            _ => (dx, dy),
+        }
+    }
+
+    /// First cell read, on the edge next to the clue
+    fn entry(&self) -> CellPosition {
+        match self.side {
+            Side::Top => CellPosition::new(self.index, 0),
+            Side::Bottom => CellPosition::new(self.index, 8),
+            Side::Left => CellPosition::new(0, self.index),
+            Side::Right => CellPosition::new(8, self.index),
+        }
+    }
+
+    /// Cells the clue reads, in order from its side
+    pub fn cells(&self) -> Vec<CellPosition> {
+        let (dx, dy) = self.step();
+        let entry = self.entry();
+        let (mut x, mut y) = (entry.x as i32, entry.y as i32);
+        let mut cells = Vec::with_capacity(9);
+        while (0..9).contains(&x) && (0..9).contains(&y) {
+            cells.push(CellPosition::new(x as usize, y as usize));
+            x += dx;
+            y += dy;
+        }
+        cells
+    }
+
+    /// Whether the values read so far, None for empty cells, can still meet the clue
+    fn allows(&self, values: &[Option<usize>]) -> bool {
+        let complete = values.iter().all(Option::is_some);
+        let sum_range = |values: &[Option<usize>], low: usize, high: usize| {
+            let (sum, unknown) = values.iter().fold((0, 0), |(sum, unknown), v| match v {
+                Some(v) => (sum + v, unknown),
+                None => (sum, unknown + 1),
+            });
+            (sum + unknown * low, sum + unknown * high)
+        };
+        match self.kind {
+            OutsideKind::Sandwich => {
+                let one = values.iter().position(|v| *v == Some(1));
+                let nine = values.iter().position(|v| *v == Some(9));
+                match (one, nine) {
+                    (Some(a), Some(b)) => {
+                        let between = &values[a.min(b) + 1..a.max(b)];
+                        let (min, max) = sum_range(between, 2, 8);
+                        min <= self.value && self.value <= max
+                    }
+                    _ => !complete,
+                }
+            }
+            OutsideKind::Skyscraper => {
+                // the 9 hides everything behind it
+                if let Some(nine) = values.iter().position(|v| *v == Some(9)) {
+                    if self.value > nine + 1 {
+                        return false;
+                    }
+                }
+                let mut tallest = 0;
+                let mut seen = 0;
+                for value in values.iter() {
+                    match value {
+                        Some(value) if *value > tallest => {
+                            tallest = *value;
+                            seen += 1;
+                        }
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+                if complete {
+                    seen == self.value
+                } else {
+                    seen <= self.value
+                }
+            }
+            OutsideKind::XSum => match values.first().copied().flatten() {
+                Some(first) => {
+                    let (min, max) = sum_range(&values[..first.min(values.len())], 1, 9);
+                    min <= self.value && self.value <= max
+                }
+                None => true,
+            },
+            OutsideKind::LittleKiller { .. } => {
+                let (min, max) = sum_range(values, 1, 9);
+                min <= self.value && self.value <= max
+            }
+        }
+    }
+
+    /// The clue a solved grid gives, None when it has no sandwich or diagonal to read
+    fn read(kind: OutsideKind, side: Side, index: usize, solution: &Grid) -> Option<Self> {
+        let mut clue = Self {
+            kind,
+            side,
+            index,
+            value: 0,
+        };
+        let values = clue
+            .cells()
+            .iter()
+            .map(|pos| value(solution, *pos))
+            .collect::<Vec<_>>();
+        clue.value = match kind {
+            OutsideKind::Sandwich => {
+                let one = values.iter().position(|v| *v == 1)?;
+                let nine = values.iter().position(|v| *v == 9)?;
+                values[one.min(nine) + 1..one.max(nine)].iter().sum()
+            }
+            OutsideKind::Skyscraper => {
+                let mut tallest = 0;
+                values
+                    .iter()
+                    .filter(|v| {
+                        let taller = **v > tallest;
+                        tallest = tallest.max(**v);
+                        taller
+                    })
+                    .count()
+            }
+            OutsideKind::XSum => values.iter().take(values[0]).sum(),
+            OutsideKind::LittleKiller { .. } if values.len() < 2 => return None,
+            OutsideKind::LittleKiller { .. } => values.iter().sum(),
+        };
+        Some(clue)
+    }
+}
+
+pub(super) fn outside_allow(
+    clues: &[OutsideClue],
+    pos: CellPosition,
+    number: usize,
+    value_at: &impl Fn(CellPosition) -> Option<usize>,
+) -> bool {
+    clues.iter().all(|clue| {
+        let cells = clue.cells();
+        if !cells.contains(&pos) {
+            return true;
+        }
+        let values = cells
+            .iter()
+            .map(|at| if *at == pos { Some(number) } else { value_at(*at) })
+            .collect::<Vec<_>>();
+        clue.allows(&values)
+    })
+}
+
+/// Sandwich sums, skyscrapers, X-sums and little killers
+#[derive(Debug, Clone)]
+pub struct OutsideConstraint {
+    pub(super) clues: Vec<OutsideClue>,
+}
+
+impl Constraint for OutsideConstraint {
+    fn check_number(&self, grid: &SudokuGrid, column: usize, row: usize, number: usize) -> bool {
+        let value_at = |at: CellPosition| grid.get_cell(at.x, at.y).ok().flatten();
+        outside_allow(&self.clues, CellPosition::new(column, row), number, &value_at)
+    }
+}
+
+/// Which outside clues new games are generated with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutsideSetting {
+    Off,
+    Sandwich,
+    LittleKiller,
+    Skyscraper,
+    XSum,
+}
+
+impl Default for OutsideSetting {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+impl Display for OutsideSetting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutsideSetting::Off => write!(f, "Off"),
+            OutsideSetting::Sandwich => write!(f, "Sandwich"),
+            OutsideSetting::LittleKiller => write!(f, "Little Killer"),
+            OutsideSetting::Skyscraper => write!(f, "Skyscraper"),
+            OutsideSetting::XSum => write!(f, "X-Sums"),
+        }
+    }
+}
+
+impl OutsideSetting {
+    pub fn next(self) -> Self {
+        match self {
+            OutsideSetting::Off => OutsideSetting::Sandwich,
+            OutsideSetting::Sandwich => OutsideSetting::LittleKiller,
+            OutsideSetting::LittleKiller => OutsideSetting::Skyscraper,
+            OutsideSetting::Skyscraper => OutsideSetting::XSum,
+            OutsideSetting::XSum => OutsideSetting::Off,
+        }
+    }
+}
+
+/// Clues read off the solved grid for a random choice of rows, columns or diagonals
+pub fn random_outside_clues(
+    solution: &Grid,
+    setting: OutsideSetting,
+    rng: &mut impl Rng,
+) -> Vec<OutsideClue> {
+    let sides = [Side::Top, Side::Bottom, Side::Left, Side::Right];
+    let candidates: Vec<(OutsideKind, Side, usize)> = match setting {
+        OutsideSetting::Off => Vec::new(),
+        // the sandwich from the other side is the same sum
+        OutsideSetting::Sandwich => [Side::Top, Side::Left]
+            .into_iter()
+            .flat_map(|side| (0..9).map(move |i| (OutsideKind::Sandwich, side, i)))
+            .collect(),
+        // every diagonal read from its top end
+        OutsideSetting::LittleKiller => (0..9)
+            .flat_map(|i| {
+                let top = [-1, 1].map(|turn| (OutsideKind::LittleKiller { turn }, Side::Top, i));
+                let edges = [Side::Left, Side::Right]
+                    .map(|side| (OutsideKind::LittleKiller { turn: 1 }, side, i));
+                top.into_iter().chain(edges.into_iter().filter(|(_, _, i)| *i > 0))
+            })
+            .collect(),
+        OutsideSetting::Skyscraper | OutsideSetting::XSum => {
+            let kind = if setting == OutsideSetting::Skyscraper {
+                OutsideKind::Skyscraper
+            } else {
+                OutsideKind::XSum
+            };
+            sides
+                .into_iter()
+                .flat_map(|side| (0..9).map(move |i| (kind, side, i)))
+                .collect()
+        }
+    };
+    candidates
+        .into_iter()
+        .filter(|_| rng.gen_bool(CLUE_CHANCE))
+        .filter_map(|(kind, side, index)| OutsideClue::read(kind, side, index, solution))
+        .collect()
+}
+
+/// Holds the clue labels, positioned around the board in the margin the ring makes room for
+#[derive(Component)]
+pub struct OutsideClueLayer;
+
+pub fn spawn_outside_clue_layer(parent: &mut ChildBuilder) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(0.0),
+                        top: Val::Px(0.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            Name::new("Outside Clues"),
+            OutsideClueLayer,
+        ))
+        .insert(FocusPolicy::Pass);
+}
+
+pub(super) fn spawn_outside_clues(
+    parent: &mut ChildBuilder,
+    clues: &[OutsideClue],
+    cell_size: f32,
+    theme: &Theme,
+    font_assets: &FontAssets,
+) {
+    let ring = cell_size * RING_SCALE;
+    for clue in clues.iter() {
+        let (dx, dy) = clue.step();
+        let step = Vec2::new(dx as f32, dy as f32);
+        // one step back from the entry cell, pulled in to sit inside the ring
+        let edge = cell_center(clue.entry(), cell_size);
+        let outward = match clue.side {
+            Side::Top => Vec2::new(0.0, -1.0),
+            Side::Bottom => Vec2::new(0.0, 1.0),
+            Side::Left => Vec2::new(-1.0, 0.0),
+            Side::Right => Vec2::new(1.0, 0.0),
+        };
+        let mut center = edge + outward * (cell_size * 0.5 + 4.0 + ring * 0.5);
+        if let OutsideKind::LittleKiller { .. } = clue.kind {
+            let sideways = step + outward;
+            center -= sideways * cell_size * 0.5;
+            // a short stroke shows which diagonal it sums
+            let tip = center + step.normalize() * ring * 0.7;
+            spawn_segment(parent, center + step.normalize() * ring * 0.4, tip, 2.0, theme.text);
+        }
+
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(center.x - ring * 0.5),
+                        top: Val::Px(center.y - ring * 0.5),
+                        ..default()
+                    },
+                    size: Size::new(Val::Px(ring), Val::Px(ring)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            })
+            .insert(FocusPolicy::Pass)
+            .with_children(|parent| {
+                parent.spawn(TextBundle {
+                    text: Text::from_section(
+                        clue.value.to_string(),
+                        TextStyle {
+                            font: font_assets.ui_font.clone(),
+                            font_size: ring * 0.8,
+                            color: theme.text,
+                        },
+                    ),
+                    ..default()
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::test_grid;
+
+    fn constraint(kind: OutsideKind, side: Side, index: usize, value: usize) -> OutsideConstraint {
+        OutsideConstraint {
+            clues: vec![OutsideClue {
+                kind,
+                side,
+                index,
+                value,
+            }],
+        }
+    }
+
+    #[test]
+    fn sandwich_sums_between_one_and_nine() {
+        let sandwich = constraint(OutsideKind::Sandwich, Side::Top, 0, 5);
+        let grid = test_grid(&[(0, 0, 1), (0, 2, 9)]);
+        assert!(sandwich.check_number(&grid, 0, 1, 5));
+        assert!(!sandwich.check_number(&grid, 0, 1, 4));
+    }
+
+    #[test]
+    fn skyscraper_nine_hides_the_rest() {
+        let skyscraper = constraint(OutsideKind::Skyscraper, Side::Left, 0, 2);
+        let grid = test_grid(&[]);
+        assert!(skyscraper.check_number(&grid, 0, 0, 8));
+        assert!(!skyscraper.check_number(&grid, 0, 0, 9));
+    }
+
+    #[test]
+    fn x_sum_counts_the_first_value_cells() {
+        let x_sum = constraint(OutsideKind::XSum, Side::Top, 0, 6);
+        let grid = test_grid(&[(0, 0, 3), (0, 1, 1)]);
+        assert!(x_sum.check_number(&grid, 0, 2, 2));
+        assert!(!x_sum.check_number(&grid, 0, 2, 4));
+    }
+
+    #[test]
+    fn little_killer_sums_the_diagonal() {
+        let killer = constraint(OutsideKind::LittleKiller { turn: 1 }, Side::Top, 6, 10);
+        let grid = test_grid(&[(6, 0, 2), (7, 1, 3)]);
+        assert!(killer.check_number(&grid, 8, 2, 5));
+        assert!(!killer.check_number(&grid, 8, 2, 6));
+    }
+}
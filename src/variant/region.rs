@@ -0,0 +1,204 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use serde::{Deserialize, Serialize};
+use sudoku_variants::{
+    constraint::{Constraint, Group},
+    SudokuGrid,
+};
+
+use crate::{board::CellPosition, ui::Theme};
+
+/// Top left cell of each Windoku window, one cell in from the boxes around it
+const WINDOWS: [(usize, usize); 4] = [(1, 1), (5, 1), (1, 5), (5, 5)];
+
+/// Regions besides rows, columns and boxes that must hold distinct values
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtraRegions {
+    /// Four shaded 3x3 windows
+    pub windoku: bool,
+    /// Cells at the same spot in each box
+    pub disjoint: bool,
+}
+
+impl ExtraRegions {
+    pub fn any(&self) -> bool {
+        self.windoku || self.disjoint
+    }
+
+    /// Cells of each extra region, the Windoku windows first
+    pub fn regions(self) -> Vec<Vec<CellPosition>> {
+        let mut regions = Vec::new();
+        if self.windoku {
+            for (left, top) in WINDOWS {
+                regions.push(
+                    (0..9)
+                        .map(|i| CellPosition::new(left + i % 3, top + i / 3))
+                        .collect(),
+                );
+            }
+        }
+        if self.disjoint {
+            for spot in 0..9 {
+                regions.push(
+                    (0..9)
+                        .map(|b| CellPosition::new(b % 3 * 3 + spot % 3, b / 3 * 3 + spot / 3))
+                        .collect(),
+                );
+            }
+        }
+        regions
+    }
+}
+
+fn in_window(pos: CellPosition) -> bool {
+    WINDOWS
+        .iter()
+        .any(|(left, top)| (*left..left + 3).contains(&pos.x) && (*top..top + 3).contains(&pos.y))
+}
+
+pub(super) fn regions_allow(
+    regions: ExtraRegions,
+    pos: CellPosition,
+    number: usize,
+    value_at: &impl Fn(CellPosition) -> Option<usize>,
+) -> bool {
+    regions
+        .regions()
+        .iter()
+        .filter(|region| region.contains(&pos))
+        .all(|region| {
+            region
+                .iter()
+                .all(|other| *other == pos || value_at(*other) != Some(number))
+        })
+}
+
+/// Windoku windows and disjoint groups
+#[derive(Debug, Clone)]
+pub struct RegionConstraint {
+    pub(super) regions: ExtraRegions,
+}
+
+impl Constraint for RegionConstraint {
+    fn check_number(&self, grid: &SudokuGrid, column: usize, row: usize, number: usize) -> bool {
+        let value_at = |at: CellPosition| grid.get_cell(at.x, at.y).ok().flatten();
+        regions_allow(
+            self.regions,
+            CellPosition::new(column, row),
+            number,
+            &value_at,
+        )
+    }
+
+    fn get_groups(&self, _grid: &SudokuGrid) -> Vec<Group> {
+        self.regions
+            .regions()
+            .into_iter()
+            .map(|region| region.iter().map(|pos| (pos.x, pos.y)).collect())
+            .collect()
+    }
+}
+
+/// Which extra regions new games are generated with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegionSetting {
+    Off,
+    Windoku,
+    Disjoint,
+    Both,
+}
+
+impl Default for RegionSetting {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+impl Display for RegionSetting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegionSetting::Off => write!(f, "Off"),
+            RegionSetting::Windoku => write!(f, "Windoku"),
+            RegionSetting::Disjoint => write!(f, "Disjoint"),
+            RegionSetting::Both => write!(f, "Both"),
+        }
+    }
+}
+
+impl RegionSetting {
+    pub fn next(self) -> Self {
+        match self {
+            RegionSetting::Off => RegionSetting::Windoku,
+            RegionSetting::Windoku => RegionSetting::Disjoint,
+            RegionSetting::Disjoint => RegionSetting::Both,
+            RegionSetting::Both => RegionSetting::Off,
+        }
+    }
+
+    /// The setting giving exactly these regions
+    pub fn from_regions(regions: ExtraRegions) -> Self {
+        match (regions.windoku, regions.disjoint) {
+            (false, false) => RegionSetting::Off,
+            (true, false) => RegionSetting::Windoku,
+            (false, true) => RegionSetting::Disjoint,
+            (true, true) => RegionSetting::Both,
+        }
+    }
+
+    pub fn regions(self) -> ExtraRegions {
+        ExtraRegions {
+            windoku: matches!(self, RegionSetting::Windoku | RegionSetting::Both),
+            disjoint: matches!(self, RegionSetting::Disjoint | RegionSetting::Both),
+        }
+    }
+}
+
+/// Shade Windoku cells, and mark each cell's disjoint group with a dot at its spot in the box
+pub(super) fn spawn_region_shading(
+    parent: &mut ChildBuilder,
+    regions: ExtraRegions,
+    pos: CellPosition,
+    cell_size: f32,
+    theme: &Theme,
+) {
+    if regions.windoku && in_window(pos) {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(0.0),
+                        top: Val::Px(0.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    ..default()
+                },
+                background_color: theme.windoku.into(),
+                ..default()
+            })
+            .insert(FocusPolicy::Pass);
+    }
+    if regions.disjoint {
+        let size = cell_size * 0.12;
+        let step = (cell_size - size) * 0.5;
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px((pos.x % 3) as f32 * step),
+                        top: Val::Px((pos.y % 3) as f32 * step),
+                        ..default()
+                    },
+                    size: Size::new(Val::Px(size), Val::Px(size)),
+                    ..default()
+                },
+                background_color: theme.disjoint.into(),
+                ..default()
+            })
+            .insert(FocusPolicy::Pass);
+    }
+}
@@ -0,0 +1,413 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_tweening::*;
+
+use crate::{
+    assist::{candidates, fill_candidates, grid_values, is_peer, Grid},
+    board::{BoardSelected, Cell, CellPosition, Notes, Value, ValueEntered, ValueOrigin},
+    events::{NewGame, Reset, Solve},
+    game::{AssistUsage, GameStatus},
+    history::{BoardSnapshot, History},
+    lens::BackgroundColorLens,
+    logic::solve_path,
+    ui::{FontAssets, Theme, ThickLine},
+    variant::VariantRules,
+};
+
+/// Frames per second for each speed setting
+const SPEEDS: [f32; 6] = [1.0, 2.0, 5.0, 10.0, 25.0, 60.0];
+const DEFAULT_SPEED: usize = 2;
+/// Give up on watching a brute force search longer than this
+const MAX_FRAMES: usize = 5000;
+
+pub struct WatchPlugin;
+
+impl Plugin for WatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WatchSolveStart>()
+            .init_resource::<WatchSolve>()
+            .add_system(watch_solve_events)
+            .add_system(watch_button_system)
+            .add_system(play_watch_solve.after(watch_button_system));
+    }
+}
+
+/// Event to solve the board one step at a time instead of all at once
+pub struct WatchSolveStart;
+
+/// One thing the solver does to the board
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SolveFrame {
+    Place(CellPosition, Value),
+    Eliminate(CellPosition, Value),
+    /// A brute force guess didn't work out and is taken back
+    Backtrack(CellPosition),
+}
+
+/// Whether no filled cell repeats a value in its row, column or box, or breaks a variant rule
+fn is_consistent(grid: &Grid, rules: &VariantRules) -> bool {
+    rules.is_consistent(grid)
+        && (0..81).all(|a| {
+            let (ax, ay) = (a % 9, a / 9);
+            grid[ay][ax].is_none()
+                || (0..81).all(|b| {
+                    let (bx, by) = (b % 9, b / 9);
+                    !is_peer((ax, ay), (bx, by)) || grid[by][bx] != grid[ay][ax]
+                })
+        })
+}
+
+/// Fill the grid by guessing, cells with the fewest candidates first
+fn backtrack(grid: &mut Grid, rules: &VariantRules, frames: &mut Vec<SolveFrame>) -> bool {
+    if frames.len() > MAX_FRAMES {
+        return false;
+    }
+    let next = (0..9)
+        .flat_map(|y| (0..9).map(move |x| (x, y)))
+        .filter(|(x, y)| grid[*y][*x].is_none())
+        .map(|(x, y)| (x, y, candidates(grid, x, y)))
+        .min_by_key(|(_, _, notes)| notes.len());
+    let (x, y, notes) = match next {
+        Some(next) => next,
+        None => return true,
+    };
+    let pos = CellPosition::new(x, y);
+    for value in notes.values() {
+        if !rules.allows(grid, pos, value) {
+            continue;
+        }
+        grid[y][x] = Some(value);
+        frames.push(SolveFrame::Place(pos, value));
+        if backtrack(grid, rules, frames) {
+            return true;
+        }
+        grid[y][x] = None;
+        frames.push(SolveFrame::Backtrack(pos));
+    }
+    false
+}
+
+/// Frames solving the grid, logical steps first and brute force for the rest, None if it can't be solved
+pub fn solve_frames(grid: &Grid, rules: &VariantRules) -> Option<Vec<SolveFrame>> {
+    if !is_consistent(grid, rules) {
+        return None;
+    }
+    let mut grid = *grid;
+    let mut frames = Vec::new();
+    for step in solve_path(&grid, rules).steps {
+        for (pos, value) in step.eliminations {
+            frames.push(SolveFrame::Eliminate(pos, value));
+        }
+        if let Some((pos, value)) = step.placement {
+            grid[pos.y][pos.x] = Some(value);
+            frames.push(SolveFrame::Place(pos, value));
+        }
+    }
+    backtrack(&mut grid, rules, &mut frames).then_some(frames)
+}
+
+/// Steps through frames at an adjustable speed
+pub struct Playback {
+    frames: Vec<SolveFrame>,
+    next: usize,
+    timer: Timer,
+    speed: usize,
+    pub paused: bool,
+}
+
+impl Playback {
+    pub fn new(frames: Vec<SolveFrame>) -> Self {
+        Self {
+            frames,
+            next: 0,
+            timer: Timer::from_seconds(1.0 / SPEEDS[DEFAULT_SPEED], TimerMode::Repeating),
+            speed: DEFAULT_SPEED,
+            paused: false,
+        }
+    }
+
+    /// Frames to show after `delta` has passed
+    pub fn due(&mut self, delta: Duration) -> Vec<SolveFrame> {
+        if self.paused || self.is_done() {
+            return Vec::new();
+        }
+        self.timer.tick(delta);
+        let end = (self.next + self.timer.times_finished_this_tick() as usize).min(self.frames.len());
+        let due = self.frames[self.next..end].to_vec();
+        self.next = end;
+        due
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.frames.len()
+    }
+
+    /// Step the speed up or down, staying within [`SPEEDS`]
+    pub fn change_speed(&mut self, faster: bool) {
+        self.speed = if faster {
+            (self.speed + 1).min(SPEEDS.len() - 1)
+        } else {
+            self.speed.saturating_sub(1)
+        };
+        self.timer.set_duration(Duration::from_secs_f32(1.0 / SPEEDS[self.speed]));
+    }
+
+    pub fn label(&self) -> String {
+        if self.paused {
+            "Paused".to_string()
+        } else {
+            format!("{}/s", SPEEDS[self.speed])
+        }
+    }
+}
+
+/// Resource for the solve being watched on the board
+#[derive(Resource, Default)]
+pub struct WatchSolve {
+    pub playback: Option<Playback>,
+    /// Board before the solve started, so it can be undone as one move
+    before: Option<BoardSnapshot>,
+    stop: bool,
+}
+
+#[derive(Component)]
+struct WatchPanel;
+
+#[derive(Component)]
+struct WatchStatusText;
+
+#[derive(Component, Debug, Copy, Clone)]
+enum WatchButton {
+    Pause,
+    Slower,
+    Faster,
+    Stop,
+}
+
+/// Fade a cell's background from `color` back to normal
+pub fn flash(commands: &mut Commands, entity: Entity, color: Color, theme: &Theme) {
+    commands.entity(entity).insert(Animator::new(Tween::new(
+        EaseFunction::QuadraticOut,
+        Duration::from_secs_f32(0.4),
+        BackgroundColorLens {
+            start: color,
+            end: theme.btn_normal,
+        },
+    )));
+}
+
+fn watch_solve_events(
+    mut commands: Commands,
+    mut watch_events: EventReader<WatchSolveStart>,
+    status: Res<GameStatus>,
+    theme: Res<Theme>,
+    font_assets: Res<FontAssets>,
+    mut watch: ResMut<WatchSolve>,
+    mut assists: ResMut<AssistUsage>,
+    mut solve_event: EventWriter<Solve>,
+    rules: Res<VariantRules>,
+    mut query: Query<(&mut Cell, &CellPosition)>,
+) {
+    for _ in watch_events.iter() {
+        if *status != GameStatus::Playing || watch.playback.is_some() {
+            continue;
+        }
+        match solve_frames(&grid_values(query.iter()), &rules) {
+            Some(frames) => {
+                assists.solves += 1;
+                watch.before = Some(BoardSnapshot::new(query.iter()));
+                watch.stop = false;
+                // start from every candidate so eliminations have something to remove
+                fill_candidates(&mut query);
+                let playback = Playback::new(frames);
+                spawn_watch_panel(&mut commands, &theme, &font_assets, playback.label());
+                watch.playback = Some(playback);
+            }
+            // the instant solve reports why the board can't be solved
+            None => solve_event.send(Solve),
+        }
+    }
+}
+
+fn spawn_watch_panel(commands: &mut Commands, theme: &Theme, font_assets: &FontAssets, label: String) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(20.0),
+                        right: Val::Px(190.0),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                background_color: theme.line_thick.into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            ThickLine,
+            Name::new("Watch Solve"),
+            WatchPanel,
+        ))
+        .with_children(|parent| {
+            for (button, label) in [
+                (WatchButton::Pause, "Pause"),
+                (WatchButton::Slower, "Slower"),
+                (WatchButton::Faster, "Faster"),
+                (WatchButton::Stop, "Stop"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(100.0), Val::Px(40.0)),
+                                margin: UiRect::all(Val::Px(2.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: theme.btn_normal.into(),
+                            ..default()
+                        },
+                        Name::new(label),
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: font_assets.status(label, theme),
+                            ..default()
+                        });
+                    });
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(100.0), Val::Px(40.0)),
+                        margin: UiRect::all(Val::Px(2.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: theme.btn_normal.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle {
+                            text: font_assets.status(label, theme),
+                            ..default()
+                        },
+                        WatchStatusText,
+                    ));
+                });
+        });
+}
+
+fn watch_button_system(
+    interaction_query: Query<(&Interaction, &WatchButton), (Changed<Interaction>, With<Button>)>,
+    mut watch: ResMut<WatchSolve>,
+    mut text_query: Query<&mut Text, With<WatchStatusText>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        if let WatchButton::Stop = button {
+            watch.stop = true;
+            continue;
+        }
+        if let Some(playback) = watch.playback.as_mut() {
+            match button {
+                WatchButton::Pause => playback.paused = !playback.paused,
+                WatchButton::Slower => playback.change_speed(false),
+                WatchButton::Faster => playback.change_speed(true),
+                WatchButton::Stop => {}
+            }
+            for mut text in text_query.iter_mut() {
+                text.sections[0].value = playback.label();
+            }
+        }
+    }
+}
+
+/// Show the frames that are due, and finish when they run out or the player steps in
+fn play_watch_solve(
+    mut commands: Commands,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    board_selected: Res<BoardSelected>,
+    mut value_entered: EventReader<ValueEntered>,
+    mut new_game: EventReader<NewGame>,
+    mut reset_event: EventReader<Reset>,
+    mut watch: ResMut<WatchSolve>,
+    mut history: ResMut<History>,
+    panel_query: Query<Entity, With<WatchPanel>>,
+    mut query: Query<(Entity, &mut Cell, &CellPosition)>,
+) {
+    // a new board, or leaving the board, leaves nothing to record the solve against
+    let dropped =
+        new_game.iter().count() > 0 || reset_event.iter().count() > 0 || query.is_empty();
+    let interrupted = value_entered.iter().count() > 0;
+    let playback = match watch.playback.as_mut() {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    for frame in playback.due(time.delta()) {
+        for (entity, mut cell, pos) in query.iter_mut() {
+            let flash_color = match frame {
+                SolveFrame::Place(at, value) if at == *pos => {
+                    cell.set_value(value, ValueOrigin::Solver);
+                    Some(theme.logic_pattern)
+                }
+                SolveFrame::Place(at, value) if is_peer((at.x, at.y), (pos.x, pos.y)) => {
+                    remove_note(&mut cell, value);
+                    None
+                }
+                SolveFrame::Eliminate(at, value) if at == *pos => {
+                    remove_note(&mut cell, value);
+                    Some(theme.logic_elimination)
+                }
+                SolveFrame::Backtrack(at) if at == *pos => {
+                    cell.clear_value();
+                    Some(theme.text_error)
+                }
+                _ => None,
+            };
+            if let Some(color) = flash_color {
+                if board_selected.entity != Some(entity) {
+                    flash(&mut commands, entity, color, &theme);
+                }
+            }
+        }
+    }
+
+    let done = playback.is_done();
+    if !done && !watch.stop && !dropped && !interrupted {
+        return;
+    }
+    if let Some(before) = watch.before.take() {
+        if !dropped {
+            let after = BoardSnapshot::new(query.iter().map(|(_, cell, pos)| (cell, pos)));
+            history.record(&before, &after);
+        }
+    }
+    watch.playback = None;
+    watch.stop = false;
+    for panel in panel_query.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+}
+
+fn remove_note(cell: &mut Cell, value: Value) {
+    let mut notes: Notes = cell.notes();
+    if notes.contains(value) {
+        notes.remove(value);
+        cell.set_notes(notes);
+    }
+}